@@ -7,19 +7,320 @@ extern crate regex;
 
 use etrace::some_or;
 use itertools::Itertools;
+use object::read::archive::ArchiveFile;
+use object::{Object, ObjectSection, ObjectSymbol, SymbolKind};
 use regex::Regex;
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::fs::*;
+use std::hash::{Hash, Hasher};
 use std::io::*;
 use std::path::*;
 use std::process::Command;
+use std::sync::Mutex;
 
 /// We make additional wrapper functions for existing bindings.
 /// To avoid collision, we add a magic prefix for each.
 static PREFIX: &str = "prefix_8a9f682d_";
 
+/// DPDK library families which can be selected by cargo features.
+///
+/// Each entry consists of a feature name, libraries of the family (`librte_<name>`), and name
+/// prefixes of its headers (see `prefix_matches`). Libraries and headers not belonging to any
+/// family (e.g. EAL, ring, mempool, mbuf) are always used.
+static LIBRARY_FAMILIES: &[(&str, &[&str], &[&str])] = &[
+    (
+        "ethdev",
+        &[
+            "ethdev",
+            "gro",
+            "gso",
+            "ip_frag",
+            "kni",
+            "latencystats",
+            "bitratestats",
+            "pdump",
+            "bpf",
+        ],
+        &[
+            "rte_ethdev",
+            "rte_eth_ctrl",
+            "rte_dev_info",
+            "rte_flow",
+            "rte_mtr",
+            "rte_tm",
+            "rte_gro",
+            "rte_gso",
+            "rte_ip_frag",
+            "rte_kni",
+            "rte_latencystats",
+            "rte_bitrate",
+            "rte_pdump",
+            "rte_bpf",
+        ],
+    ),
+    (
+        "cryptodev",
+        &["cryptodev", "security"],
+        &["rte_crypto", "rte_cryptodev", "rte_security"],
+    ),
+    ("ipsec", &["ipsec"], &["rte_ipsec"]),
+    ("eventdev", &["eventdev"], &["rte_event", "rte_eventdev"]),
+    (
+        "hash",
+        &["hash", "efd", "member"],
+        &[
+            "rte_hash",
+            "rte_jhash",
+            "rte_fbk_hash",
+            "rte_thash",
+            "rte_crc_",
+            "rte_efd",
+            "rte_member",
+        ],
+    ),
+    (
+        "lpm",
+        &["lpm", "rib", "fib"],
+        &[
+            "rte_lpm", "rte_lpm6", "rte_rib", "rte_rib6", "rte_fib", "rte_fib6",
+        ],
+    ),
+    ("acl", &["acl"], &["rte_acl"]),
+    ("sched", &["sched"], &["rte_sched", "rte_red", "rte_approx"]),
+    (
+        "pipeline",
+        &["pipeline", "port", "table", "flow_classify"],
+        &[
+            "rte_pipeline",
+            "rte_port",
+            "rte_table",
+            "rte_swx",
+            "rte_lru",
+            "rte_flow_classify",
+        ],
+    ),
+    ("vhost", &["vhost"], &["rte_vhost", "rte_vdpa"]),
+    (
+        "compressdev",
+        &["compressdev"],
+        &["rte_comp", "rte_compressdev"],
+    ),
+    ("regexdev", &["regexdev"], &["rte_regexdev"]),
+    ("rawdev", &["rawdev"], &["rte_rawdev"]),
+    ("bbdev", &["bbdev"], &["rte_bbdev"]),
+    ("graph", &["graph", "node"], &["rte_graph", "rte_node"]),
+    ("power", &["power"], &["rte_power"]),
+];
+
+/// PMD groups which can be selected by cargo features.
+///
+/// Each entry consists of a feature name, driver classes of the group
+/// (`librte_<class>_<driver>`), and name prefixes of driver-specific headers. Bus, mempool and
+/// common drivers are always linked.
+static PMD_GROUPS: &[(&str, &[&str], &[&str])] = &[
+    // DPDK 19.11 names net drivers `librte_pmd_<driver>`.
+    ("pmd-net", &["net", "pmd"], &["rte_pmd_", "rte_eth_"]),
+    ("pmd-crypto", &["crypto"], &["rte_cryptodev_scheduler"]),
+    ("pmd-event", &["event"], &[]),
+    ("pmd-compress", &["compress"], &[]),
+    ("pmd-regex", &["regex"], &[]),
+    ("pmd-raw", &["raw"], &["rte_ioat_rawdev", "rte_pmd_ntb"]),
+    (
+        "pmd-baseband",
+        &["baseband"],
+        &["rte_pmd_fpga", "rte_acc100"],
+    ),
+    ("pmd-vdpa", &["vdpa"], &[]),
+];
+
+/// Drivers which depend on library families other than their group's.
+static PMD_DEPENDENCIES: &[(&str, &[&str])] = &[
+    ("net_vhost", &["vhost"]),
+    ("net_softnic", &["pipeline", "sched"]),
+];
+
+/// Returns whether the given cargo feature is enabled.
+fn feature_enabled(feature: &str) -> bool {
+    env::var_os(format!(
+        "CARGO_FEATURE_{}",
+        feature.to_uppercase().replace('-', "_")
+    ))
+    .is_some()
+}
+
+/// Returns whether `name` equals `prefix` or starts with `prefix` followed by `_`.
+///
+/// This prevents e.g. `rte_comp` (compressdev) from matching `rte_compat`.
+fn prefix_matches(name: &str, prefix: &str) -> bool {
+    match name.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || prefix.ends_with('_') || rest.starts_with('_'),
+        None => false,
+    }
+}
+
+/// Find the feature selecting the given header (file stem), if any.
+///
+/// The family or group with the longest matching prefix wins (e.g. `rte_flow_classify` belongs to
+/// `pipeline`, not `ethdev`).
+fn header_feature(header: &str) -> Option<&'static str> {
+    LIBRARY_FAMILIES
+        .iter()
+        .chain(PMD_GROUPS.iter())
+        .flat_map(|(feature, _, prefixes)| prefixes.iter().map(move |prefix| (*feature, *prefix)))
+        .filter(|(_, prefix)| prefix_matches(header, prefix))
+        .max_by_key(|(_, prefix)| prefix.len())
+        .map(|(feature, _)| feature)
+}
+
+/// Returns whether the given library (without `librte_` prefix and extension) is used with the
+/// enabled features.
+fn library_selected(library: &str) -> bool {
+    if let Some((feature, _, _)) = LIBRARY_FAMILIES
+        .iter()
+        .find(|(_, libraries, _)| libraries.contains(&library))
+    {
+        return feature_enabled(feature);
+    }
+    let class = some_or!(library.split('_').next(), return true);
+    if library == class {
+        // Not a driver.
+        return true;
+    }
+    if let Some((feature, _, _)) = PMD_GROUPS
+        .iter()
+        .find(|(_, classes, _)| classes.contains(&class))
+    {
+        let dependencies = PMD_DEPENDENCIES
+            .iter()
+            .filter(|(driver, _)| *driver == library)
+            .flat_map(|(_, features)| features.iter());
+        return feature_enabled(feature) && dependencies.into_iter().all(|f| feature_enabled(f));
+    }
+    true
+}
+
+/// Pairs of (macro prefix, replacement) naming flags after macros.
+type FlagPrefixes = &'static [(&'static str, &'static str)];
+
+/// Groups of bit flag macros exposed as `bitflags` types.
+///
+/// Each entry is (type name, description, header defining the flags, macro prefixes, legacy macro
+/// prefixes). A flag is named after the macro, with the matching prefix replaced by the given
+/// string. Legacy prefixes (releases before 21.11) are used only if no macro matches the current
+/// prefixes.
+static FLAG_TYPES: &[(&str, &str, &str, FlagPrefixes, FlagPrefixes)] = &[
+    (
+        "RxOffload",
+        "Rx offload capabilities and configuration",
+        "rte_ethdev.h",
+        &[("RTE_ETH_RX_OFFLOAD_", "")],
+        &[("DEV_RX_OFFLOAD_", "")],
+    ),
+    (
+        "TxOffload",
+        "Tx offload capabilities and configuration",
+        "rte_ethdev.h",
+        &[("RTE_ETH_TX_OFFLOAD_", "")],
+        &[("DEV_TX_OFFLOAD_", "")],
+    ),
+    (
+        "RssHf",
+        "RSS hash functions",
+        "rte_ethdev.h",
+        &[("RTE_ETH_RSS_", "")],
+        &[("ETH_RSS_", "")],
+    ),
+    (
+        "MbufFlags",
+        "Offload features of mbufs (`rte_mbuf::ol_flags`)",
+        "rte_mbuf_core.h",
+        &[("RTE_MBUF_F_", "")],
+        &[
+            ("PKT_", ""),
+            ("IND_ATTACHED_MBUF", "INDIRECT"),
+            ("EXT_ATTACHED_MBUF", "EXTERNAL"),
+        ],
+    ),
+];
+
+/// DPDK releases at which APIs changed. For each release not newer than the installed DPDK,
+/// `dpdk_version_ge="<release>"` cfg is emitted.
+static KNOWN_RELEASES: &[(u32, u32)] = &[
+    (19, 11),
+    (20, 2),
+    (20, 5),
+    (20, 8),
+    (20, 11),
+    (21, 2),
+    (21, 5),
+    (21, 8),
+    (21, 11),
+    (22, 3),
+    (22, 7),
+    (22, 11),
+    (23, 3),
+    (23, 7),
+    (23, 11),
+    (24, 3),
+    (24, 7),
+    (24, 11),
+];
+
+/// Declare cfgs set by this build script, so that rustc checks their names and values.
+fn emit_check_cfgs() {
+    let releases = KNOWN_RELEASES
+        .iter()
+        .map(|(year, month)| format!(r#""{}.{:02}""#, year, month))
+        .join(", ");
+    println!(
+        "cargo::rustc-check-cfg=cfg(dpdk_version, values({}))",
+        releases
+    );
+    println!(
+        "cargo::rustc-check-cfg=cfg(dpdk_version_ge, values({}))",
+        releases
+    );
+    println!(r#"cargo::rustc-check-cfg=cfg(main_lcore_name, values("main", "master"))"#);
+}
+
+/// Emit cfgs and environment variables describing the DPDK version.
+///
+/// `dpdk_version="YY.MM"` is set to the exact release, and `dpdk_version_ge="YY.MM"` is set for
+/// each known release not newer than it.
+fn emit_version_cfgs(version: (u32, u32, u32)) {
+    let (year, month, minor) = version;
+    println!(r#"cargo:rustc-cfg=dpdk_version="{}.{:02}""#, year, month);
+    for (release_year, release_month) in KNOWN_RELEASES {
+        if (*release_year, *release_month) <= (year, month) {
+            println!(
+                r#"cargo:rustc-cfg=dpdk_version_ge="{}.{:02}""#,
+                release_year, release_month
+            );
+        }
+    }
+    println!(
+        "cargo:rustc-env=DPDK_VERSION={}.{:02}.{}",
+        year, month, minor
+    );
+}
+
+/// Files generated from DPDK headers. They are cached (and can be pre-generated) together with
+/// `directives.txt`, which lists cargo directives printed while generating them.
+static GENERATED_FILES: &[&str] = &["dpdk.h", "static.h", "static.c", "dpdk.rs", "lib.rs"];
+
+/// Cargo directives printed while generating bindings.
+static GENERATED_DIRECTIVES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Print a cargo directive (e.g. `rustc-cfg=...`) which depends on generated bindings, so that it
+/// can be replayed when the bindings are reused.
+fn emit_generated(directive: String) {
+    println!("cargo:{}", directive);
+    GENERATED_DIRECTIVES.lock().unwrap().push(directive);
+}
+
 /// Convert `/**` comments into `///` comments
 fn strip_comments(comment: String) -> String {
     comment
@@ -49,6 +350,18 @@ struct State {
     /// Machine string
     machine_string: Option<String>,
 
+    /// Target triple if cross-compiling (`TARGET` differs from `HOST`).
+    cross_target: Option<String>,
+
+    /// Root folder of the target system (`DPDK_SYSROOT`).
+    sysroot: Option<PathBuf>,
+
+    /// Target CPU (`DPDK_TARGET_CPU`), e.g. `native`, `generic` or a specific model.
+    target_cpu: String,
+
+    /// Code generation flag for the target CPU (e.g. `-march=native`).
+    cpu_flag: String,
+
     /// DPDK include folders.
     include_path: Vec<PathBuf>,
 
@@ -64,19 +377,41 @@ struct State {
     /// DPDK config file (will be included as a predefined macro file).
     dpdk_config: Option<PathBuf>,
 
+    /// Installed DPDK version (year, month, minor).
+    dpdk_version: Option<(u32, u32, u32)>,
+
+    /// Additional C compiler flags required by DPDK (from `libdpdk.pc`).
+    dpdk_cflags: Vec<String>,
+
+    /// Whether DPDK is linked as shared libraries (`dynamic` feature).
+    ///
+    /// PMDs are not linked in this mode. They are loaded as plugins by EAL at runtime.
+    dynamic: bool,
+
+    /// Folder of PMD plugins of a shared DPDK install.
+    pmd_path: Option<PathBuf>,
+
+    /// Ordered `cargo:rustc-link-lib` directives (from `libdpdk.pc`).
+    ///
+    /// Empty if DPDK is found without pkg-config.
+    link_directives: Vec<String>,
+
     /// Use definitions for automatically found EAL APIs.
     eal_function_use_defs: Vec<String>,
 
+    /// `bitflags` definitions of DPDK flag macros.
+    flag_type_defs: Vec<String>,
+
     /// Names of `static inline` functions found in DPDK headers.
     static_functions: Vec<String>,
 
-    /// Names of linkable (non-static) PMD-specific functions. We use them to create explicit
-    /// symbolic dependencies to PMDs.
+    /// Names of global symbols defined next to constructors in DPDK archives. We use them to
+    /// create explicit symbolic dependencies to PMDs.
     ///
-    /// Currently, DPDK's conditional build is incomplete. For example, declaration of
-    /// `rte_pmd_ixgbe_bypass_wd_reset` is controlled by `RTE_LIBRTE_IXGBE_BYPASS`, but its
-    /// definition is not.  Thus, we fallback to use explicit whitelist rather than automatically
-    /// detect non-static symbols.
+    /// DPDK drivers (and some libraries) register themselves with constructors (`RTE_INIT`,
+    /// `RTE_PMD_REGISTER_*`, `MEMPOOL_REGISTER_OPS`, ...), which are never referred to by the
+    /// application. The linker drops such objects from static archives unless one of their
+    /// symbols is referred to.
     linkable_pmd_functions: Vec<String>,
 }
 
@@ -90,13 +425,23 @@ impl State {
             project_path,
             out_path,
             machine_string: None,
+            cross_target: None,
+            sysroot: None,
+            target_cpu: "native".into(),
+            cpu_flag: "-march=native".into(),
             system_include_path: Default::default(),
             include_path: Default::default(),
             library_path: Default::default(),
             dpdk_headers: Default::default(),
             dpdk_links: Default::default(),
             dpdk_config: Default::default(),
+            dpdk_version: Default::default(),
+            dpdk_cflags: Default::default(),
+            dynamic: env::var("CARGO_FEATURE_DYNAMIC").is_ok(),
+            pmd_path: Default::default(),
+            link_directives: Default::default(),
             eal_function_use_defs: Default::default(),
+            flag_type_defs: Default::default(),
             static_functions: Default::default(),
             linkable_pmd_functions: Default::default(),
         }
@@ -109,7 +454,40 @@ impl State {
         index: &'a clang::Index,
         header_path: &Path,
     ) -> clang::TranslationUnit<'a> {
-        let mut argument = vec!["-march=native".into()];
+        match self.try_trans_unit_from_header(index, header_path) {
+            Ok(trans_unit) => trans_unit,
+            Err(fatal_diagnostics) => {
+                panic!("Encountering {} fatal parse error(s)", fatal_diagnostics)
+            }
+        }
+    }
+
+    /// Create clang trans unit from given header file.
+    /// Returns the number of fatal diagnostics if the header cannot be parsed.
+    fn try_trans_unit_from_header<'a>(
+        &self,
+        index: &'a clang::Index,
+        header_path: &Path,
+    ) -> std::result::Result<clang::TranslationUnit<'a>, usize> {
+        let trans_unit = index
+            .parser(header_path)
+            .arguments(&self.clang_args())
+            .parse()
+            .unwrap();
+        let fatal_diagnostics = trans_unit
+            .get_diagnostics()
+            .iter()
+            .filter(|diagnostic| clang::diagnostic::Severity::Fatal == diagnostic.get_severity())
+            .count();
+        if fatal_diagnostics > 0 {
+            return Err(fatal_diagnostics);
+        }
+        Ok(trans_unit)
+    }
+
+    /// Arguments to parse DPDK headers with clang.
+    fn clang_args(&self) -> Vec<String> {
+        let mut argument = self.clang_target_args();
         argument.extend(
             self.include_path
                 .iter()
@@ -125,23 +503,61 @@ impl State {
                 .unwrap()
                 .to_string(),
         ]);
+        argument.extend(self.dpdk_cflags.iter().cloned());
         for path in self.system_include_path.iter() {
             argument.push(format!("-I{}", path).to_string());
         }
-        let trans_unit = index
-            .parser(header_path)
-            .arguments(&argument)
-            .parse()
-            .unwrap();
-        let fatal_diagnostics = trans_unit
-            .get_diagnostics()
-            .iter()
-            .filter(|diagnostic| clang::diagnostic::Severity::Fatal == diagnostic.get_severity())
-            .count();
-        if fatal_diagnostics > 0 {
-            panic!("Encountering {} fatal parse error(s)", fatal_diagnostics);
+        argument
+    }
+
+    /// Compiler flags selecting the target platform and CPU, as accepted by clang.
+    fn clang_target_args(&self) -> Vec<String> {
+        let mut args = vec![self.cpu_flag.clone()];
+        if let Some(target) = &self.cross_target {
+            args.push(format!("--target={}", target));
+        }
+        if let Some(sysroot) = &self.sysroot {
+            args.push(format!("--sysroot={}", sysroot.display()));
         }
-        trans_unit
+        args
+    }
+
+    /// Check target platform and CPU.
+    ///
+    /// `DPDK_TARGET_CPU` selects the CPU which DPDK headers and wrappers are compiled for:
+    /// `native` (default for native builds), `generic` (baseline supported by DPDK, default for
+    /// cross builds), or a specific model (e.g. `haswell`, `neoverse-n1`).
+    ///
+    /// When cross-compiling, `DPDK_SYSROOT` may point to the root folder of the target system,
+    /// which contains DPDK and the C standard library.
+    fn check_target(&mut self) {
+        let target = env::var("TARGET").unwrap();
+        if target != env::var("HOST").unwrap() {
+            self.cross_target = Some(target.clone());
+        }
+        self.sysroot = env::var_os("DPDK_SYSROOT").map(PathBuf::from);
+
+        let default_cpu = match self.cross_target {
+            Some(_) => "generic",
+            None => "native",
+        };
+        let cpu = env::var("DPDK_TARGET_CPU").unwrap_or_else(|_| default_cpu.into());
+        let arch = target.split('-').next().unwrap();
+        // x86 selects CPUs with `-march`, while other architectures prefer `-mcpu`.
+        self.cpu_flag = match (arch, cpu.as_str()) {
+            // DPDK requires SSE4.2 on x86.
+            ("x86_64", "generic") | ("i686", "generic") => "-march=corei7".into(),
+            ("x86_64", _) | ("i686", _) => format!("-march={}", cpu),
+            // DPDK requires the CRC extension on Armv8.
+            ("aarch64", "generic") => "-march=armv8-a+crc".into(),
+            ("powerpc64le", "generic") => "-mcpu=power8".into(),
+            (_, "generic") => panic!("No generic CPU is known for {}", arch),
+            (_, _) => format!("-mcpu={}", cpu),
+        };
+        self.target_cpu = cpu;
+
+        println!("cargo:rerun-if-env-changed=DPDK_TARGET_CPU");
+        println!("cargo:rerun-if-env-changed=DPDK_SYSROOT");
     }
 
     /// Check current OS.
@@ -155,9 +571,12 @@ impl State {
     /// Check compiler and retrieve link path for C standard libs.
     fn check_compiler(&mut self) {
         let output = Command::new("bash")
-            .args(&[
+            .args([
                 "-c",
-                "clang -march=native -Wp,-v -x c - -fsyntax-only < /dev/null 2>&1 | sed -e '/^#include <...>/,/^End of search/{ //!b };d'",
+                &format!(
+                    "clang {} -Wp,-v -x c - -fsyntax-only < /dev/null 2>&1 | sed -e '/^#include <...>/,/^End of search/{{ //!b }};d'",
+                    self.clang_target_args().join(" ")
+                ),
             ])
             .output()
             .expect("failed to extract cc include path");
@@ -166,35 +585,413 @@ impl State {
             .extend(message.lines().map(|x| String::from(x.trim())));
     }
 
+    /// Build DPDK from source with meson (`vendored` feature) and install it into `OUT_DIR`.
+    ///
+    /// The source tree is given by `DPDK_SOURCE_PATH`, either as a folder or as a tarball. If it
+    /// is not set, a `dpdk-*.tar.xz` (or `.tar.gz`) tarball next to this crate is used. Only
+    /// libraries and drivers selected by cargo features are built. Returns the install path,
+    /// which is used like `DPDK_INSTALL_PATH`.
+    fn build_vendored_dpdk(&self) -> PathBuf {
+        println!("cargo:rerun-if-env-changed=DPDK_SOURCE_PATH");
+        println!("cargo:rerun-if-env-changed=DPDK_MESON_CROSS_FILE");
+        let source = match env::var_os("DPDK_SOURCE_PATH") {
+            Some(path) => PathBuf::from(path),
+            None => self
+                .project_path
+                .read_dir()
+                .expect("read_dir failed")
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    let name = path.file_name().unwrap().to_str().unwrap();
+                    name.starts_with("dpdk-")
+                        && (name.ends_with(".tar.xz") || name.ends_with(".tar.gz"))
+                })
+                .max()
+                .expect("Cannot find DPDK source (DPDK_SOURCE_PATH or dpdk-*.tar.xz)"),
+        };
+        println!("cargo:rerun-if-changed={}", source.display());
+
+        let source_path = if source.is_dir() {
+            source
+        } else {
+            let source_path = self.out_path.join("dpdk-src");
+            if !source_path.join("meson.build").exists() {
+                create_dir_all(&source_path).unwrap();
+                let status = Command::new("tar")
+                    .arg("-xf")
+                    .arg(&source)
+                    .arg("-C")
+                    .arg(&source_path)
+                    .arg("--strip-components=1")
+                    .status()
+                    .expect("failed to run tar");
+                if !status.success() {
+                    panic!("Cannot extract {}", source.display());
+                }
+            }
+            source_path
+        };
+
+        // Libraries of disabled families. Libraries which do not exist in this release are
+        // skipped, as meson rejects unknown names.
+        let disable_libs = LIBRARY_FAMILIES
+            .iter()
+            .filter(|(feature, _, _)| !feature_enabled(feature))
+            .flat_map(|(_, libraries, _)| libraries.iter())
+            .filter(|library| source_path.join("lib").join(library).is_dir())
+            .join(",");
+        let enable_drivers = ["bus", "mempool", "common"]
+            .iter()
+            .chain(
+                PMD_GROUPS
+                    .iter()
+                    .filter(|(feature, _, _)| feature_enabled(feature))
+                    .flat_map(|(_, classes, _)| classes.iter()),
+            )
+            .filter(|class| source_path.join("drivers").join(class).is_dir())
+            .map(|class| format!("{}/*", class))
+            .join(",");
+
+        // Older releases (e.g. 20.11) name the target CPU option `machine`, and meson rejects
+        // unknown options. Thus, use the option declared by this source tree.
+        let has_cpu_instruction_set = ["meson_options.txt", "meson.options"]
+            .iter()
+            .filter_map(|options| std::fs::read_to_string(source_path.join(options)).ok())
+            .any(|options| options.contains("'cpu_instruction_set'"));
+        let cpu_option = if has_cpu_instruction_set {
+            "cpu_instruction_set"
+        } else {
+            "machine"
+        };
+
+        let build_path = self.out_path.join("dpdk-build");
+        let install_path = self.out_path.join("dpdk-install");
+        if !build_path.join("build.ninja").exists() {
+            let mut command = Command::new("meson");
+            command
+                .arg("setup")
+                .arg(&build_path)
+                .arg(&source_path)
+                // The install is found like `DPDK_INSTALL_PATH` (`<install path>/usr/local`).
+                .arg(format!(
+                    "--prefix={}",
+                    install_path.join("usr/local").display()
+                ))
+                .arg("--libdir=lib")
+                .arg("--buildtype=release")
+                .arg(format!(
+                    "-Ddefault_library={}",
+                    if self.dynamic { "shared" } else { "static" }
+                ))
+                .arg(format!("-D{}={}", cpu_option, self.target_cpu))
+                .arg(format!("-Denable_drivers={}", enable_drivers))
+                .arg(format!("-Ddisable_libs={}", disable_libs))
+                .arg("-Dtests=false")
+                .arg("-Denable_kmods=false")
+                .arg("-Denable_docs=false");
+            if let Some(target) = &self.cross_target {
+                let cross_file = env::var("DPDK_MESON_CROSS_FILE").unwrap_or_else(|_| {
+                    panic!(
+                        "Building DPDK for {} requires a meson cross file (DPDK_MESON_CROSS_FILE)",
+                        target
+                    )
+                });
+                command.arg(format!("--cross-file={}", cross_file));
+            }
+            let status = command.status().expect("failed to run meson");
+            if !status.success() {
+                panic!("Cannot configure DPDK in {}", source_path.display());
+            }
+        }
+        let status = Command::new("meson")
+            .arg("install")
+            .arg("-C")
+            .arg(&build_path)
+            .arg("--quiet")
+            .status()
+            .expect("failed to run meson");
+        if !status.success() {
+            panic!("Cannot build DPDK in {}", source_path.display());
+        }
+        install_path
+    }
+
     /// Find DPDK install path.
     ///
     /// After 20.11 update, build system is integrated into meson.
     /// Thus, it is difficult to obtain build path manually.
     /// Currently, one must install DPDK to one's system.
     /// This function validates whether DPDK is installed.
+    ///
+    /// DPDK is looked up with pkg-config first. If `libdpdk.pc` is not found, we fall back to
+    /// guessing paths from the install prefix.
     fn find_dpdk(&mut self, install_path: &Option<String>) {
         // To find correct lib path of this platform.
-        let full_install_path = match install_path {
-            Some(install_path) => Path::new(&install_path).join("usr/local"),
-            None => PathBuf::from("/usr"),
+        let full_install_path = match (install_path, &self.sysroot) {
+            (Some(install_path), _) => Path::new(&install_path).join("usr/local"),
+            (None, Some(sysroot)) => sysroot.join("usr"),
+            (None, None) => PathBuf::from("/usr"),
         };
-        let machine_string = Command::new("cc")
-            .args(&["-dumpmachine"])
-            .output()
-            .expect("failed obtain current machine");
-        self.machine_string = Some(
-            String::from_utf8(machine_string.stdout)
-                .unwrap()
-                .trim()
-                .to_owned(),
+        self.machine_string = Some(match &self.cross_target {
+            // Multiarch folder names omit the vendor (e.g. `aarch64-linux-gnu`).
+            Some(target) => target
+                .split('-')
+                .filter(|part| *part != "unknown" && *part != "pc")
+                .join("-"),
+            None => {
+                let machine_string = Command::new("cc")
+                    .args(["-dumpmachine"])
+                    .output()
+                    .expect("failed obtain current machine");
+                String::from_utf8(machine_string.stdout)
+                    .unwrap()
+                    .trim()
+                    .to_owned()
+            }
+        });
+
+        if !self.find_dpdk_with_pkg_config(&full_install_path) {
+            eprintln!("Cannot find libdpdk with pkg-config, attempting install path heuristics");
+            self.find_dpdk_with_install_path(install_path, &full_install_path);
+        }
+        if self.dynamic {
+            self.find_pmd_path();
+        }
+
+        for path in &self.include_path {
+            println!("cargo:rerun-if-changed={}", path.display());
+        }
+        println!(
+            "cargo:rerun-if-changed={}",
+            self.library_path.as_ref().unwrap().to_str().unwrap()
         );
+        for entry in self
+            .project_path
+            .join("gen")
+            .read_dir()
+            .expect("read_dir failed")
+            .flatten()
+        {
+            let path = entry.path();
+
+            if let Some(ext) = path.extension() {
+                if ext == "template" {
+                    println!("cargo:rerun-if-changed={}", path.to_str().unwrap());
+                }
+            }
+        }
+
+        println!("cargo:rerun-if-env-changed=RTE_SDK");
+        println!("cargo:rerun-if-env-changed=RTE_TARGET");
+        println!("cargo:rerun-if-env-changed=DPDK_INSTALL_PATH");
+        println!("cargo:rerun-if-env-changed=PKG_CONFIG");
+        println!("cargo:rerun-if-env-changed=PKG_CONFIG_PATH");
+        println!("cargo:rerun-if-env-changed=PKG_CONFIG_LIBDIR");
+        println!("cargo:rerun-if-env-changed=PKG_CONFIG_SYSROOT_DIR");
+    }
+
+    /// Find DPDK through `libdpdk.pc`.
+    ///
+    /// `PKG_CONFIG_PATH` is honoured, and pkgconfig folders under the install path are searched
+    /// first. Include paths, compiler flags, and the ordered link line are obtained from
+    /// pkg-config. Returns `false` if `libdpdk` is not found.
+    fn find_dpdk_with_pkg_config(&mut self, full_install_path: &Path) -> bool {
+        let machine_string = self.machine_string.as_ref().unwrap();
+        let mut pkg_config_path: Vec<PathBuf> = vec![
+            full_install_path.join("lib").join(machine_string),
+            full_install_path.join("lib64"),
+            full_install_path.join("lib"),
+        ]
+        .into_iter()
+        .map(|path| path.join("pkgconfig"))
+        .filter(|path| path.exists())
+        .collect();
+        if let Some(paths) = env::var_os("PKG_CONFIG_PATH") {
+            pkg_config_path.extend(env::split_paths(&paths));
+        }
+        let pkg_config_path = env::join_paths(pkg_config_path).unwrap();
+
+        let sysroot = self.sysroot.clone();
+        let pkg_config = |args: &[&str]| -> Option<Vec<String>> {
+            let mut command =
+                Command::new(env::var("PKG_CONFIG").unwrap_or_else(|_| "pkg-config".into()));
+            command.env("PKG_CONFIG_PATH", &pkg_config_path);
+            // Paths in `.pc` files of the target system are relative to its root.
+            if let Some(sysroot) = &sysroot {
+                if env::var_os("PKG_CONFIG_SYSROOT_DIR").is_none() {
+                    command.env("PKG_CONFIG_SYSROOT_DIR", sysroot);
+                }
+                if env::var_os("PKG_CONFIG_LIBDIR").is_none() {
+                    let libdir = env::join_paths(
+                        [
+                            format!("usr/lib/{}/pkgconfig", machine_string),
+                            "usr/lib/pkgconfig".into(),
+                            "usr/share/pkgconfig".into(),
+                        ]
+                        .iter()
+                        .map(|path| sysroot.join(path)),
+                    )
+                    .unwrap();
+                    command.env("PKG_CONFIG_LIBDIR", libdir);
+                }
+            }
+            let output = command.args(args).arg("libdpdk").output().ok()?;
+            if !output.status.success() {
+                return None;
+            }
+            Some(
+                String::from_utf8(output.stdout)
+                    .unwrap()
+                    .split_whitespace()
+                    .map(String::from)
+                    .collect(),
+            )
+        };
+
+        let version = some_or!(pkg_config(&["--modversion"]), return false);
+        eprintln!("Found libdpdk {} with pkg-config", version.join(" "));
+        let cflags = pkg_config(&["--cflags"]).expect("failed to obtain DPDK cflags");
+        let libs = if self.dynamic {
+            pkg_config(&["--libs"])
+        } else {
+            pkg_config(&["--libs", "--static"])
+        }
+        .expect("failed to obtain DPDK libs");
+        let lib_dir = pkg_config(&["--variable=libdir"]).expect("failed to obtain DPDK libdir");
+
+        // Only folders containing DPDK headers are scanned to generate bindings. Other include
+        // paths (of DPDK's dependencies) are passed as compiler flags.
+        let mut config_header_name = "rte_config.h".to_string();
+        let mut cflags = cflags.into_iter();
+        while let Some(flag) = cflags.next() {
+            if let Some(path) = flag.strip_prefix("-I") {
+                let path = PathBuf::from(path);
+                let has_dpdk_headers = path
+                    .read_dir()
+                    .map(|mut entries| {
+                        entries.any(|entry| {
+                            entry.is_ok_and(|entry| {
+                                let file_name = entry.file_name();
+                                let file_name = file_name.to_str().unwrap_or_default();
+                                file_name.starts_with("rte_") && file_name.ends_with(".h")
+                            })
+                        })
+                    })
+                    .unwrap_or(false);
+                if has_dpdk_headers {
+                    self.include_path.push(path);
+                    continue;
+                }
+            } else if flag == "-include" {
+                // `rte_config.h` is given as `-imacros` instead.
+                config_header_name = cflags.next().expect("-include without file name");
+                continue;
+            } else if ["-march=", "-mcpu=", "-mtune="]
+                .iter()
+                .any(|prefix| flag.starts_with(prefix))
+            {
+                // Target CPU is selected by `DPDK_TARGET_CPU`.
+                continue;
+            }
+            self.dpdk_cflags.push(flag);
+        }
+        let config_header = self
+            .include_path
+            .iter()
+            .map(|path| path.join(&config_header_name))
+            .find(|path| path.exists())
+            .unwrap_or_else(|| {
+                panic!(
+                    "DPDK is not installed on your system! (Cannot find {})",
+                    config_header_name
+                )
+            });
+        self.dpdk_config = Some(config_header);
+
+        // Translate the link line while keeping its order. Libraries between `--whole-archive`
+        // and `--no-whole-archive` (PMDs) must be linked as a whole, since they are only
+        // referenced through constructors.
+        let mut library_paths = lib_dir.into_iter().map(PathBuf::from).collect::<Vec<_>>();
+        let mut whole_archive = false;
+        for flag in libs {
+            let lib_name = if flag == "-Wl,--whole-archive" {
+                whole_archive = true;
+                continue;
+            } else if flag == "-Wl,--no-whole-archive" {
+                whole_archive = false;
+                continue;
+            } else if let Some(path) = flag.strip_prefix("-L") {
+                library_paths.push(PathBuf::from(path));
+                continue;
+            } else if let Some(file_name) = flag.strip_prefix("-l:") {
+                // Archive given by its file name, e.g. `-l:librte_eal.a`.
+                let name = file_name.strip_prefix("lib").unwrap_or(file_name);
+                name.strip_suffix(".a").unwrap_or(name).to_string()
+            } else if let Some(name) = flag.strip_prefix("-l") {
+                if !name.starts_with("rte_") {
+                    // Dependencies of DPDK (e.g. numa, pcap) are linked as the system provides.
+                    self.link_directives.push(name.to_string());
+                    continue;
+                }
+                name.to_string()
+            } else {
+                // Other flags (e.g. `-pthread`, `-Wl,--as-needed`) are handled by rustc.
+                continue;
+            };
+
+            if !library_selected(lib_name.strip_prefix("rte_").unwrap_or(&lib_name)) {
+                continue;
+            }
+            let extension = if self.dynamic { "so" } else { "a" };
+            if let Some(path) = library_paths
+                .iter()
+                .map(|path| path.join(format!("lib{}.{}", lib_name, extension)))
+                .find(|path| path.exists())
+            {
+                self.dpdk_links.push(path);
+            }
+            if self.dynamic {
+                self.link_directives.push(format!("dylib={}", lib_name));
+            } else if whole_archive {
+                self.link_directives
+                    .push(format!("static:-bundle,+whole-archive={}", lib_name));
+            } else {
+                self.link_directives.push(format!("static={}", lib_name));
+            }
+        }
+        // Libraries appear in both `Libs` and `Libs.private`. Keep the last occurrence, which
+        // comes after every library depending on it.
+        let mut seen = HashSet::new();
+        self.link_directives.reverse();
+        self.link_directives
+            .retain(|directive| seen.insert(directive.rsplit('=').next().unwrap().to_string()));
+        self.link_directives.reverse();
+        self.dpdk_links.sort();
+        self.dpdk_links.dedup();
+
+        // Keep the first occurrence of each path, since the first one is the main library path.
+        let mut seen = HashSet::new();
+        library_paths.retain(|path| seen.insert(path.clone()));
+        for path in library_paths.iter().skip(1) {
+            println!("cargo:rustc-link-search=native={}", path.display());
+        }
+        self.library_path = library_paths.into_iter().next();
+        true
+    }
+
+    /// Find DPDK by guessing paths from the install prefix.
+    fn find_dpdk_with_install_path(
+        &mut self,
+        install_path: &Option<String>,
+        full_install_path: &Path,
+    ) {
         let machine_string = &self.machine_string.as_ref().unwrap();
 
         let config_header = match install_path {
             Some(_) => full_install_path.join("include/rte_config.h"),
             None => full_install_path
                 .join("include")
-                .join(&machine_string)
+                .join(machine_string)
                 .join("dpdk/rte_config.h"),
         };
         if !config_header.exists() {
@@ -216,39 +1013,72 @@ impl State {
 
         // TODO: check library_path without machine_string too
         self.library_path = Some(full_install_path.join("lib").join(machine_string));
+        self.dpdk_config = Some(config_header);
+    }
 
-        for path in &self.include_path {
-            println!("cargo:rerun-if-changed={}", path.display());
-        }
-        println!(
-            "cargo:rerun-if-changed={}",
-            self.library_path.as_ref().unwrap().to_str().unwrap()
-        );
-        for entry in self
-            .project_path
-            .join("gen")
+    /// Find the folder of PMD plugins (`<libdir>/dpdk/pmds-<ABI version>`) of a shared install.
+    ///
+    /// The path is exposed to the crate as `DPDK_PMD_PATH` so that applications can pass it to EAL
+    /// (`-d` option).
+    fn find_pmd_path(&mut self) {
+        let plugin_dir = self.library_path.as_ref().unwrap().join("dpdk");
+        let mut pmd_paths = plugin_dir
             .read_dir()
-            .expect("read_dir failed")
-        {
-            if let Ok(entry) = entry {
-                let path = entry.path();
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.is_dir()
+                    && path
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .is_some_and(|name| name.starts_with("pmds-"))
+            })
+            .collect::<Vec<_>>();
+        // Prefer the latest ABI version.
+        pmd_paths.sort();
+        self.pmd_path = pmd_paths.pop();
+        match &self.pmd_path {
+            Some(path) => println!("cargo:rustc-env=DPDK_PMD_PATH={}", path.display()),
+            None => eprintln!(
+                "Cannot find PMD plugin folder in {}, PMDs must be loaded manually",
+                plugin_dir.display()
+            ),
+        }
+    }
 
-                if let Some(ext) = path.extension() {
-                    if ext == "template" {
-                        println!("cargo:rerun-if-changed={}", path.to_str().unwrap());
-                    }
+    /// Detect DPDK version from `rte_build_config.h` (meson builds) or `rte_version.h`.
+    fn find_dpdk_version(&mut self) {
+        let format = Regex::new(r"#define\s+RTE_VER_(YEAR|MONTH|MINOR)\s+(\d+)").unwrap();
+        let mut fields = HashMap::new();
+        for header in &["rte_build_config.h", "rte_version.h"] {
+            for path in self.include_path.iter().map(|path| path.join(header)) {
+                let content = some_or!(std::fs::read_to_string(&path).ok(), continue);
+                for capture in format.captures_iter(&content) {
+                    fields
+                        .entry(capture[1].to_string())
+                        .or_insert_with(|| capture[2].parse::<u32>().unwrap());
                 }
             }
         }
-
-        println!("cargo:rerun-if-env-changed=RTE_SDK");
-        println!("cargo:rerun-if-env-changed=RTE_TARGET");
-
-        self.dpdk_config = Some(config_header);
+        let field = |name: &str| {
+            *fields
+                .get(name)
+                .unwrap_or_else(|| panic!("Cannot find RTE_VER_{} in DPDK headers", name))
+        };
+        let version = (field("YEAR"), field("MONTH"), field("MINOR"));
+        eprintln!("DPDK version: {}.{:02}.{}", version.0, version.1, version.2);
+        emit_version_cfgs(version);
+        self.dpdk_version = Some(version);
     }
 
     /// Search through DPDK's link dir and extract library names.
     fn find_link_libs(&mut self) {
+        if !self.link_directives.is_empty() {
+            // Already obtained from pkg-config.
+            return;
+        }
         let lib_dir = self.library_path.as_ref().unwrap();
 
         let mut libs = vec![];
@@ -260,8 +1090,7 @@ impl State {
                 }
 
                 if let Some(ext) = path.extension() {
-                    if ext != "a" {
-                        //if ext != "so" {
+                    if ext != if self.dynamic { "so" } else { "a" } {
                         continue;
                     }
                 } else {
@@ -273,6 +1102,9 @@ impl State {
                     if !string.starts_with("librte_") {
                         continue;
                     }
+                    if !library_selected(&string["librte_".len()..]) {
+                        continue;
+                    }
                     libs.push(path.clone());
                 } else {
                     continue;
@@ -292,7 +1124,7 @@ impl State {
     fn make_all_in_one_header(&mut self) {
         let dpdk_config = self.dpdk_config.as_ref().unwrap();
         // dlb drivers have duplicated enum definitions.
-        let blacklist = vec!["rte_pmd_dlb", "rte_pmd_dlb2"];
+        let blacklist = ["rte_pmd_dlb", "rte_pmd_dlb2"];
         let mut headers = vec![];
         for entry in self
             .include_path
@@ -306,9 +1138,15 @@ impl State {
                     continue;
                 }
                 if let Some(stem) = path.file_stem() {
-                    if blacklist.contains(&stem.to_str().unwrap()) {
+                    let stem = stem.to_str().unwrap();
+                    if blacklist.contains(&stem) {
                         continue;
                     }
+                    if let Some(feature) = header_feature(stem) {
+                        if !feature_enabled(feature) {
+                            continue;
+                        }
+                    }
                 }
                 if let Some(ext) = path.extension() {
                     if ext != "h" {
@@ -329,6 +1167,27 @@ impl State {
         headers.dedup();
         assert!(!headers.is_empty());
 
+        // Report selected families which are not installed, and let build scripts of dependent
+        // crates know which families are available (`DEP_DPDK_LIBS`, see `links` in Cargo.toml).
+        let mut installed_families = Vec::new();
+        for (feature, _, _) in LIBRARY_FAMILIES {
+            if !feature_enabled(feature) {
+                continue;
+            }
+            let installed = headers.iter().any(|header| {
+                header_feature(header.file_stem().unwrap().to_str().unwrap()) == Some(*feature)
+            });
+            if installed {
+                installed_families.push(*feature);
+            } else {
+                emit_generated(format!(
+                    "warning=DPDK library family `{}` is not installed, skipping it",
+                    feature
+                ));
+            }
+        }
+        emit_generated(format!("libs={}", installed_families.join(",")));
+
         // Heuristically remove platform-specific headers
         let platform_set = vec!["x86", "x86_64", "x64", "arm", "arm32", "arm64", "amd64"];
         let mut name_set = vec![];
@@ -357,7 +1216,7 @@ impl State {
             let left_count = left_str.split('_').count();
             let right_count = right_str.split('_').count();
             match left_count.cmp(&right_count) {
-                Ordering::Equal => left_str.cmp(&right_str),
+                Ordering::Equal => left_str.cmp(right_str),
                 Ordering::Less => Ordering::Less,
                 Ordering::Greater => Ordering::Greater,
             }
@@ -367,6 +1226,37 @@ impl State {
 
         // Generate all-in-one dpdk header (`dpdk.h`).
         self.dpdk_headers = headers;
+        self.write_all_in_one_header();
+
+        // Headers of partially installed libraries (e.g. split distribution packages) may include
+        // missing files. Exclude unparsable headers rather than failing.
+        let clang = clang::Clang::new().unwrap();
+        let index = clang::Index::new(&clang, true, false);
+        let header_path = self.out_path.join("dpdk.h");
+        if self
+            .try_trans_unit_from_header(&index, &header_path)
+            .is_err()
+        {
+            let broken_headers = self
+                .dpdk_headers
+                .iter()
+                .filter(|header| self.try_trans_unit_from_header(&index, header).is_err())
+                .cloned()
+                .collect::<Vec<_>>();
+            for header in &broken_headers {
+                emit_generated(format!(
+                    "warning=Cannot parse {}, skipping it",
+                    header.file_name().unwrap().to_str().unwrap()
+                ));
+            }
+            self.dpdk_headers
+                .retain(|header| !broken_headers.contains(header));
+            self.write_all_in_one_header();
+        }
+    }
+
+    /// Write all-in-one dpdk header (`dpdk.h`) including `dpdk_headers`.
+    fn write_all_in_one_header(&self) {
         let template_path = self.project_path.join("gen/dpdk.h.template");
         let target_path = self.out_path.join("dpdk.h");
         let mut template = File::open(template_path).unwrap();
@@ -384,15 +1274,17 @@ impl State {
         target.write_fmt(format_args!("{}", formatted_string)).ok();
     }
 
-    /// Extract trivial EAL APIs whose paramter types are all primitive (e.g. `uint8_t`).
+    /// Extract EAL APIs and wrap them as methods of `EalStaticFunctions`.
     ///
     /// This function does followings:
     /// 1. List up all headers in `librte_eal/include/generic`
     /// 1. Also list up some selected headers in `librte_eal/include`
     /// 1. Extract all function in the listed headers.
-    /// 1. Filter out "trivial" FFI implementations. For instance, a function whose arguments and
-    ///    return type are primitive types.
-    /// 1. Generate a trait which trivially invokes the selected foriegn functions.
+    /// 1. Translate argument and return types into Rust. Functions with types which cannot be
+    ///    translated (e.g. variadic arguments, SIMD vectors) are skipped.
+    /// 1. Generate a trait which trivially invokes the selected foriegn functions. Functions are
+    ///    generated as `unsafe fn`, except the ones listed in `safe_functions` whose arguments are
+    ///    all primitive types (e.g. `uint8_t`).
     /// 1. Remove `rte_` prefix of them.
     fn extract_eal_apis(&mut self) {
         // List of acceptable primitive types.
@@ -410,10 +1302,109 @@ impl State {
             ("int16_t", "i16"),
             ("int32_t", "i32"),
             ("int64_t", "i64"),
+            ("uintptr_t", "usize"),
+            ("char", "::std::os::raw::c_char"),
+            ("unsigned char", "u8"),
+            ("short", "i16"),
+            ("unsigned short", "u16"),
+            ("long", "::std::os::raw::c_long"),
+            ("unsigned long", "::std::os::raw::c_ulong"),
+            ("long long", "i64"),
+            ("unsigned long long", "u64"),
+            ("float", "f32"),
+            ("double", "f64"),
+            ("bool", "bool"),
+            ("_Bool", "bool"),
         ]
         .iter()
         .map(|(c_type, rust_type)| (String::from(*c_type), String::from(*rust_type)))
         .collect();
+
+        /// Translate a C type into Rust.
+        ///
+        /// Returns the Rust type and whether values of the type can be passed safely (primitive
+        /// types), or `None` if the type is not supported.
+        fn rust_type(
+            type_: clang::Type<'_>,
+            primitives: &HashMap<String, String>,
+        ) -> Option<(String, bool)> {
+            let display_name = type_.get_display_name();
+            let unqualified_name = display_name
+                .trim_start_matches("const ")
+                .trim_start_matches("volatile ");
+            if let Some(rust_type_name) = primitives.get(unqualified_name) {
+                return Some((rust_type_name.clone(), true));
+            }
+            match type_.get_kind() {
+                clang::TypeKind::Elaborated => rust_type(type_.get_elaborated_type()?, primitives),
+                clang::TypeKind::Pointer => {
+                    let pointee = type_.get_pointee_type()?;
+                    let pointee_name = match pointee.get_canonical_type().get_kind() {
+                        clang::TypeKind::Void => "::std::os::raw::c_void".to_string(),
+                        clang::TypeKind::CharS | clang::TypeKind::CharU => {
+                            "::std::os::raw::c_char".to_string()
+                        }
+                        // bindgen translates pointers to functions into `Option<fn>`.
+                        clang::TypeKind::FunctionPrototype
+                        | clang::TypeKind::FunctionNoPrototype => return None,
+                        _ => rust_type(pointee, primitives)?.0,
+                    };
+                    let mutability = if pointee.is_const_qualified() {
+                        "const"
+                    } else {
+                        "mut"
+                    };
+                    Some((format!("*{} {}", mutability, pointee_name), false))
+                }
+                clang::TypeKind::Record | clang::TypeKind::Enum => {
+                    let name = type_.get_declaration()?.get_name()?;
+                    Some((format!("crate::{}", name), false))
+                }
+                clang::TypeKind::Typedef => {
+                    let name = type_.get_declaration()?.get_name()?;
+                    let safe = match type_.get_canonical_type().get_kind() {
+                        clang::TypeKind::Bool
+                        | clang::TypeKind::CharS
+                        | clang::TypeKind::CharU
+                        | clang::TypeKind::SChar
+                        | clang::TypeKind::UChar
+                        | clang::TypeKind::Short
+                        | clang::TypeKind::UShort
+                        | clang::TypeKind::Int
+                        | clang::TypeKind::UInt
+                        | clang::TypeKind::Long
+                        | clang::TypeKind::ULong
+                        | clang::TypeKind::LongLong
+                        | clang::TypeKind::ULongLong
+                        | clang::TypeKind::Float
+                        | clang::TypeKind::Double => true,
+                        clang::TypeKind::Record
+                        | clang::TypeKind::Enum
+                        | clang::TypeKind::Pointer => false,
+                        // e.g. SIMD vectors and arrays.
+                        _ => return None,
+                    };
+                    Some((format!("crate::{}", name), safe))
+                }
+                _ => None,
+            }
+        }
+
+        /// Rename arguments which are Rust keywords.
+        fn rust_arg_name(name: String) -> String {
+            let keywords = [
+                "as", "box", "break", "const", "continue", "crate", "dyn", "else", "enum",
+                "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+                "move", "mut", "priv", "pub", "ref", "return", "self", "static", "struct", "super",
+                "trait", "true", "type", "unsafe", "use", "where", "while",
+            ];
+            if keywords.contains(&name.as_str()) {
+                name + "_"
+            } else {
+                name
+            }
+        }
+
         let headers_whitelist = vec![
             // From librte_eal/include/generic
             "rte_atomic.h",
@@ -431,7 +1422,7 @@ impl State {
             "rte_vect.h",
             // From librte_eal/include
             // "rte_alarm.h",
-            // "rte_bitmap.h",
+            "rte_bitmap.h",
             // "rte_branch_prediction.h",
             // "rte_bus.h",
             // "rte_class.h",
@@ -442,29 +1433,29 @@ impl State {
             // "rte_devargs.h",
             // "rte_eal_interrupts.h",
             // "rte_eal_memconfig.h",
-            // "rte_eal.h",
-            // "rte_errno.h",
+            "rte_eal.h",
+            "rte_errno.h",
             // "rte_fbarray.h",
             // "rte_function_versioning.h",
             // "rte_hexdump.h",
             // "rte_hypervisor.h",
             // "rte_interrupts.h",
             // "rte_keepalive.h",
-            // "rte_launch.h",
-            // "rte_lcore.h",
-            // "rte_log.h",
-            // "rte_malloc.h",
-            // "rte_memory.h",
-            // "rte_memzone.h",
+            "rte_launch.h",
+            "rte_lcore.h",
+            "rte_log.h",
+            "rte_malloc.h",
+            "rte_memory.h",
+            "rte_memzone.h",
             // "rte_option.h",
             // "rte_pci_dev_feature_defs.h",
             // "rte_pci_dev_features.h",
-            // "rte_per_lcore.h",
+            "rte_per_lcore.h",
             "rte_random.h",
-            // "rte_reciprocal.h",
+            "rte_reciprocal.h",
             // "rte_service_component.h",
-            // "rte_service.h",
-            // "rte_string_fns.h",
+            "rte_service.h",
+            "rte_string_fns.h",
             // "rte_tailq.h",
             // "rte_test.h",
             "rte_time.h",
@@ -473,6 +1464,71 @@ impl State {
             // "rte_vfio.h",
         ];
 
+        // Functions which are safe to call with any primitive arguments, once EAL is initialized.
+        // Every other function (e.g. `rte_eal_cleanup`, `rte_eal_remote_launch`, `rte_service_*`,
+        // `rte_memzone_*`) changes global state or resources that Rust code may rely on, thus it
+        // is generated as `unsafe fn`.
+        let safe_functions = [
+            // Timers
+            "rte_get_tsc_hz",
+            "rte_get_tsc_cycles",
+            "rte_get_timer_cycles",
+            "rte_get_timer_hz",
+            "rte_rdtsc",
+            "rte_rdtsc_precise",
+            "rte_delay_us_block",
+            "rte_delay_us_sleep",
+            "rte_pause",
+            // Random numbers
+            "rte_rand",
+            "rte_rand_max",
+            "rte_srand",
+            // Arithmetic helpers
+            "rte_is_power_of_2",
+            "rte_align32pow2",
+            "rte_align64pow2",
+            "rte_align32prevpow2",
+            "rte_align64prevpow2",
+            "rte_combine32ms1b",
+            "rte_combine64ms1b",
+            "rte_bsf32",
+            "rte_bsf64",
+            "rte_fls_u32",
+            "rte_fls_u64",
+            "rte_log2_u32",
+            "rte_log2_u64",
+            "rte_arch_bswap16",
+            "rte_arch_bswap32",
+            "rte_arch_bswap64",
+            // Memory barriers
+            "rte_mb",
+            "rte_wmb",
+            "rte_rmb",
+            "rte_smp_mb",
+            "rte_smp_wmb",
+            "rte_smp_rmb",
+            "rte_io_mb",
+            "rte_io_wmb",
+            "rte_io_rmb",
+            // Lcore and process information
+            "rte_lcore_id",
+            "rte_lcore_count",
+            "rte_lcore_index",
+            "rte_lcore_is_enabled",
+            "rte_lcore_to_socket_id",
+            "rte_get_main_lcore",
+            "rte_get_master_lcore",
+            "rte_get_next_lcore",
+            "rte_socket_id",
+            "rte_socket_count",
+            "rte_eal_process_type",
+            "rte_eal_has_hugepages",
+            "rte_eal_has_pci",
+            "rte_gettid",
+            "rte_sys_gettid",
+            "rte_version",
+        ];
+
         // Set of function definition strings (Rust), coupled with function names.
         // This will prevent duplicated function definitions.
         let mut use_def_map = HashMap::new();
@@ -486,8 +1542,8 @@ impl State {
             {
                 None => {
                     // In case where our whitelist is outdated.
-                    println!(
-                        "cargo:warning=EAL header whitelist is outdated. Contact maintainers."
+                    emit_generated(
+                        "warning=EAL header whitelist is outdated. Contact maintainers.".into(),
                     );
                     continue;
                 }
@@ -509,6 +1565,10 @@ impl State {
                 let return_type = some_or!(f.get_result_type(), continue);
                 let is_decl = f.is_definition();
                 let comment = strip_comments(some_or!(f.get_comment(), continue));
+                if f.is_variadic() {
+                    // Variadic functions cannot be wrapped.
+                    continue;
+                }
                 if use_def_map.contains_key(&name) {
                     // Skip duplicate
                     continue;
@@ -524,28 +1584,35 @@ impl State {
                     // function declaration is found.
                     continue;
                 }
+                if is_decl && !self.static_functions.contains(&name) {
+                    // No wrapper is generated for this `static inline` function.
+                    continue;
+                }
 
                 // Extract type names in C and Rust.
-                let c_return_type_string = return_type.get_display_name();
-                let rust_return_type_string =
-                    some_or!(arg_type_whitelist.get(&c_return_type_string), {
+                let (rust_return_type_string, _) =
+                    some_or!(rust_type(return_type, &arg_type_whitelist), {
                         continue;
                     });
 
                 let args = f.get_arguments().unwrap_or_default();
                 let mut arg_names = Vec::new();
                 let mut rust_arg_names = Vec::new();
+                // Whether the function is known to be safe, and all arguments are primitive types.
+                let mut is_safe = safe_functions.contains(&name.as_str());
                 // Format arguments
                 for (counter, arg) in args.iter().enumerate() {
-                    let arg_name = arg
-                        .get_display_name()
-                        .unwrap_or_else(|| format!("_unnamed_arg{}", counter));
-                    let c_type_name = arg.get_type().unwrap().get_display_name();
-                    let rust_type_name = some_or!(arg_type_whitelist.get(&c_type_name), {
-                        // If the given C type is not supported as primitive Rust types. Skip
-                        // processing this function.
-                        continue 'each_function;
-                    });
+                    let arg_name = rust_arg_name(
+                        arg.get_display_name()
+                            .unwrap_or_else(|| format!("_unnamed_arg{}", counter)),
+                    );
+                    let (rust_type_name, is_primitive) =
+                        some_or!(rust_type(arg.get_type().unwrap(), &arg_type_whitelist), {
+                            // If the given C type cannot be translated into Rust. Skip processing
+                            // this function.
+                            continue 'each_function;
+                        });
+                    is_safe &= is_primitive;
                     rust_arg_names.push(format!("{}: {}", arg_name, rust_type_name));
                     arg_names.push(arg_name);
                 }
@@ -556,20 +1623,167 @@ impl State {
                     format!(" -> {}", rust_return_type_string)
                 };
                 /*
-                Following code generates trait function definitions like this:
+                Following code generates trait function definitions of safe functions like this:
 
                 /// Comment from C
                 #[inline(always)]
                 fn function_name ( &self, arg: u8 ) -> u8 {
                     unsafe { crate::rte_function_name(arg) }
                 }
+
+                Other functions are generated like this:
+
+                /// Comment from C
+                ///
+                /// # Safety
+                /// ...
+                #[inline(always)]
+                unsafe fn function_name ( &self, arg: *mut crate::rte_struct ) {
+                    crate::rte_function_name(arg)
+                }
                 */
-                use_def_map.insert(name.clone(), format!("\n{comment}\n#[inline(always)]\nfn {func_name} ( &self, {rust_args} ){ret} {{\n\tunsafe {{ crate::{name}({c_arg}) }}\n}}", comment=comment, func_name=name.trim_start_matches("rte_"), name=name, rust_args=rust_arg_names.join(", "), ret=ret, c_arg=arg_names.join(", ")));
+                let def = if is_safe {
+                    format!("\n{comment}\n#[inline(always)]\nfn {func_name} ( &self, {rust_args} ){ret} {{\n\tunsafe {{ crate::{name}({c_arg}) }}\n}}", comment=comment, func_name=name.trim_start_matches("rte_"), name=name, rust_args=rust_arg_names.join(", "), ret=ret, c_arg=arg_names.join(", "))
+                } else {
+                    format!("\n{comment}\n///\n/// # Safety\n/// The requirements of `{name}` must be satisfied (e.g. pointers must be valid, and resources in use must not be released).\n#[inline(always)]\nunsafe fn {func_name} ( &self, {rust_args} ){ret} {{\n\tcrate::{name}({c_arg})\n}}", comment=comment, func_name=name.trim_start_matches("rte_"), name=name, rust_args=rust_arg_names.join(", "), ret=ret, c_arg=arg_names.join(", "))
+                };
+                use_def_map.insert(name.clone(), def);
             }
         }
         self.eal_function_use_defs = use_def_map.values().cloned().collect();
     }
 
+    /// Generate `bitflags` types for groups of flag macros listed in `FLAG_TYPES`.
+    ///
+    /// Since 21.11, most flags are defined with `RTE_BIT64`, which bindgen cannot evaluate. Thus,
+    /// we list up macro definitions with clang, and evaluate them as initializers of generated C
+    /// constants.
+    fn extract_flag_types(&mut self) {
+        let header_path = self.out_path.join("dpdk.h");
+        let clang = clang::Clang::new().unwrap();
+        let index = clang::Index::new(&clang, true, false);
+        let trans_unit = index
+            .parser(&header_path)
+            .arguments(&self.clang_args())
+            .detailed_preprocessing_record(true)
+            .parse()
+            .unwrap();
+
+        // Names of object-like macros, grouped by their header file.
+        let mut macros: HashMap<String, Vec<String>> = HashMap::new();
+        for entity in trans_unit
+            .get_entity()
+            .get_children()
+            .into_iter()
+            .filter(|e| e.get_kind() == clang::EntityKind::MacroDefinition)
+            .filter(|e| !e.is_function_like_macro())
+        {
+            let name = some_or!(entity.get_name(), continue);
+            let location = some_or!(entity.get_location(), continue);
+            let file = some_or!(location.get_file_location().file, continue);
+            let file_name = file
+                .get_path()
+                .file_name()
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string();
+            macros.entry(file_name).or_default().push(name);
+        }
+
+        // (type name, flag name, macro name) of each flag candidate.
+        let mut candidates = vec![];
+        for (type_name, _, header, prefixes, legacy_prefixes) in FLAG_TYPES {
+            let names = some_or!(macros.get(*header), continue);
+            let match_prefixes = |prefixes: &[(&str, &str)]| {
+                names
+                    .iter()
+                    .filter_map(|name| {
+                        prefixes.iter().find_map(|(prefix, replace)| {
+                            name.strip_prefix(prefix)
+                                .map(|flag| (format!("{}{}", replace, flag), name.clone()))
+                        })
+                    })
+                    // Sizes of RSS redirection tables are not flags.
+                    .filter(|(flag, _)| !flag.is_empty() && !flag.starts_with("RETA_SIZE_"))
+                    .collect::<Vec<_>>()
+            };
+            let mut flags = match_prefixes(prefixes);
+            if flags.is_empty() {
+                flags = match_prefixes(legacy_prefixes);
+            }
+            flags.sort();
+            flags.dedup_by(|left, right| left.0 == right.0);
+            candidates.extend(
+                flags
+                    .into_iter()
+                    .map(|(flag, name)| (*type_name, flag, name)),
+            );
+        }
+
+        // Evaluate flags as C constants.
+        let source_path = self.out_path.join("flags.c");
+        let mut source = String::from("#include \"dpdk.h\"\n");
+        for (counter, (_, _, name)) in candidates.iter().enumerate() {
+            source += &format!(
+                "const unsigned long long __rust_flag_{} = (unsigned long long)({});\n",
+                counter, name
+            );
+        }
+        let mut target = File::create(&source_path).unwrap();
+        target.write_fmt(format_args!("{}", source)).ok();
+
+        let trans_unit = self.trans_unit_from_header(&index, &source_path);
+        let mut values = HashMap::new();
+        for entity in trans_unit
+            .get_entity()
+            .get_children()
+            .into_iter()
+            .filter(|e| e.get_kind() == clang::EntityKind::VarDecl)
+        {
+            let name = some_or!(entity.get_name(), continue);
+            let counter = some_or!(name.strip_prefix("__rust_flag_"), continue);
+            let value = match entity.evaluate() {
+                Some(clang::EvaluationResult::UnsignedInteger(value)) => value,
+                Some(clang::EvaluationResult::SignedInteger(value)) => value as u64,
+                // Not a constant expression (e.g. a flag defined with a runtime value).
+                _ => continue,
+            };
+            values.insert(counter.parse::<usize>().unwrap(), value);
+        }
+
+        for (type_name, description, _, prefixes, _) in FLAG_TYPES {
+            let flags = candidates
+                .iter()
+                .enumerate()
+                .filter(|(_, (flag_type, _, _))| flag_type == type_name)
+                .filter_map(|(counter, (_, flag, name))| {
+                    values.get(&counter).map(|value| {
+                        format!("\t\t/// `{}`\n\t\tconst {} = {:#x};", name, flag, value)
+                    })
+                })
+                .join("\n");
+            /*
+            Following code generates flag types like this:
+
+            bitflags::bitflags! {
+                /// Description (`RTE_ETH_RX_OFFLOAD_*`).
+                pub struct RxOffload: u64 {
+                    /// `RTE_ETH_RX_OFFLOAD_VLAN_STRIP`
+                    const VLAN_STRIP = 0x1;
+                }
+            }
+            */
+            self.flag_type_defs.push(format!(
+                "bitflags::bitflags! {{\n\t/// {description} (`{prefix}*`).\n\t#[repr(transparent)]\n\t#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]\n\tpub struct {type_name}: u64 {{\n{flags}\n\t}}\n}}",
+                description = description,
+                prefix = prefixes[0].0,
+                type_name = type_name,
+                flags = flags
+            ));
+        }
+    }
+
     /// Generate wrappers for static functions and create explicit links for PMDs.
     fn generate_static_impls_and_link_pmds(&mut self) {
         let header_path = self.out_path.join("dpdk.h");
@@ -599,9 +1813,7 @@ impl State {
                     let name = name + "[]";
                     format_arg(elem_type, name)
                 }
-                _ => {
-                    return format!("{} {}", type_.get_display_name(), name);
-                }
+                _ => format!("{} {}", type_.get_display_name(), name),
             }
         }
 
@@ -617,10 +1829,7 @@ impl State {
             let return_type = some_or!(f.get_result_type(), continue);
             let is_decl = f.is_definition();
 
-            if storage == clang::StorageClass::None && !is_decl && name.starts_with("rte_pmd_") {
-                // non-static function definition for a PMD is found.
-                self.linkable_pmd_functions.push(name);
-            } else if storage == clang::StorageClass::Static && is_decl && !name.starts_with('_') {
+            if storage == clang::StorageClass::Static && is_decl && !name.starts_with('_') {
                 // Declaration of static function is found (skip if function name starts with _).
                 let mut arg_strings = Vec::new();
                 let mut param_strings = Vec::new();
@@ -652,6 +1861,10 @@ impl State {
                 self.static_functions.push(name.clone());
             }
         }
+        // `rte_errno` is a per-lcore (thread-local) variable, which Rust cannot access directly.
+        static_def_list.push(format!("int {prefix}rte_errno (void)", prefix = PREFIX));
+        static_impl_list.push("{ return rte_errno; }".into());
+        self.static_functions.push("rte_errno".into());
 
         let header_path = self.out_path.join("static.h");
         let header_template = self.project_path.join("gen/static.h.template");
@@ -666,61 +1879,19 @@ impl State {
             .map(|(def_, decl_)| format!("{}{}", def_, decl_))
             .join("\n");
 
-        // List of manually enabled DPDK PMDs
-        let mut linkable_whitelist: Vec<_> = vec![
-            "rte_pmd_ixgbe_set_all_queues_drop_en", // ixgbe
-            "rte_pmd_i40e_ping_vfs",                // i40e
-            "e1000_igb_init_log",                   // e1000
-            "ice_release_vsi",                      // ice
-            "vmxnet3_dev_tx_queue_release",         // vmxnet3
-            "virtio_dev_pause",                     // virtio
-            "softnic_thread_free",                  // softnic
-            // "ipn3ke_hw_tm_init", // ipn3ke (currently not enabled)
-            // "mlx4_fd_set_non_blocking", // mlx4 (currently not enabled)
-            // "mlx5_set_cksum_table", // mlx5 (currently not enabled)
-            "iavf_prep_pkts",                    // iavf
-            "fm10k_get_pcie_msix_count_generic", // fm10k
-        ]
-        .iter()
-        .map(|name| (*name).to_string())
-        .collect();
+        self.linkable_pmd_functions = self.find_pmd_constructors();
 
-        // List of non-static PMD-specific functions used to create symbolic dependencies to PMDs.
-        let mut linkable_extern_def_list: Vec<_> = vec![
-            "void e1000_igb_init_log(void)",                           // e1000
-            "int ice_release_vsi(struct ice_vsi *vsi)",                // ice
-            "void vmxnet3_dev_tx_queue_release(void *txq)",            // vmxnet3
-            "int virtio_dev_pause(struct rte_eth_dev *dev)",           // virtio
-            "void softnic_thread_free(struct pmd_internals *softnic)", // softnic
-            // "int ipn3ke_hw_tm_init(struct ipn3ke_hw *hw)", // ipn3ke (currently not enabled)
-            // "int mlx4_fd_set_non_blocking(int fd)", // mlx4 (currently not enabled)
-            // "void mlx5_set_cksum_table(void)", // mlx5 (currently not enabled)
-            "uint16_t iavf_prep_pkts(void *tx_queue, struct rte_mbuf **tx_pkts, uint16_t nb_pkts)", // iavf
-            "uint16_t fm10k_get_pcie_msix_count_generic(struct fm10k_hw *hw)", // fm10k
-        ]
-        .iter()
-        .map(|name| (*name).to_string())
-        .collect();
-
-        // If non-default net drivers are enabled (ex. MLX5), add their PMD to the list.
-        for link in &self.dpdk_links {
-            let libname = link.file_name().unwrap().to_str().unwrap();
-
-            if libname == "librte_pmd_mlx5.a" {
-                linkable_whitelist.push("mlx5_set_cksum_table".to_string());
-                linkable_extern_def_list.push("void mlx5_set_cksum_table(void)".to_string());
-                break;
-            }
-        }
-
-        // Currently, we use whitelist instead of extracted function list from DPDK library.  See
-        // `linkable_pmd_functions` field of `State` for more information.
-        self.linkable_pmd_functions = linkable_whitelist;
-
-        // Create `extern` definition for each symbol.
-        let linkable_extern_defs = linkable_extern_def_list
+        // Declare each symbol under an alias, as its C declaration (if any) is unknown.
+        let linkable_extern_defs = self
+            .linkable_pmd_functions
             .iter()
-            .map(|name| format!("extern {name};", name = name))
+            .map(|name| {
+                format!(
+                    "extern char {prefix}sym_{name} __asm__(\"{name}\");",
+                    prefix = PREFIX,
+                    name = name
+                )
+            })
             .join("\n");
 
         // Create explicit symbolic links to PMDs from `rust-dpdk-sys` rust library.  We will
@@ -730,7 +1901,7 @@ impl State {
             .iter()
             .map(|name| {
                 format!(
-                    "void* {prefix}{name}() {{\n\treturn {name};\n}}",
+                    "void* {prefix}{name}() {{\n\treturn &{prefix}sym_{name};\n}}",
                     prefix = PREFIX,
                     name = name
                 )
@@ -757,6 +1928,62 @@ impl State {
         target.write_fmt(format_args!("{}", &formatted_string)).ok();
     }
 
+    /// Find a global symbol of each object which has constructors in linked DPDK archives.
+    ///
+    /// Constructors are found by parsing objects in static archives (`.init_array` and `.ctors`
+    /// sections), like `nm` does. Shared builds do not link PMDs, thus no symbol is returned.
+    fn find_pmd_constructors(&self) -> Vec<String> {
+        let mut symbols = vec![];
+        if self.dynamic {
+            return symbols;
+        }
+        for link in &self.dpdk_links {
+            let archive_data = std::fs::read(link).unwrap();
+            let archive = some_or!(ArchiveFile::parse(&*archive_data).ok(), {
+                emit_generated(format!("warning=Cannot parse {}", link.display()));
+                continue;
+            });
+            for member in archive.members().filter_map(|member| member.ok()) {
+                let data = some_or!(member.data(&*archive_data).ok(), continue);
+                let object = some_or!(object::File::parse(data).ok(), continue);
+                let has_constructors = object.sections().any(|section| {
+                    let name = section.name().unwrap_or_default();
+                    (name.starts_with(".init_array") || name.starts_with(".ctors"))
+                        && section.size() > 0
+                });
+                if !has_constructors {
+                    continue;
+                }
+                // Prefer functions. Any defined global symbol pulls the object into the link.
+                let mut candidates = object
+                    .symbols()
+                    .filter(|symbol| symbol.is_global() && symbol.is_definition())
+                    .filter(|symbol| !symbol.is_weak())
+                    .filter_map(|symbol| {
+                        let name = symbol.name().ok()?;
+                        Some((symbol.kind() != SymbolKind::Text, name.to_string()))
+                    })
+                    // Names are used as C identifiers.
+                    .filter(|(_, name)| {
+                        !name.is_empty()
+                            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                            && !symbols.contains(name)
+                    })
+                    .collect::<Vec<_>>();
+                candidates.sort();
+                match candidates.into_iter().next() {
+                    Some((_, name)) => symbols.push(name),
+                    None => emit_generated(format!(
+                        "warning=Constructors of {}({}) cannot be linked explicitly",
+                        link.file_name().unwrap().to_str().unwrap(),
+                        String::from_utf8_lossy(member.name())
+                    )),
+                }
+            }
+        }
+        symbols
+    }
+
     /// Generate Rust bindings from DPDK source.
     fn generate_rust_def(&mut self) {
         let dpdk_config_path = self.dpdk_config.as_ref().unwrap();
@@ -769,13 +1996,28 @@ impl State {
         for path in &self.include_path {
             builder = builder.clang_arg(format!("-I{}", path.display()));
         }
+        // Functions of disabled families may be declared by transitively included headers.
+        for (feature, _, prefixes) in LIBRARY_FAMILIES {
+            if !feature_enabled(feature) {
+                for prefix in prefixes.iter() {
+                    builder = builder.blocklist_function(format!("{}(_.*)?", prefix));
+                }
+            }
+        }
         builder
             .clang_arg(format!("-I{}", self.out_path.to_str().unwrap()))
             .clang_arg("-imacros")
             .clang_arg(dpdk_config_path.to_str().unwrap())
-            .clang_arg("-march=native")
+            .clang_args(self.clang_target_args())
+            .clang_args(&self.dpdk_cflags)
             .clang_arg("-Wno-everything")
-            .rustfmt_bindings(true)
+            .formatter(bindgen::Formatter::Rustfmt)
+            // Enum values are exposed as associated constants of newtypes (e.g.
+            // `rte_eth_rx_mq_mode::ETH_MQ_RX_RSS`), which also accept values unknown to Rust.
+            .default_enum_style(bindgen::EnumVariation::NewType {
+                is_bitfield: false,
+                is_global: false,
+            })
             .opaque_type("max_align_t")
             .opaque_type("rte_event.*")
             .generate()
@@ -827,6 +2069,8 @@ impl State {
             formatted_string.replace("%explicit_use_defs%", &explicit_use_string);
         let formatted_string =
             formatted_string.replace("%explicit_invokes%", &explicit_invoke_string);
+        let formatted_string =
+            formatted_string.replace("%flag_types%", &self.flag_type_defs.join("\n\n"));
         let formatted_string = formatted_string.replace(
             "%static_eal_functions%",
             &self
@@ -837,9 +2081,9 @@ impl State {
         );
 
         if formatted_string.contains("rte_get_main_lcore") {
-            println!(r#"cargo:rustc-cfg=main_lcore_name="main""#);
+            emit_generated(r#"rustc-cfg=main_lcore_name="main""#.into());
         } else if formatted_string.contains("rte_get_master_lcore") {
-            println!(r#"cargo:rustc-cfg=main_lcore_name="master""#);
+            emit_generated(r#"rustc-cfg=main_lcore_name="master""#.into());
         } else {
             panic!("neither rte_get_main_lcore nor rte_get_master_lcore found");
         }
@@ -848,23 +2092,123 @@ impl State {
         target.write_fmt(format_args!("{}", formatted_string)).ok();
     }
 
+    /// Folder caching bindings generated from the current inputs.
+    ///
+    /// The cache is stored under `DPDK_BINDINGS_CACHE` if set, or next to build folders of the
+    /// target directory otherwise. Entries are keyed by a hash of DPDK version, include paths,
+    /// config header, linked libraries, templates, the build script, compiler flags and enabled
+    /// features.
+    fn bindings_cache_dir(&self) -> PathBuf {
+        let cache_root = match env::var("DPDK_BINDINGS_CACHE") {
+            Ok(path) => PathBuf::from(path),
+            // `target/<profile>/build/dpdk-sys-<hash>/out` -> `target/<profile>/build`
+            Err(_) => self
+                .out_path
+                .ancestors()
+                .nth(2)
+                .unwrap()
+                .join("dpdk-sys-bindings"),
+        };
+
+        let mut hasher = DefaultHasher::new();
+        include_str!("build.rs").hash(&mut hasher);
+        self.dpdk_version.hash(&mut hasher);
+        self.include_path.hash(&mut hasher);
+        std::fs::read(self.dpdk_config.as_ref().unwrap())
+            .unwrap()
+            .hash(&mut hasher);
+        // Constructors are found in linked archives.
+        for link in &self.dpdk_links {
+            link.hash(&mut hasher);
+            link.metadata()
+                .map(|metadata| metadata.len())
+                .ok()
+                .hash(&mut hasher);
+        }
+        let mut templates = self
+            .project_path
+            .join("gen")
+            .read_dir()
+            .expect("read_dir failed")
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect::<Vec<_>>();
+        templates.sort();
+        for template in templates {
+            template.file_name().hash(&mut hasher);
+            std::fs::read(&template).unwrap().hash(&mut hasher);
+        }
+        self.clang_target_args().hash(&mut hasher);
+        self.dpdk_cflags.hash(&mut hasher);
+        self.dynamic.hash(&mut hasher);
+        let mut features = env::vars()
+            .map(|(key, _)| key)
+            .filter(|key| key.starts_with("CARGO_FEATURE_"))
+            .collect::<Vec<_>>();
+        features.sort();
+        features.hash(&mut hasher);
+
+        cache_root.join(format!("{:016x}", hasher.finish()))
+    }
+
+    /// Copy generated bindings from the given folder and replay their cargo directives.
+    ///
+    /// Returns false if the folder does not contain generated bindings.
+    fn load_bindings(&self, dir: &Path) -> bool {
+        let directives_path = dir.join("directives.txt");
+        if !directives_path.exists() || GENERATED_FILES.iter().any(|file| !dir.join(file).exists())
+        {
+            return false;
+        }
+        for file in GENERATED_FILES {
+            std::fs::copy(dir.join(file), self.out_path.join(file)).unwrap();
+        }
+        for directive in std::fs::read_to_string(directives_path).unwrap().lines() {
+            println!("cargo:{}", directive);
+        }
+        eprintln!("Reusing bindings in {}", dir.display());
+        true
+    }
+
+    /// Store generated bindings to the given folder.
+    fn store_bindings(&self, dir: &Path) {
+        // Concurrent builds may store the same entry. Write to a temporary folder first and move
+        // it in place.
+        let temp_dir = dir.with_extension(format!("tmp{}", std::process::id()));
+        create_dir_all(&temp_dir).unwrap();
+        for file in GENERATED_FILES {
+            std::fs::copy(self.out_path.join(file), temp_dir.join(file)).unwrap();
+        }
+        let directives = GENERATED_DIRECTIVES.lock().unwrap().join("\n");
+        std::fs::write(temp_dir.join("directives.txt"), directives).unwrap();
+        if rename(&temp_dir, dir).is_err() {
+            remove_dir_all(&temp_dir).ok();
+        }
+    }
+
     /// Do compile.
     fn compile(&mut self) {
         let dpdk_config = self.dpdk_config.as_ref().unwrap();
         let source_path = self.out_path.join("static.c");
         let lib_path = self.library_path.as_ref().unwrap();
 
-        cc::Build::new()
+        let mut build = cc::Build::new();
+        build
             .file(source_path)
-            .static_flag(true)
-            .shared_flag(false)
             .opt_level(3)
             .includes(&self.include_path)
             .include(&self.out_path)
             .flag("-w") // hide warnings
-            .flag("-march=native")
+            .flag(&self.cpu_flag)
             .flag("-imacros")
-            .flag(dpdk_config.to_str().unwrap())
+            .flag(dpdk_config.to_str().unwrap());
+        // `cc` selects the cross compiler and its target by itself.
+        if let Some(sysroot) = &self.sysroot {
+            build.flag(&format!("--sysroot={}", sysroot.display()));
+        }
+        for flag in &self.dpdk_cflags {
+            build.flag(flag);
+        }
+        build
             .flag(&format!("-L{}", lib_path.to_str().unwrap()))
             .flag("-ldpdk")
             .compile("lib_static_wrapper.a");
@@ -874,8 +2218,16 @@ impl State {
             lib_path.to_str().unwrap()
         );
 
+        if !self.link_directives.is_empty() {
+            for directive in &self.link_directives {
+                println!("cargo:rustc-link-lib={}", directive);
+            }
+            return;
+        }
+
         // Legacy mode: Rust cargo cannot recognize library groups (libdpdk.a).
-        let format = Regex::new(r"lib(.*)\.(a)").unwrap();
+        let format = Regex::new(r"lib(.*)\.(a|so)$").unwrap();
+        let kind = if self.dynamic { "dylib" } else { "static" };
         for link in &self.dpdk_links {
             let lib_name = link.file_name().unwrap().to_str().unwrap();
 
@@ -888,7 +2240,7 @@ impl State {
                     println!("cargo:rustc-link-lib=ibverbs");
                     println!("cargo:rustc-link-lib=mlx5");
                 }
-                println!("cargo:rustc-link-lib=static={}", link_name);
+                println!("cargo:rustc-link-lib={}={}", kind, link_name);
             }
         }
         println!("cargo:rustc-link-lib=numa");
@@ -896,19 +2248,62 @@ impl State {
 }
 
 fn main() {
+    emit_check_cfgs();
+    if env::var("CARGO_FEATURE_MOCK").is_ok() {
+        // The mock backend is pure Rust and does not need DPDK. It mimics the API of the release
+        // given by `DPDK_MOCK_VERSION` ("YY.MM", 20.11 by default).
+        println!("cargo:rerun-if-env-changed=DPDK_MOCK_VERSION");
+        let version = env::var("DPDK_MOCK_VERSION").unwrap_or_else(|_| "20.11".to_string());
+        let release = version
+            .split_once('.')
+            .and_then(|(year, month)| Some((year.parse().ok()?, month.parse().ok()?)))
+            .filter(|release| KNOWN_RELEASES.contains(release) && *release >= (20, 11))
+            .unwrap_or_else(|| panic!("Unsupported DPDK_MOCK_VERSION {}", version));
+        println!(r#"cargo:rustc-cfg=main_lcore_name="main""#);
+        emit_version_cfgs((release.0, release.1, 0));
+        // No library family is installed, but `DEP_DPDK_LIBS` is still defined (empty).
+        println!("cargo:libs=");
+        return;
+    }
     let mut state = State::new();
-    let dpdk_install_path = env::var("DPDK_INSTALL_PATH").ok();
+    state.check_os();
+    state.check_target();
+    let dpdk_install_path = if feature_enabled("vendored") {
+        Some(state.build_vendored_dpdk().to_str().unwrap().to_owned())
+    } else {
+        env::var("DPDK_INSTALL_PATH").ok()
+    };
     if dpdk_install_path.is_none() {
         eprintln!("Path to DPDK install path not specified (as env variable DPDK_INSTALL_PATH), attempting system path");
     }
-    state.check_os();
-    state.check_compiler();
     state.find_dpdk(&dpdk_install_path);
+    state.find_dpdk_version();
     state.find_link_libs();
-    state.make_all_in_one_header();
-    state.extract_eal_apis();
-    state.generate_static_impls_and_link_pmds();
-    state.generate_rust_def();
-    state.generate_lib_rs();
+
+    println!("cargo:rerun-if-env-changed=DPDK_BINDINGS_DIR");
+    println!("cargo:rerun-if-env-changed=DPDK_BINDINGS_CACHE");
+    if let Ok(bindings_dir) = env::var("DPDK_BINDINGS_DIR") {
+        // Pre-generated bindings do not require libclang.
+        let bindings_dir = PathBuf::from(bindings_dir);
+        println!("cargo:rerun-if-changed={}", bindings_dir.display());
+        if !state.load_bindings(&bindings_dir) {
+            panic!(
+                "Cannot find pre-generated bindings in {}",
+                bindings_dir.display()
+            );
+        }
+    } else {
+        let cache_dir = state.bindings_cache_dir();
+        if !state.load_bindings(&cache_dir) {
+            state.check_compiler();
+            state.make_all_in_one_header();
+            state.extract_flag_types();
+            state.generate_static_impls_and_link_pmds();
+            state.extract_eal_apis();
+            state.generate_rust_def();
+            state.generate_lib_rs();
+            state.store_bindings(&cache_dir);
+        }
+    }
     state.compile();
 }
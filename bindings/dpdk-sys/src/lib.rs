@@ -5,20 +5,51 @@
 //! Currently, build.rs cannot configure linker options, thus, a user must set RUSTFLAGS env
 //! variable as this library's panic message says.
 
+//!
+//! With the `mock` feature, DPDK is replaced with a pure-Rust software backend implementing the
+//! subset of the API used by the `dpdk` crate. See the `mock` module for details.
+
+#[cfg(not(feature = "mock"))]
 #[allow(warnings, clippy)]
 mod dpdk;
+#[cfg(not(feature = "mock"))]
 pub use dpdk::*;
 
+#[cfg(not(feature = "mock"))]
 #[link(name = "bsd")]
 extern "C" {}
 
+#[cfg(not(feature = "mock"))]
 #[link(name = "pcap")]
 extern "C" {}
 
+#[cfg(not(feature = "mock"))]
 include!(concat!(env!("OUT_DIR"), "/lib.rs"));
 
-/// Thin compatibility layer for items which names changed between dpdk 19 and 20 versions. This
-/// allows dowstream crates to compile on both versions.
+#[cfg(feature = "mock")]
+mod mock;
+#[cfg(feature = "mock")]
+pub use mock::*;
+
+/// Folder of PMD plugins of the shared DPDK install found at build time (`dynamic` feature).
+///
+/// PMDs are not linked to the application in shared builds. Pass this folder to EAL with the `-d`
+/// option if the install's default plugin path (`RTE_EAL_PMD_PATH`) is not usable.
+pub const PMD_PATH: Option<&str> = option_env!("DPDK_PMD_PATH");
+
+/// Version of the DPDK install found at build time (e.g. `"20.11.0"`).
+///
+/// The release is also exposed as cfgs: `dpdk_version = "YY.MM"` for the exact release and
+/// `dpdk_version_ge = "YY.MM"` for every known release not newer than it.
+pub const DPDK_VERSION: &str = env!("DPDK_VERSION");
+
+/// Thin compatibility layer for items which names changed between dpdk releases. This allows
+/// dowstream crates to compile on all supported versions (20.11 through the latest LTS).
+///
+/// Items are exported with the names of the latest release. Renamed enum values are added to the
+/// enum types as associated constants (e.g. `rte_eth_rx_mq_mode::RTE_ETH_MQ_RX_RSS`). Flags are
+/// exposed as `bitflags` types (`RxOffload`, `TxOffload`, `RssHf` and `MbufFlags`) whose flag
+/// names do not depend on the release.
 pub mod compat {
     #[cfg(main_lcore_name = "main")]
     pub use super::rte_get_main_lcore;
@@ -26,12 +57,52 @@ pub mod compat {
     pub use super::rte_get_master_lcore as rte_get_main_lcore;
 
     #[cfg(main_lcore_name = "main")]
-    pub use super::rte_rmt_call_main_t_SKIP_MAIN;
+    pub use super::rte_rmt_call_main_t;
     #[cfg(main_lcore_name = "master")]
-    pub use super::rte_rmt_call_master_t_SKIP_MASTER as rte_rmt_call_main_t_SKIP_MAIN;
+    pub use super::rte_rmt_call_master_t as rte_rmt_call_main_t;
 
-    #[cfg(main_lcore_name = "main")]
-    pub use super::rte_rmt_call_main_t_CALL_MAIN;
     #[cfg(main_lcore_name = "master")]
-    pub use super::rte_rmt_call_master_t_CALL_MASTER as rte_rmt_call_main_t_CALL_MAIN;
+    impl rte_rmt_call_main_t {
+        pub const SKIP_MAIN: Self = Self::SKIP_MASTER;
+        pub const CALL_MAIN: Self = Self::CALL_MASTER;
+    }
+
+    // Ethdev items were prefixed with `RTE_ETH_` in 21.11.
+
+    /// Link is up (`rte_eth_link::link_status`).
+    #[cfg(not(dpdk_version_ge = "21.11"))]
+    pub const RTE_ETH_LINK_UP: u16 = super::ETH_LINK_UP as u16;
+    /// Link is up (`rte_eth_link::link_status`).
+    #[cfg(dpdk_version_ge = "21.11")]
+    pub const RTE_ETH_LINK_UP: u16 = super::RTE_ETH_LINK_UP as u16;
+
+    #[cfg(not(dpdk_version_ge = "21.11"))]
+    impl super::rte_eth_rx_mq_mode {
+        pub const RTE_ETH_MQ_RX_NONE: Self = Self::ETH_MQ_RX_NONE;
+        pub const RTE_ETH_MQ_RX_RSS: Self = Self::ETH_MQ_RX_RSS;
+    }
+
+    #[cfg(not(dpdk_version_ge = "21.11"))]
+    impl super::rte_eth_tx_mq_mode {
+        pub const RTE_ETH_MQ_TX_NONE: Self = Self::ETH_MQ_TX_NONE;
+    }
+
+    /// Set the maximum size of frames received by a port, including Ethernet header and CRC.
+    ///
+    /// Before 21.11, this is `rxmode.max_rx_pkt_len` (which requires the jumbo frame offload if
+    /// larger than a standard frame). Since 21.11, the limit is configured as `rxmode.mtu`.
+    #[inline]
+    pub fn set_max_rx_frame_len(conf: &mut super::rte_eth_conf, len: u32) {
+        #[cfg(not(dpdk_version_ge = "21.11"))]
+        {
+            conf.rxmode.max_rx_pkt_len = len;
+            if len > super::RTE_ETHER_MAX_LEN {
+                conf.rxmode.offloads |= super::RxOffload::JUMBO_FRAME.bits();
+            }
+        }
+        #[cfg(dpdk_version_ge = "21.11")]
+        {
+            conf.rxmode.mtu = len - super::RTE_ETHER_HDR_LEN - super::RTE_ETHER_CRC_LEN;
+        }
+    }
 }
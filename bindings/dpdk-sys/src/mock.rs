@@ -0,0 +1,2611 @@
+//! Pure-Rust software backend used instead of DPDK when the `mock` feature is enabled.
+//!
+//! It implements the small subset of DPDK's API used by the `dpdk` crate with the same names and
+//! signatures as the generated bindings: EAL initialization, lcores, mempools, rings, and
+//! ethdev ports backed by in-memory queues. This allows building, documenting and testing
+//! downstream crates on machines without a DPDK install.
+//!
+//! Supported virtual devices (via `--vdev`, `rte_dev_probe` or `rte_eal_hotplug_add`):
+//! - `net_null*`: rx generates 64-byte packets, tx frees every packet.
+//! - `net_ring*`: tx queue `i` loops back to rx queue `i`.
+//! - `net_pcap*`: rx queue `i` replays the `i`-th `rx_pcap=` file (classic pcap format), and tx
+//!   queue `i` writes to the `i`-th `tx_pcap=` file. Interfaces are not supported.
+//! - `net_tap*`: creates the interface `iface=` (`dtapN` by default). Its rx queues support
+//!   interrupts.
+//! - `net_af_packet*`: attaches to the interface `iface=` of a `net_tap` port. Tx queue `i` of a
+//!   port on an interface delivers to rx queue `i` of the other port on it.
+//!
+//! Ports created by `rte_eth_from_rings` read from and write to the given rings.
+//!
+//! The process type is selected with `--proc-type`, but there is a single process: secondaries
+//! share the state of the mock EAL. IPC requests (`rte_mp_request_sync`) are handled by the
+//! actions registered in the process itself, which stands for its peer.
+#![allow(
+    non_camel_case_types,
+    non_upper_case_globals,
+    clippy::missing_safety_doc
+)]
+
+use std::alloc::{self, Layout};
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::convert::TryInto;
+use std::ffi::{CStr, CString};
+use std::fs::File;
+use std::io::Write;
+use std::mem::size_of;
+use std::os::raw::{c_char, c_int, c_uint, c_void};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::UnixStream;
+use std::ptr;
+use std::sync::atomic::{AtomicU16, AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant, SystemTime};
+
+pub const EPERM: u32 = 1;
+pub const ENOENT: u32 = 2;
+pub const EIO: u32 = 5;
+pub const ENOMEM: u32 = 12;
+pub const EEXIST: u32 = 17;
+pub const ENODEV: u32 = 19;
+pub const EINVAL: u32 = 22;
+pub const ENOSPC: u32 = 28;
+pub const ENOTSUP: u32 = 95;
+pub const ETIMEDOUT: u32 = 110;
+pub const EALREADY: u32 = 114;
+
+pub const RTE_MAX_LCORE: u32 = 128;
+pub const RTE_MAX_ETHPORTS: u32 = 32;
+pub const RTE_ETHDEV_QUEUE_STAT_CNTRS: u32 = 16;
+pub const RTE_PKTMBUF_HEADROOM: u32 = 128;
+pub const RTE_CACHE_LINE_SIZE: u32 = 64;
+pub const RTE_ETHER_MAX_LEN: u32 = 1518;
+pub const RTE_ETHER_HDR_LEN: u32 = 14;
+pub const RTE_ETHER_CRC_LEN: u32 = 4;
+pub const RTE_ETHER_MTU: u32 = 1500;
+pub const RTE_MEMPOOL_NAMESIZE: u32 = 32;
+pub const RTE_RING_NAMESIZE: u32 = 32;
+pub const RTE_ETH_NAME_MAX_LEN: u32 = 64;
+pub const RTE_ETH_MAX_OWNER_NAME_LEN: u32 = 64;
+pub const SOCKET_ID_ANY: i32 = -1;
+pub const RTE_EPOLL_PER_THREAD: i32 = -1;
+pub const RTE_INTR_EVENT_ADD: u32 = 1;
+pub const RTE_INTR_EVENT_DEL: u32 = 2;
+#[cfg(not(dpdk_version_ge = "21.11"))]
+pub const ETH_LINK_DOWN: u32 = 0;
+#[cfg(not(dpdk_version_ge = "21.11"))]
+pub const ETH_LINK_UP: u32 = 1;
+#[cfg(dpdk_version_ge = "21.11")]
+pub const RTE_ETH_LINK_DOWN: u32 = 0;
+#[cfg(dpdk_version_ge = "21.11")]
+pub const RTE_ETH_LINK_UP: u32 = 1;
+pub const RTE_MP_MAX_FD_NUM: u32 = 8;
+pub const RTE_MP_MAX_NAME_LEN: u32 = 64;
+pub const RTE_MP_MAX_PARAM_LEN: u32 = 256;
+
+bitflags::bitflags! {
+    /// Rx offload capabilities and configuration (`RTE_ETH_RX_OFFLOAD_*`).
+    #[repr(transparent)]
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct RxOffload: u64 {
+        const IPV4_CKSUM = 0x2;
+        const UDP_CKSUM = 0x4;
+        const TCP_CKSUM = 0x8;
+        const JUMBO_FRAME = 0x800;
+    }
+}
+
+bitflags::bitflags! {
+    /// Tx offload capabilities and configuration (`RTE_ETH_TX_OFFLOAD_*`).
+    #[repr(transparent)]
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct TxOffload: u64 {
+        const IPV4_CKSUM = 0x2;
+        const UDP_CKSUM = 0x4;
+        const TCP_CKSUM = 0x8;
+    }
+}
+
+bitflags::bitflags! {
+    /// RSS hash functions (`RTE_ETH_RSS_*`).
+    #[repr(transparent)]
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct RssHf: u64 {
+        const NONFRAG_IPV4_TCP = 1 << 4;
+        const NONFRAG_IPV4_UDP = 1 << 5;
+    }
+}
+
+bitflags::bitflags! {
+    /// Offload features of mbufs (`rte_mbuf::ol_flags`) (`RTE_MBUF_F_*`).
+    #[repr(transparent)]
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct MbufFlags: u64 {
+        const RX_L4_CKSUM_BAD = 1 << 3;
+        const RX_IP_CKSUM_BAD = 1 << 4;
+        const TX_TCP_CKSUM = 1 << 52;
+        const TX_UDP_CKSUM = 3 << 52;
+        const TX_IP_CKSUM = 1 << 54;
+        const TX_IPV4 = 1 << 55;
+        const EXTERNAL = 1 << 61;
+        const INDIRECT = 1 << 62;
+    }
+}
+
+#[repr(transparent)]
+#[derive(Debug, Default, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct rte_eth_rx_mq_mode(pub c_uint);
+#[cfg(not(dpdk_version_ge = "21.11"))]
+impl rte_eth_rx_mq_mode {
+    pub const ETH_MQ_RX_NONE: rte_eth_rx_mq_mode = rte_eth_rx_mq_mode(0);
+    pub const ETH_MQ_RX_RSS: rte_eth_rx_mq_mode = rte_eth_rx_mq_mode(1);
+}
+#[cfg(dpdk_version_ge = "21.11")]
+impl rte_eth_rx_mq_mode {
+    pub const RTE_ETH_MQ_RX_NONE: rte_eth_rx_mq_mode = rte_eth_rx_mq_mode(0);
+    pub const RTE_ETH_MQ_RX_RSS: rte_eth_rx_mq_mode = rte_eth_rx_mq_mode(1);
+}
+
+#[repr(transparent)]
+#[derive(Debug, Default, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct rte_eth_tx_mq_mode(pub c_uint);
+#[cfg(not(dpdk_version_ge = "21.11"))]
+impl rte_eth_tx_mq_mode {
+    pub const ETH_MQ_TX_NONE: rte_eth_tx_mq_mode = rte_eth_tx_mq_mode(0);
+}
+#[cfg(dpdk_version_ge = "21.11")]
+impl rte_eth_tx_mq_mode {
+    pub const RTE_ETH_MQ_TX_NONE: rte_eth_tx_mq_mode = rte_eth_tx_mq_mode(0);
+}
+
+#[repr(transparent)]
+#[derive(Debug, Default, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct rte_rmt_call_main_t(pub c_uint);
+impl rte_rmt_call_main_t {
+    pub const SKIP_MAIN: rte_rmt_call_main_t = rte_rmt_call_main_t(0);
+    pub const CALL_MAIN: rte_rmt_call_main_t = rte_rmt_call_main_t(1);
+}
+
+#[repr(transparent)]
+#[derive(Debug, Default, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct rte_proc_type_t(pub c_int);
+impl rte_proc_type_t {
+    pub const RTE_PROC_AUTO: rte_proc_type_t = rte_proc_type_t(-1);
+    pub const RTE_PROC_PRIMARY: rte_proc_type_t = rte_proc_type_t(0);
+    pub const RTE_PROC_SECONDARY: rte_proc_type_t = rte_proc_type_t(1);
+    pub const RTE_PROC_INVALID: rte_proc_type_t = rte_proc_type_t(2);
+}
+
+/// Length of packets generated by `net_null` ports.
+const NULL_PACKET_SIZE: u16 = 64;
+/// Capacity of each loopback queue of `net_ring` ports, and of each rx queue of interface ports.
+const LOOPBACK_QUEUE_SIZE: usize = 1024;
+
+/// A raw pointer which can be stored in global state.
+struct SendPtr<T>(*mut T);
+// Safety: all accesses to pointees are serialized by the global lock or owned by a single thread.
+unsafe impl<T> Send for SendPtr<T> {}
+
+impl<T> Clone for SendPtr<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T> Copy for SendPtr<T> {}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct rte_mbuf {
+    pub buf_addr: *mut c_void,
+    pub buf_iova: u64,
+    pub data_off: u16,
+    pub refcnt: u16,
+    pub nb_segs: u16,
+    pub port: u16,
+    pub ol_flags: u64,
+    pub packet_type: u32,
+    pub pkt_len: u32,
+    pub data_len: u16,
+    pub vlan_tci: u16,
+    pub buf_len: u16,
+    pub priv_size: u16,
+    pub pool: *mut rte_mempool,
+    pub next: *mut rte_mbuf,
+    pub shinfo: *mut rte_mbuf_ext_shared_info,
+}
+
+pub type rte_mbuf_extbuf_free_callback_t =
+    Option<unsafe extern "C" fn(addr: *mut c_void, opaque: *mut c_void)>;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct rte_mbuf_ext_shared_info {
+    pub free_cb: rte_mbuf_extbuf_free_callback_t,
+    pub fcb_opaque: *mut c_void,
+    pub refcnt: u16,
+}
+
+/// Software mempool. Objects are individually allocated when the pool is populated.
+///
+/// Every mempool ops behaves as a stack.
+#[derive(Debug)]
+pub struct rte_mempool {
+    name: String,
+    size: u32,
+    elt_size: u32,
+    ops: String,
+    /// Private data and data room sizes of mbufs, if this is a packet pool.
+    priv_size: u16,
+    data_room_size: u16,
+    objects: Vec<*mut c_void>,
+    free: Mutex<Vec<*mut c_void>>,
+}
+
+pub type rte_mempool_ctor_t = Option<unsafe extern "C" fn(mp: *mut rte_mempool, arg: *mut c_void)>;
+pub type rte_mempool_obj_cb_t = Option<
+    unsafe extern "C" fn(
+        mp: *mut rte_mempool,
+        opaque: *mut c_void,
+        obj: *mut c_void,
+        obj_idx: c_uint,
+    ),
+>;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct rte_mp_msg {
+    pub name: [c_char; RTE_MP_MAX_NAME_LEN as usize],
+    pub len_param: c_int,
+    pub num_fds: c_int,
+    pub param: [u8; RTE_MP_MAX_PARAM_LEN as usize],
+    pub fds: [c_int; RTE_MP_MAX_FD_NUM as usize],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct rte_mp_reply {
+    pub nb_sent: c_int,
+    pub nb_received: c_int,
+    pub msgs: *mut rte_mp_msg,
+}
+
+pub type rte_mp_t =
+    Option<unsafe extern "C" fn(msg: *const rte_mp_msg, peer: *const c_void) -> c_int>;
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct timespec {
+    pub tv_sec: i64,
+    pub tv_nsec: i64,
+}
+
+/// In-memory stream of `open_memstream`.
+#[derive(Debug)]
+pub struct FILE {
+    buf: Vec<u8>,
+    bufloc: *mut *mut c_char,
+    sizeloc: *mut usize,
+}
+
+/// Software ring of pointers.
+#[derive(Debug)]
+pub struct rte_ring {
+    name: String,
+    capacity: u32,
+    queue: Mutex<VecDeque<*mut c_void>>,
+}
+
+#[derive(Debug)]
+pub struct rte_device {
+    port_id: u16,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct rte_cpuset_t {
+    pub __bits: [u64; 16],
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct rte_ether_addr {
+    pub addr_bytes: [u8; 6],
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct rte_eth_stats {
+    pub ipackets: u64,
+    pub opackets: u64,
+    pub ibytes: u64,
+    pub obytes: u64,
+    pub imissed: u64,
+    pub ierrors: u64,
+    pub oerrors: u64,
+    pub rx_nombuf: u64,
+    pub q_ipackets: [u64; 16],
+    pub q_opackets: [u64; 16],
+    pub q_ibytes: [u64; 16],
+    pub q_obytes: [u64; 16],
+    pub q_errors: [u64; 16],
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct rte_eth_link {
+    pub link_speed: u32,
+    link_status: u16,
+}
+
+impl rte_eth_link {
+    #[inline]
+    pub fn link_status(&self) -> u16 {
+        self.link_status
+    }
+
+    #[inline]
+    pub fn set_link_status(&mut self, val: u16) {
+        self.link_status = val;
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct rte_eth_dev_owner {
+    pub id: u64,
+    pub name: [c_char; 64],
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct rte_eth_rxmode {
+    pub mq_mode: rte_eth_rx_mq_mode,
+    #[cfg(not(dpdk_version_ge = "21.11"))]
+    pub max_rx_pkt_len: u32,
+    #[cfg(dpdk_version_ge = "21.11")]
+    pub mtu: u32,
+    pub max_lro_pkt_size: u32,
+    pub split_hdr_size: u16,
+    pub offloads: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct rte_eth_txmode {
+    pub mq_mode: rte_eth_tx_mq_mode,
+    pub offloads: u64,
+    pub pvid: u16,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct rte_eth_rss_conf {
+    pub rss_key: *mut u8,
+    pub rss_key_len: u8,
+    pub rss_hf: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct rte_eth_rx_adv_conf {
+    pub rss_conf: rte_eth_rss_conf,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct rte_eth_conf {
+    pub link_speeds: u32,
+    pub rxmode: rte_eth_rxmode,
+    pub txmode: rte_eth_txmode,
+    pub lpbk_mode: u32,
+    pub rx_adv_conf: rte_eth_rx_adv_conf,
+    pub intr_conf: rte_intr_conf,
+}
+
+/// Interrupt flags, which are bitfields accessed through methods in bindgen's output.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct rte_intr_conf {
+    bits: u32,
+}
+
+impl rte_intr_conf {
+    pub fn rxq(&self) -> u32 {
+        (self.bits >> 1) & 1
+    }
+
+    pub fn set_rxq(&mut self, val: u32) {
+        self.bits = (self.bits & !2) | ((val & 1) << 1);
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct rte_eth_rxconf {
+    pub rx_free_thresh: u16,
+    pub rx_drop_en: u8,
+    pub rx_deferred_start: u8,
+    pub offloads: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct rte_eth_txconf {
+    pub tx_rs_thresh: u16,
+    pub tx_free_thresh: u16,
+    pub tx_deferred_start: u8,
+    pub offloads: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct rte_eth_dev_info {
+    pub device: *mut rte_device,
+    pub driver_name: *const c_char,
+    pub if_index: c_uint,
+    pub min_mtu: u16,
+    pub max_mtu: u16,
+    pub min_rx_bufsize: u32,
+    pub max_rx_pktlen: u32,
+    pub max_rx_queues: u16,
+    pub max_tx_queues: u16,
+    pub rx_offload_capa: u64,
+    pub tx_offload_capa: u64,
+    pub default_rxconf: rte_eth_rxconf,
+    pub default_txconf: rte_eth_txconf,
+}
+
+/// Kind of a mock ethdev port.
+enum PortKind {
+    Null,
+    Loopback(Vec<VecDeque<SendPtr<rte_mbuf>>>),
+    Rings {
+        rx: Vec<SendPtr<rte_ring>>,
+        tx: Vec<SendPtr<rte_ring>>,
+    },
+    Pcap {
+        /// Per rx queue, frames of its file and the index of the next one.
+        rx: Vec<(Vec<Vec<u8>>, usize)>,
+        tx: Vec<File>,
+        infinite_rx: bool,
+    },
+    /// `net_tap` or `net_af_packet` port on an interface, receiving what its peer transmits.
+    Iface {
+        driver: &'static [u8],
+        iface: String,
+        rx: Vec<VecDeque<SendPtr<rte_mbuf>>>,
+    },
+}
+
+struct MockPort {
+    name: String,
+    kind: PortKind,
+    device: Box<rte_device>,
+    owner_id: u64,
+    started: bool,
+    promiscuous: bool,
+    mtu: u16,
+    nb_rx_queues: u16,
+    nb_tx_queues: u16,
+    rx_pools: Vec<SendPtr<rte_mempool>>,
+    callbacks: Vec<SendPtr<rte_eth_rxtx_callback>>,
+    /// Whether rx interrupts are configured, and per rx queue, whether they are enabled and the
+    /// thread whose epoll instance waits for them.
+    rx_intr: bool,
+    rx_intr_enabled: Vec<bool>,
+    rx_intr_threads: Vec<Option<ThreadId>>,
+    /// Per rx queue, sockets standing for interrupt event fds (read end, write end).
+    rx_intr_fds: Vec<Option<(UnixStream, UnixStream)>>,
+    stats: rte_eth_stats,
+}
+
+const MAX_QUEUES: u16 = 16;
+const DRIVER_NULL: &[u8] = b"net_null\0";
+const DRIVER_RING: &[u8] = b"net_ring\0";
+const DRIVER_PCAP: &[u8] = b"net_pcap\0";
+const DRIVER_TAP: &[u8] = b"net_tap\0";
+const DRIVER_AF_PACKET: &[u8] = b"net_af_packet\0";
+
+/// Global state of the mock EAL.
+struct MockState {
+    initialized: bool,
+    lcores: Vec<u32>,
+    ports: Vec<Option<MockPort>>,
+    mempools: Vec<SendPtr<rte_mempool>>,
+    rings: Vec<SendPtr<rte_ring>>,
+    next_owner_id: u64,
+    proc_type: rte_proc_type_t,
+    mp_actions: Vec<(String, rte_mp_t)>,
+    /// Messages of `rte_mp_reply::msgs`, which are freed by `free`.
+    mp_replies: Vec<SendPtr<rte_mp_msg>>,
+}
+
+static STATE: Mutex<MockState> = Mutex::new(MockState {
+    initialized: false,
+    lcores: Vec::new(),
+    ports: Vec::new(),
+    mempools: Vec::new(),
+    rings: Vec::new(),
+    next_owner_id: 1,
+    proc_type: rte_proc_type_t::RTE_PROC_PRIMARY,
+    mp_actions: Vec::new(),
+    mp_replies: Vec::new(),
+});
+
+fn state() -> MutexGuard<'static, MockState> {
+    STATE
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Run `f` on a valid port, or return `-ENODEV`.
+fn with_port(port_id: u16, f: impl FnOnce(&mut MockPort) -> c_int) -> c_int {
+    match state().ports.get_mut(port_id as usize) {
+        Some(Some(port)) => f(port),
+        _ => -(ENODEV as c_int),
+    }
+}
+
+thread_local! {
+    static RTE_ERRNO: Cell<c_int> = const { Cell::new(0) };
+}
+
+/// Set `rte_errno` of the calling thread and return a null pointer, like failing DPDK functions.
+fn null_with_errno<T>(errno: u32) -> *mut T {
+    RTE_ERRNO.with(|rte_errno| rte_errno.set(errno as c_int));
+    ptr::null_mut()
+}
+
+/// Returns `rte_errno` of the calling thread.
+pub unsafe extern "C" fn rte_errno() -> c_int {
+    RTE_ERRNO.with(Cell::get)
+}
+
+unsafe fn c_str<'a>(s: *const c_char) -> &'a str {
+    if s.is_null() {
+        ""
+    } else {
+        CStr::from_ptr(s).to_str().unwrap_or("")
+    }
+}
+
+/// Add a port to the global state and return its id.
+fn add_port(st: &mut MockState, name: &str, kind: PortKind) -> c_int {
+    if st.ports.iter().flatten().any(|port| port.name == name) {
+        return -(EEXIST as c_int);
+    }
+    let port_id = match st.ports.iter().position(Option::is_none) {
+        Some(port_id) => port_id,
+        None if st.ports.len() < RTE_MAX_ETHPORTS as usize => {
+            st.ports.push(None);
+            st.ports.len() - 1
+        }
+        None => return -(ENOSPC as c_int),
+    };
+    st.ports[port_id] = Some(MockPort {
+        name: name.to_owned(),
+        kind,
+        device: Box::new(rte_device {
+            port_id: port_id as u16,
+        }),
+        owner_id: 0,
+        started: false,
+        promiscuous: false,
+        mtu: RTE_ETHER_MTU as u16,
+        nb_rx_queues: 0,
+        nb_tx_queues: 0,
+        rx_pools: Vec::new(),
+        callbacks: Vec::new(),
+        rx_intr: false,
+        rx_intr_enabled: Vec::new(),
+        rx_intr_threads: Vec::new(),
+        rx_intr_fds: Vec::new(),
+        stats: Default::default(),
+    });
+    port_id as c_int
+}
+
+/// Create a virtual device from its name (e.g. `net_null0`) and driver arguments.
+fn create_vdev(st: &mut MockState, name: &str, args: &str) -> c_int {
+    if name.starts_with("net_null") {
+        add_port(st, name, PortKind::Null)
+    } else if name.starts_with("net_ring") {
+        let queues = (0..MAX_QUEUES).map(|_| VecDeque::new()).collect();
+        add_port(st, name, PortKind::Loopback(queues))
+    } else if name.starts_with("net_pcap") {
+        match pcap_port(args) {
+            Ok(kind) => add_port(st, name, kind),
+            Err(err) => -(err as c_int),
+        }
+    } else if name.starts_with("net_tap") {
+        let taps = st
+            .ports
+            .iter()
+            .flatten()
+            .filter(
+                |port| matches!(port.kind, PortKind::Iface { driver, .. } if driver == DRIVER_TAP),
+            )
+            .count();
+        let iface = match iface_arg(args) {
+            Ok(iface) => iface.unwrap_or_else(|| format!("dtap{}", taps)),
+            Err(err) => return -(err as c_int),
+        };
+        if iface_exists(st, &iface) {
+            return -(EEXIST as c_int);
+        }
+        add_port(st, name, iface_port(DRIVER_TAP, iface))
+    } else if name.starts_with("net_af_packet") {
+        // Only interfaces of `net_tap` ports exist.
+        match iface_arg(args) {
+            Ok(Some(iface)) if iface_exists(st, &iface) => {
+                add_port(st, name, iface_port(DRIVER_AF_PACKET, iface))
+            }
+            Ok(_) => -(ENODEV as c_int),
+            Err(err) => -(err as c_int),
+        }
+    } else {
+        -(ENOTSUP as c_int)
+    }
+}
+
+/// Parse the arguments of an interface port (e.g. `iface=dtap0`).
+fn iface_arg(args: &str) -> Result<Option<String>, u32> {
+    let mut iface = None;
+    for arg in args.split(',').filter(|arg| !arg.is_empty()) {
+        match arg.split_once('=').ok_or(EINVAL)? {
+            ("iface", value) => iface = Some(value.to_owned()),
+            _ => return Err(ENOTSUP),
+        }
+    }
+    Ok(iface)
+}
+
+fn iface_exists(st: &MockState, name: &str) -> bool {
+    st.ports
+        .iter()
+        .flatten()
+        .any(|port| matches!(&port.kind, PortKind::Iface { iface, .. } if iface == name))
+}
+
+fn iface_port(driver: &'static [u8], iface: String) -> PortKind {
+    PortKind::Iface {
+        driver,
+        iface,
+        rx: (0..MAX_QUEUES).map(|_| VecDeque::new()).collect(),
+    }
+}
+
+/// Open the files of a `net_pcap` port from its arguments (e.g. `rx_pcap=in.pcap,tx_pcap=out.pcap`).
+fn pcap_port(args: &str) -> Result<PortKind, u32> {
+    let (mut rx, mut tx, mut infinite_rx) = (Vec::new(), Vec::new(), false);
+    for arg in args.split(',').filter(|arg| !arg.is_empty()) {
+        let (key, value) = arg.split_once('=').ok_or(EINVAL)?;
+        match key {
+            "rx_pcap" => rx.push((read_pcap(value)?, 0)),
+            "tx_pcap" => tx.push(create_pcap(value)?),
+            "infinite_rx" => infinite_rx = value == "1",
+            _ => return Err(ENOTSUP),
+        }
+    }
+    if rx.len() > MAX_QUEUES as usize || tx.len() > MAX_QUEUES as usize {
+        return Err(EINVAL);
+    }
+    Ok(PortKind::Pcap {
+        rx,
+        tx,
+        infinite_rx,
+    })
+}
+
+/// Read the frames of a classic pcap file, in microsecond or nanosecond resolution.
+fn read_pcap(path: &str) -> Result<Vec<Vec<u8>>, u32> {
+    let data = std::fs::read(path).map_err(|_| ENOENT)?;
+    let little_endian = match data.get(..4) {
+        Some([0xd4, 0xc3, 0xb2, 0xa1]) | Some([0x4d, 0x3c, 0xb2, 0xa1]) => true,
+        Some([0xa1, 0xb2, 0xc3, 0xd4]) | Some([0xa1, 0xb2, 0x3c, 0x4d]) => false,
+        _ => return Err(EINVAL),
+    };
+    let read_u32 = |offset: usize| {
+        let bytes: [u8; 4] = data
+            .get(offset..offset + 4)
+            .ok_or(EINVAL)?
+            .try_into()
+            .unwrap();
+        Ok::<_, u32>(if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    };
+    let mut frames = Vec::new();
+    let mut offset = 24;
+    while offset < data.len() {
+        let start = offset + 16;
+        let end = start + read_u32(offset + 8)? as usize;
+        frames.push(data.get(start..end).ok_or(EINVAL)?.to_vec());
+        offset = end;
+    }
+    Ok(frames)
+}
+
+/// Create a classic pcap file of Ethernet frames with microsecond timestamps.
+fn create_pcap(path: &str) -> Result<File, u32> {
+    let mut file = File::create(path).map_err(|_| EIO)?;
+    let mut header = Vec::with_capacity(24);
+    header.extend_from_slice(&0xa1b2_c3d4u32.to_le_bytes());
+    header.extend_from_slice(&2u16.to_le_bytes());
+    header.extend_from_slice(&4u16.to_le_bytes());
+    header.extend_from_slice(&[0; 8]);
+    header.extend_from_slice(&u32::from(u16::MAX).to_le_bytes());
+    header.extend_from_slice(&1u32.to_le_bytes());
+    file.write_all(&header).map_err(|_| EIO)?;
+    Ok(file)
+}
+
+/// Append the first segment of a packet to a pcap file.
+unsafe fn write_pcap(file: &mut File, m: *const rte_mbuf) -> bool {
+    let data = std::slice::from_raw_parts(
+        ((*m).buf_addr as *const u8).add((*m).data_off.into()),
+        (*m).data_len.into(),
+    );
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    let mut record = Vec::with_capacity(16 + data.len());
+    record.extend_from_slice(&(now.as_secs() as u32).to_le_bytes());
+    record.extend_from_slice(&now.subsec_micros().to_le_bytes());
+    record.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    record.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    record.extend_from_slice(data);
+    file.write_all(&record).is_ok()
+}
+
+/// Parse an lcore list such as `0-3,6`.
+fn parse_lcore_list(list: &str) -> Vec<u32> {
+    let mut lcores = list
+        .split(',')
+        .filter_map(|range| {
+            let mut bounds = range.splitn(2, '-').map(|x| x.trim().parse::<u32>());
+            let start = bounds.next()?.ok()?;
+            let end = bounds.next().map_or(Some(start), |end| end.ok())?;
+            Some(start..=end)
+        })
+        .flatten()
+        .filter(|lcore| *lcore < RTE_MAX_LCORE)
+        .collect::<Vec<_>>();
+    lcores.sort_unstable();
+    lcores.dedup();
+    lcores
+}
+
+/// Parse a hexadecimal coremask such as `0xf`.
+fn parse_coremask(mask: &str) -> Vec<u32> {
+    let mask = u128::from_str_radix(mask.trim_start_matches("0x"), 16).unwrap_or(1);
+    (0..RTE_MAX_LCORE)
+        .filter(|lcore| mask & (1 << lcore) != 0)
+        .collect()
+}
+
+pub unsafe extern "C" fn rte_eal_init(argc: c_int, argv: *mut *mut c_char) -> c_int {
+    let mut st = state();
+    if st.initialized {
+        return -(EALREADY as c_int);
+    }
+    let args = (0..argc as usize)
+        .map(|i| c_str(*argv.add(i)).to_owned())
+        .collect::<Vec<_>>();
+
+    // Every argument up to `--` (inclusive) belongs to EAL.
+    let consumed = match args.iter().position(|arg| arg == "--") {
+        Some(index) => index,
+        None => args.len().saturating_sub(1),
+    };
+    let mut lcores = vec![0];
+    let mut vdevs = vec![];
+    let mut proc_type = rte_proc_type_t::RTE_PROC_PRIMARY;
+    let mut iter = args.iter().skip(1).take(consumed);
+    while let Some(arg) = iter.next() {
+        let (key, inline_value) = match arg.find('=') {
+            Some(index) if arg.starts_with("--") => {
+                (&arg[..index], Some(arg[index + 1..].to_owned()))
+            }
+            _ => (arg.as_str(), None),
+        };
+        let mut value = || inline_value.clone().or_else(|| iter.next().cloned());
+        match key {
+            "--vdev" => vdevs.extend(value()),
+            "-l" | "--lcores" => lcores = value().map(|v| parse_lcore_list(&v)).unwrap_or(lcores),
+            "-c" => lcores = value().map(|v| parse_coremask(&v)).unwrap_or(lcores),
+            // There is no other process, thus `auto` selects the primary.
+            "--proc-type" => match value().as_deref() {
+                Some("primary") | Some("auto") => proc_type = rte_proc_type_t::RTE_PROC_PRIMARY,
+                Some("secondary") => proc_type = rte_proc_type_t::RTE_PROC_SECONDARY,
+                _ => return -(EINVAL as c_int),
+            },
+            _ => {}
+        }
+    }
+
+    for vdev in vdevs {
+        let name = vdev.split(',').next().unwrap_or_default().to_owned();
+        let args = vdev.split_once(',').map_or("", |(_, args)| args);
+        let ret = create_vdev(&mut st, &name, args);
+        if ret < 0 {
+            return ret;
+        }
+    }
+    st.lcores = lcores;
+    st.proc_type = proc_type;
+    st.initialized = true;
+
+    // As DPDK does, program name is moved right before the remaining arguments.
+    *argv.add(consumed) = *argv;
+    consumed as c_int
+}
+
+pub unsafe extern "C" fn rte_eal_cleanup() -> c_int {
+    let mut st = state();
+    st.ports.clear();
+    st.mp_actions.clear();
+    st.initialized = false;
+    0
+}
+
+/// Keep drivers linked. Nothing to do for the mock backend.
+pub fn load_drivers() {}
+
+pub unsafe extern "C" fn rte_lcore_is_enabled(lcore_id: c_uint) -> c_int {
+    state().lcores.contains(&lcore_id) as c_int
+}
+
+pub unsafe extern "C" fn rte_lcore_to_socket_id(_lcore_id: c_uint) -> c_uint {
+    0
+}
+
+pub unsafe extern "C" fn rte_socket_id() -> c_uint {
+    0
+}
+
+pub unsafe extern "C" fn rte_get_main_lcore() -> c_uint {
+    state().lcores.first().copied().unwrap_or(0)
+}
+
+pub unsafe extern "C" fn rte_lcore_cpuset(lcore_id: c_uint) -> rte_cpuset_t {
+    let mut cpuset = rte_cpuset_t::default();
+    cpuset.__bits[(lcore_id / 64) as usize % 16] |= 1 << (lcore_id % 64);
+    cpuset
+}
+
+/// Threads are not pinned by the mock backend.
+pub unsafe extern "C" fn rte_thread_set_affinity(_cpusetp: *mut rte_cpuset_t) -> c_int {
+    0
+}
+
+/* Multi-process */
+
+pub unsafe extern "C" fn rte_eal_process_type() -> rte_proc_type_t {
+    state().proc_type
+}
+
+/// Returns the name of an IPC message, if it is valid.
+unsafe fn mp_name<'a>(name: *const c_char) -> Option<&'a str> {
+    let name = c_str(name);
+    (!name.is_empty() && name.len() < RTE_MP_MAX_NAME_LEN as usize).then_some(name)
+}
+
+pub unsafe extern "C" fn rte_mp_action_register(name: *const c_char, action: rte_mp_t) -> c_int {
+    let mut st = state();
+    let errno = match mp_name(name) {
+        Some(name) if action.is_some() => {
+            if st.mp_actions.iter().all(|(n, _)| n != name) {
+                st.mp_actions.push((name.to_owned(), action));
+                return 0;
+            }
+            EEXIST
+        }
+        _ => EINVAL,
+    };
+    RTE_ERRNO.with(|rte_errno| rte_errno.set(errno as c_int));
+    -1
+}
+
+pub unsafe extern "C" fn rte_mp_action_unregister(name: *const c_char) {
+    let name = c_str(name);
+    state().mp_actions.retain(|(n, _)| n != name);
+}
+
+/// The peer of a request, which is given to its action.
+type MockPeer = Mutex<Option<rte_mp_msg>>;
+
+pub unsafe extern "C" fn rte_mp_reply(msg: *mut rte_mp_msg, peer: *const c_char) -> c_int {
+    if mp_name((*msg).name.as_ptr()).is_none() || (*msg).len_param as u32 > RTE_MP_MAX_PARAM_LEN {
+        return -1;
+    }
+    // Safety: actions reply before the request returns.
+    let peer = &*(peer as *const MockPeer);
+    *peer.lock().unwrap() = Some(*msg);
+    0
+}
+
+/// The timeout is not used, as actions run synchronously.
+pub unsafe extern "C" fn rte_mp_request_sync(
+    req: *mut rte_mp_msg,
+    reply: *mut rte_mp_reply,
+    _ts: *const timespec,
+) -> c_int {
+    *reply = rte_mp_reply {
+        nb_sent: 0,
+        nb_received: 0,
+        msgs: ptr::null_mut(),
+    };
+    let name = match mp_name((*req).name.as_ptr()) {
+        Some(name) if (*req).len_param as u32 <= RTE_MP_MAX_PARAM_LEN => name,
+        _ => return -1,
+    };
+    let action = state()
+        .mp_actions
+        .iter()
+        .find(|(n, _)| n == name)
+        .and_then(|(_, action)| *action);
+    // As DPDK does, requests ignored by the peer are not counted.
+    let action = match action {
+        Some(action) => action,
+        None => return 0,
+    };
+    (*reply).nb_sent = 1;
+    let peer = MockPeer::default();
+    action(req, &peer as *const MockPeer as *const c_void);
+    match peer.into_inner().unwrap() {
+        Some(msg) => {
+            let msgs = Box::into_raw(Box::new(msg));
+            state().mp_replies.push(SendPtr(msgs));
+            (*reply).nb_received = 1;
+            (*reply).msgs = msgs;
+            0
+        }
+        None => -1,
+    }
+}
+
+/* Mempool and mbuf */
+
+pub unsafe extern "C" fn rte_mempool_lookup(name: *const c_char) -> *mut rte_mempool {
+    let name = c_str(name);
+    state()
+        .mempools
+        .iter()
+        .find(|mp| (*mp.0).name == name)
+        .map_or(ptr::null_mut(), |mp| mp.0)
+}
+
+/// Mempool ops known by the mock backend.
+const MEMPOOL_OPS: &[&str] = &[
+    "ring_mp_mc",
+    "ring_sp_sc",
+    "ring_mp_sc",
+    "ring_sp_mc",
+    "stack",
+    "lf_stack",
+    "bucket",
+];
+
+fn object_layout(elt_size: u32) -> Layout {
+    Layout::from_size_align(elt_size as usize, RTE_CACHE_LINE_SIZE as usize).unwrap()
+}
+
+pub unsafe extern "C" fn rte_mempool_create_empty(
+    name: *const c_char,
+    n: c_uint,
+    elt_size: c_uint,
+    _cache_size: c_uint,
+    _private_data_size: c_uint,
+    _socket_id: c_int,
+    _flags: c_uint,
+) -> *mut rte_mempool {
+    if !rte_mempool_lookup(name).is_null() {
+        return null_with_errno(EEXIST);
+    }
+    if n == 0 || elt_size == 0 {
+        return null_with_errno(EINVAL);
+    }
+    let mp = Box::into_raw(Box::new(rte_mempool {
+        name: c_str(name).to_owned(),
+        size: n,
+        elt_size,
+        ops: MEMPOOL_OPS[0].to_owned(),
+        priv_size: 0,
+        data_room_size: 0,
+        objects: Vec::with_capacity(n as usize),
+        free: Mutex::new(Vec::with_capacity(n as usize)),
+    }));
+    state().mempools.push(SendPtr(mp));
+    mp
+}
+
+pub unsafe extern "C" fn rte_mempool_set_ops_byname(
+    mp: *mut rte_mempool,
+    name: *const c_char,
+    _pool_config: *mut c_void,
+) -> c_int {
+    if !(*mp).objects.is_empty() {
+        return -(EEXIST as c_int);
+    }
+    let name = c_str(name);
+    if !MEMPOOL_OPS.contains(&name) {
+        return -(EINVAL as c_int);
+    }
+    (*mp).ops = name.to_owned();
+    0
+}
+
+pub unsafe extern "C" fn rte_mempool_populate_default(mp: *mut rte_mempool) -> c_int {
+    if !(*mp).objects.is_empty() {
+        return -(EEXIST as c_int);
+    }
+    let layout = object_layout((*mp).elt_size);
+    for _ in 0..(*mp).size {
+        let obj = alloc::alloc_zeroed(layout) as *mut c_void;
+        if obj.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+        (*mp).objects.push(obj);
+    }
+    // Objects are taken from the tail, thus the first object is allocated first.
+    let objects = (*mp).objects.iter().rev().copied().collect();
+    *(*mp).free.lock().unwrap() = objects;
+    (*mp).size as c_int
+}
+
+pub unsafe extern "C" fn rte_mempool_create(
+    name: *const c_char,
+    n: c_uint,
+    elt_size: c_uint,
+    cache_size: c_uint,
+    private_data_size: c_uint,
+    mp_init: rte_mempool_ctor_t,
+    mp_init_arg: *mut c_void,
+    obj_init: rte_mempool_obj_cb_t,
+    obj_init_arg: *mut c_void,
+    socket_id: c_int,
+    flags: c_uint,
+) -> *mut rte_mempool {
+    let mp = rte_mempool_create_empty(
+        name,
+        n,
+        elt_size,
+        cache_size,
+        private_data_size,
+        socket_id,
+        flags,
+    );
+    if mp.is_null() {
+        return mp;
+    }
+    if let Some(mp_init) = mp_init {
+        mp_init(mp, mp_init_arg);
+    }
+    rte_mempool_populate_default(mp);
+    if let Some(obj_init) = obj_init {
+        for (index, obj) in (*mp).objects.iter().enumerate() {
+            obj_init(mp, obj_init_arg, *obj, index as c_uint);
+        }
+    }
+    mp
+}
+
+pub unsafe extern "C" fn rte_pktmbuf_pool_create_by_ops(
+    name: *const c_char,
+    n: c_uint,
+    cache_size: c_uint,
+    priv_size: u16,
+    data_room_size: u16,
+    socket_id: c_int,
+    ops_name: *const c_char,
+) -> *mut rte_mempool {
+    if priv_size & 0x7 != 0 {
+        return null_with_errno(EINVAL);
+    }
+    let elt_size = size_of::<rte_mbuf>() + priv_size as usize + data_room_size as usize;
+    let mp = rte_mempool_create_empty(name, n, elt_size as c_uint, cache_size, 0, socket_id, 0);
+    if mp.is_null() {
+        return mp;
+    }
+    if !ops_name.is_null() {
+        let ret = rte_mempool_set_ops_byname(mp, ops_name, ptr::null_mut());
+        if ret < 0 {
+            rte_mempool_free(mp);
+            return null_with_errno(-ret as u32);
+        }
+    }
+    (*mp).priv_size = priv_size;
+    (*mp).data_room_size = data_room_size;
+    rte_mempool_populate_default(mp);
+    for obj in &(*mp).objects {
+        let m = *obj as *mut rte_mbuf;
+        (*m).buf_addr =
+            (m as *mut u8).add(size_of::<rte_mbuf>() + priv_size as usize) as *mut c_void;
+        (*m).buf_iova = (*m).buf_addr as u64;
+        (*m).buf_len = data_room_size;
+        (*m).priv_size = priv_size;
+        (*m).pool = mp;
+    }
+    mp
+}
+
+pub unsafe extern "C" fn rte_pktmbuf_pool_create(
+    name: *const c_char,
+    n: c_uint,
+    cache_size: c_uint,
+    priv_size: u16,
+    data_room_size: u16,
+    socket_id: c_int,
+) -> *mut rte_mempool {
+    rte_pktmbuf_pool_create_by_ops(
+        name,
+        n,
+        cache_size,
+        priv_size,
+        data_room_size,
+        socket_id,
+        ptr::null(),
+    )
+}
+
+pub unsafe extern "C" fn rte_pktmbuf_priv_size(mp: *mut rte_mempool) -> u16 {
+    (*mp).priv_size
+}
+
+pub unsafe extern "C" fn rte_pktmbuf_data_room_size(mp: *mut rte_mempool) -> u16 {
+    (*mp).data_room_size
+}
+
+pub unsafe extern "C" fn rte_mempool_free(mp: *mut rte_mempool) {
+    if mp.is_null() {
+        return;
+    }
+    state().mempools.retain(|x| x.0 != mp);
+    let mp = Box::from_raw(mp);
+    let layout = object_layout(mp.elt_size);
+    for obj in &mp.objects {
+        alloc::dealloc(*obj as *mut u8, layout);
+    }
+}
+
+pub unsafe extern "C" fn rte_mempool_get(mp: *mut rte_mempool, obj_p: *mut *mut c_void) -> c_int {
+    match (*mp).free.lock().unwrap().pop() {
+        Some(obj) => {
+            *obj_p = obj;
+            0
+        }
+        None => -(ENOENT as c_int),
+    }
+}
+
+pub unsafe extern "C" fn rte_mempool_put(mp: *mut rte_mempool, obj: *mut c_void) {
+    (*mp).free.lock().unwrap().push(obj);
+}
+
+pub unsafe extern "C" fn rte_mempool_avail_count(mp: *const rte_mempool) -> c_uint {
+    (*mp).free.lock().unwrap().len() as c_uint
+}
+
+pub unsafe extern "C" fn rte_mempool_in_use_count(mp: *const rte_mempool) -> c_uint {
+    (*mp).size - rte_mempool_avail_count(mp)
+}
+
+pub unsafe extern "C" fn rte_mempool_full(mp: *const rte_mempool) -> c_int {
+    (rte_mempool_avail_count(mp) == (*mp).size) as c_int
+}
+
+pub unsafe extern "C" fn rte_mempool_empty(mp: *const rte_mempool) -> c_int {
+    (rte_mempool_avail_count(mp) == 0) as c_int
+}
+
+/// Panics if a free object does not belong to the pool or is freed twice.
+pub unsafe extern "C" fn rte_mempool_audit(mp: *mut rte_mempool) {
+    let free = (*mp).free.lock().unwrap();
+    let mut sorted = free.clone();
+    sorted.sort();
+    sorted.dedup();
+    assert_eq!(
+        sorted.len(),
+        free.len(),
+        "mempool {}: object freed twice",
+        (*mp).name
+    );
+    assert!(
+        free.iter().all(|obj| (*mp).objects.contains(obj)),
+        "mempool {}: foreign object",
+        (*mp).name
+    );
+}
+
+pub unsafe extern "C" fn rte_mempool_dump(f: *mut FILE, mp: *mut rte_mempool) {
+    let avail = rte_mempool_avail_count(mp);
+    let text = format!(
+        "mempool <{}>@{:p}\n  size={}\n  elt_size={}\n  ops={}\n  avail_count={}\n  in_use_count={}\n",
+        (*mp).name,
+        mp,
+        (*mp).size,
+        (*mp).elt_size,
+        (*mp).ops,
+        avail,
+        (*mp).size - avail
+    );
+    (*f).buf.extend_from_slice(text.as_bytes());
+}
+
+pub unsafe extern "C" fn rte_pktmbuf_reset_headroom(m: *mut rte_mbuf) {
+    (*m).data_off = std::cmp::min(RTE_PKTMBUF_HEADROOM as u16, (*m).buf_len);
+}
+
+pub unsafe extern "C" fn rte_pktmbuf_reset(m: *mut rte_mbuf) {
+    (*m).next = ptr::null_mut();
+    (*m).pkt_len = 0;
+    (*m).data_len = 0;
+    (*m).nb_segs = 1;
+    (*m).port = u16::MAX;
+    (*m).ol_flags = 0;
+    (*m).packet_type = 0;
+    (*m).vlan_tci = 0;
+    rte_pktmbuf_reset_headroom(m);
+}
+
+pub unsafe extern "C" fn rte_pktmbuf_alloc(mp: *mut rte_mempool) -> *mut rte_mbuf {
+    let m = match (*mp).free.lock().unwrap().pop() {
+        Some(m) => m as *mut rte_mbuf,
+        None => return ptr::null_mut(),
+    };
+    (*m).refcnt = 1;
+    rte_pktmbuf_reset(m);
+    m
+}
+
+pub unsafe extern "C" fn rte_pktmbuf_alloc_bulk(
+    pool: *mut rte_mempool,
+    mbufs: *mut *mut rte_mbuf,
+    count: c_uint,
+) -> c_int {
+    {
+        let mut free = (*pool).free.lock().unwrap();
+        if free.len() < count as usize {
+            return -(ENOENT as c_int);
+        }
+        for i in 0..count as usize {
+            *mbufs.add(i) = free.pop().unwrap() as *mut rte_mbuf;
+        }
+    }
+    for i in 0..count as usize {
+        let m = *mbufs.add(i);
+        (*m).refcnt = 1;
+        rte_pktmbuf_reset(m);
+    }
+    0
+}
+
+pub unsafe extern "C" fn rte_mbuf_to_priv(m: *mut rte_mbuf) -> *mut c_void {
+    (m as *mut u8).add(size_of::<rte_mbuf>()) as *mut c_void
+}
+
+pub unsafe extern "C" fn rte_mbuf_refcnt_read(m: *const rte_mbuf) -> u16 {
+    (*m).refcnt
+}
+
+pub unsafe extern "C" fn rte_mbuf_refcnt_set(m: *mut rte_mbuf, new_value: u16) {
+    (*m).refcnt = new_value;
+}
+
+pub unsafe extern "C" fn rte_pktmbuf_refcnt_update(m: *mut rte_mbuf, v: i16) {
+    let mut m = m;
+    while !m.is_null() {
+        (*m).refcnt = ((*m).refcnt as i16 + v) as u16;
+        m = (*m).next;
+    }
+}
+
+pub unsafe extern "C" fn rte_mbuf_ext_refcnt_read(shinfo: *const rte_mbuf_ext_shared_info) -> u16 {
+    (*(ptr::addr_of!((*shinfo).refcnt) as *const AtomicU16)).load(Ordering::Acquire)
+}
+
+pub unsafe extern "C" fn rte_mbuf_ext_refcnt_set(
+    shinfo: *mut rte_mbuf_ext_shared_info,
+    new_value: u16,
+) {
+    (*(ptr::addr_of_mut!((*shinfo).refcnt) as *const AtomicU16))
+        .store(new_value, Ordering::Release);
+}
+
+pub unsafe extern "C" fn rte_mbuf_ext_refcnt_update(
+    shinfo: *mut rte_mbuf_ext_shared_info,
+    value: i16,
+) -> u16 {
+    let refcnt = &*(ptr::addr_of_mut!((*shinfo).refcnt) as *const AtomicU16);
+    refcnt
+        .fetch_add(value as u16, Ordering::AcqRel)
+        .wrapping_add(value as u16)
+}
+
+pub unsafe extern "C" fn rte_mem_virt2iova(virt: *const c_void) -> u64 {
+    virt as u64
+}
+
+pub unsafe extern "C" fn rte_mbuf_from_indirect(mi: *mut rte_mbuf) -> *mut rte_mbuf {
+    ((*mi).buf_addr as *mut u8).sub(size_of::<rte_mbuf>() + (*mi).priv_size as usize)
+        as *mut rte_mbuf
+}
+
+/// Attach `mi` to the buffer of `m`, or of the direct mbuf if `m` is indirect.
+pub unsafe extern "C" fn rte_pktmbuf_attach(mi: *mut rte_mbuf, m: *mut rte_mbuf) {
+    if (*m).ol_flags & MbufFlags::EXTERNAL.bits() != 0 {
+        rte_mbuf_ext_refcnt_update((*m).shinfo, 1);
+        (*mi).ol_flags = (*m).ol_flags;
+        (*mi).shinfo = (*m).shinfo;
+    } else {
+        let md = if (*m).ol_flags & MbufFlags::INDIRECT.bits() != 0 {
+            rte_mbuf_from_indirect(m)
+        } else {
+            m
+        };
+        (*md).refcnt += 1;
+        (*mi).ol_flags = (*m).ol_flags | MbufFlags::INDIRECT.bits();
+    }
+    (*mi).buf_addr = (*m).buf_addr;
+    (*mi).buf_iova = (*m).buf_iova;
+    (*mi).buf_len = (*m).buf_len;
+    (*mi).priv_size = (*m).priv_size;
+    (*mi).data_off = (*m).data_off;
+    (*mi).data_len = (*m).data_len;
+    (*mi).pkt_len = (*mi).data_len as u32;
+    (*mi).nb_segs = 1;
+    (*mi).port = (*m).port;
+    (*mi).vlan_tci = (*m).vlan_tci;
+    (*mi).packet_type = (*m).packet_type;
+}
+
+/// Attach an external buffer to `m`. The reference counter of `shinfo` is not changed.
+pub unsafe extern "C" fn rte_pktmbuf_attach_extbuf(
+    m: *mut rte_mbuf,
+    buf_addr: *mut c_void,
+    buf_iova: u64,
+    buf_len: u16,
+    shinfo: *mut rte_mbuf_ext_shared_info,
+) {
+    (*m).buf_addr = buf_addr;
+    (*m).buf_iova = buf_iova;
+    (*m).buf_len = buf_len;
+    (*m).data_len = 0;
+    (*m).data_off = 0;
+    (*m).ol_flags |= MbufFlags::EXTERNAL.bits();
+    (*m).shinfo = shinfo;
+}
+
+/// Detach an indirect mbuf or an external buffer from `m`, and release the buffer.
+pub unsafe extern "C" fn rte_pktmbuf_detach(m: *mut rte_mbuf) {
+    if (*m).ol_flags & MbufFlags::EXTERNAL.bits() != 0 {
+        let shinfo = (*m).shinfo;
+        if rte_mbuf_ext_refcnt_update(shinfo, -1) == 0 {
+            (*shinfo).free_cb.unwrap()((*m).buf_addr, (*shinfo).fcb_opaque);
+        }
+    } else {
+        rte_pktmbuf_free_seg(rte_mbuf_from_indirect(m));
+    }
+    let mp = (*m).pool;
+    (*m).priv_size = (*mp).priv_size;
+    (*m).buf_addr =
+        (m as *mut u8).add(size_of::<rte_mbuf>() + (*mp).priv_size as usize) as *mut c_void;
+    (*m).buf_iova = (*m).buf_addr as u64;
+    (*m).buf_len = (*mp).data_room_size;
+    (*m).data_len = 0;
+    (*m).ol_flags = 0;
+    rte_pktmbuf_reset_headroom(m);
+}
+
+/// Create indirect mbufs from `mp` sharing the buffers of every segment of `md`.
+pub unsafe extern "C" fn rte_pktmbuf_clone(
+    md: *mut rte_mbuf,
+    mp: *mut rte_mempool,
+) -> *mut rte_mbuf {
+    let mc = rte_pktmbuf_alloc(mp);
+    if mc.is_null() {
+        return ptr::null_mut();
+    }
+    let (mut mi, mut m) = (mc, md);
+    loop {
+        rte_pktmbuf_attach(mi, m);
+        m = (*m).next;
+        if m.is_null() {
+            break;
+        }
+        let next = rte_pktmbuf_alloc(mp);
+        if next.is_null() {
+            rte_pktmbuf_free(mc);
+            return ptr::null_mut();
+        }
+        (*mi).next = next;
+        mi = next;
+    }
+    (*mc).pkt_len = (*md).pkt_len;
+    (*mc).nb_segs = (*md).nb_segs;
+    mc
+}
+
+/// Copy `length` bytes of `m` from `offset` into a new mbuf from `mp`.
+///
+/// Unlike DPDK, the copy must fit in a single segment.
+pub unsafe extern "C" fn rte_pktmbuf_copy(
+    m: *const rte_mbuf,
+    mp: *mut rte_mempool,
+    offset: u32,
+    length: u32,
+) -> *mut rte_mbuf {
+    if offset > (*m).pkt_len {
+        return ptr::null_mut();
+    }
+    let length = std::cmp::min(length, (*m).pkt_len - offset);
+    let mc = rte_pktmbuf_alloc(mp);
+    if mc.is_null() {
+        return ptr::null_mut();
+    }
+    if length > rte_pktmbuf_tailroom(mc) as u32 {
+        rte_pktmbuf_free(mc);
+        return ptr::null_mut();
+    }
+    (*mc).port = (*m).port;
+    (*mc).vlan_tci = (*m).vlan_tci;
+    (*mc).packet_type = (*m).packet_type;
+    (*mc).ol_flags = (*m).ol_flags & !(MbufFlags::INDIRECT | MbufFlags::EXTERNAL).bits();
+
+    let mut dst = rte_pktmbuf_append(mc, length as u16) as *mut u8;
+    let (mut seg, mut offset, mut remaining) = (m, offset as usize, length as usize);
+    while remaining > 0 {
+        let data_len = (*seg).data_len as usize;
+        if offset >= data_len {
+            offset -= data_len;
+        } else {
+            let n = std::cmp::min(data_len - offset, remaining);
+            let src = ((*seg).buf_addr as *const u8).add((*seg).data_off as usize + offset);
+            ptr::copy_nonoverlapping(src, dst, n);
+            dst = dst.add(n);
+            remaining -= n;
+            offset = 0;
+        }
+        seg = (*seg).next;
+    }
+    mc
+}
+
+/// Decrease the reference counter of a segment, and return it to its pool when unused.
+pub unsafe extern "C" fn rte_pktmbuf_free_seg(m: *mut rte_mbuf) {
+    (*m).refcnt -= 1;
+    if (*m).refcnt == 0 {
+        if (*m).ol_flags & (MbufFlags::INDIRECT | MbufFlags::EXTERNAL).bits() != 0 {
+            rte_pktmbuf_detach(m);
+        }
+        (*m).next = ptr::null_mut();
+        rte_mempool_put((*m).pool, m as *mut c_void);
+    }
+}
+
+pub unsafe extern "C" fn rte_pktmbuf_free(m: *mut rte_mbuf) {
+    let mut m = m;
+    while !m.is_null() {
+        let next = (*m).next;
+        rte_pktmbuf_free_seg(m);
+        m = next;
+    }
+}
+
+pub unsafe extern "C" fn rte_pktmbuf_headroom(m: *const rte_mbuf) -> u16 {
+    (*m).data_off
+}
+
+pub unsafe extern "C" fn rte_pktmbuf_tailroom(m: *const rte_mbuf) -> u16 {
+    (*m).buf_len - (*m).data_off - (*m).data_len
+}
+
+pub unsafe extern "C" fn rte_pktmbuf_prepend(m: *mut rte_mbuf, len: u16) -> *mut c_char {
+    if len > rte_pktmbuf_headroom(m) {
+        return ptr::null_mut();
+    }
+    (*m).data_off -= len;
+    (*m).data_len += len;
+    (*m).pkt_len += len as u32;
+    ((*m).buf_addr as *mut c_char).add((*m).data_off as usize)
+}
+
+pub unsafe extern "C" fn rte_pktmbuf_append(m: *mut rte_mbuf, len: u16) -> *mut c_char {
+    if len > rte_pktmbuf_tailroom(m) {
+        return ptr::null_mut();
+    }
+    let tail = ((*m).buf_addr as *mut c_char).add((*m).data_off as usize + (*m).data_len as usize);
+    (*m).data_len += len;
+    (*m).pkt_len += len as u32;
+    tail
+}
+
+pub unsafe extern "C" fn rte_pktmbuf_adj(m: *mut rte_mbuf, len: u16) -> *mut c_char {
+    if len > (*m).data_len {
+        return ptr::null_mut();
+    }
+    (*m).data_off += len;
+    (*m).data_len -= len;
+    (*m).pkt_len -= len as u32;
+    ((*m).buf_addr as *mut c_char).add((*m).data_off as usize)
+}
+
+pub unsafe extern "C" fn rte_pktmbuf_trim(m: *mut rte_mbuf, len: u16) -> c_int {
+    if len > (*m).data_len {
+        return -1;
+    }
+    (*m).data_len -= len;
+    (*m).pkt_len -= len as u32;
+    0
+}
+
+/* Stdio */
+
+pub unsafe extern "C" fn open_memstream(
+    bufloc: *mut *mut c_char,
+    sizeloc: *mut usize,
+) -> *mut FILE {
+    Box::into_raw(Box::new(FILE {
+        buf: Vec::new(),
+        bufloc,
+        sizeloc,
+    }))
+}
+
+pub unsafe extern "C" fn fclose(stream: *mut FILE) -> c_int {
+    let stream = Box::from_raw(stream);
+    *stream.sizeloc = stream.buf.len();
+    *stream.bufloc = CString::new(stream.buf).unwrap().into_raw();
+    0
+}
+
+/// Only buffers of `open_memstream` and IPC replies are allocated by the mock backend.
+pub unsafe extern "C" fn free(ptr: *mut c_void) {
+    if ptr.is_null() {
+        return;
+    }
+    let mut st = state();
+    let index = st
+        .mp_replies
+        .iter()
+        .position(|msgs| msgs.0 as *mut c_void == ptr);
+    match index {
+        Some(index) => {
+            st.mp_replies.swap_remove(index);
+            drop(Box::from_raw(ptr as *mut rte_mp_msg));
+        }
+        None => drop(CString::from_raw(ptr as *mut c_char)),
+    }
+}
+
+/* Ring */
+
+pub unsafe extern "C" fn rte_ring_lookup(name: *const c_char) -> *mut rte_ring {
+    let name = c_str(name);
+    state()
+        .rings
+        .iter()
+        .find(|r| (*r.0).name == name)
+        .map_or(ptr::null_mut(), |r| r.0)
+}
+
+pub unsafe extern "C" fn rte_ring_create(
+    name: *const c_char,
+    count: c_uint,
+    _socket_id: c_int,
+    _flags: c_uint,
+) -> *mut rte_ring {
+    if !count.is_power_of_two() {
+        return null_with_errno(EINVAL);
+    }
+    if !rte_ring_lookup(name).is_null() {
+        return null_with_errno(EEXIST);
+    }
+    let r = Box::into_raw(Box::new(rte_ring {
+        name: c_str(name).to_owned(),
+        capacity: count - 1,
+        queue: Mutex::new(VecDeque::with_capacity(count as usize)),
+    }));
+    state().rings.push(SendPtr(r));
+    r
+}
+
+pub unsafe extern "C" fn rte_ring_free(r: *mut rte_ring) {
+    if r.is_null() {
+        return;
+    }
+    state().rings.retain(|x| x.0 != r);
+    drop(Box::from_raw(r));
+}
+
+pub unsafe extern "C" fn rte_ring_enqueue_burst(
+    r: *mut rte_ring,
+    obj_table: *const *mut c_void,
+    n: c_uint,
+    free_space: *mut c_uint,
+) -> c_uint {
+    let mut queue = (*r).queue.lock().unwrap();
+    let room = (*r).capacity as usize - queue.len();
+    let cnt = std::cmp::min(room, n as usize);
+    queue.extend((0..cnt).map(|i| *obj_table.add(i)));
+    if !free_space.is_null() {
+        *free_space = (room - cnt) as c_uint;
+    }
+    cnt as c_uint
+}
+
+pub unsafe extern "C" fn rte_ring_dequeue_burst(
+    r: *mut rte_ring,
+    obj_table: *mut *mut c_void,
+    n: c_uint,
+    available: *mut c_uint,
+) -> c_uint {
+    let mut queue = (*r).queue.lock().unwrap();
+    let cnt = std::cmp::min(queue.len(), n as usize);
+    for (i, obj) in queue.drain(..cnt).enumerate() {
+        *obj_table.add(i) = obj;
+    }
+    if !available.is_null() {
+        *available = queue.len() as c_uint;
+    }
+    cnt as c_uint
+}
+
+pub unsafe extern "C" fn rte_ring_count(r: *const rte_ring) -> c_uint {
+    (*r).queue.lock().unwrap().len() as c_uint
+}
+
+pub unsafe extern "C" fn rte_ring_get_capacity(r: *const rte_ring) -> c_uint {
+    (*r).capacity
+}
+
+/* Ethdev */
+
+pub unsafe extern "C" fn rte_eth_dev_is_valid_port(port_id: u16) -> c_int {
+    with_port(port_id, |_| 1).max(0)
+}
+
+pub unsafe extern "C" fn rte_eth_dev_socket_id(port_id: u16) -> c_int {
+    with_port(port_id, |_| 0)
+}
+
+pub unsafe extern "C" fn rte_eth_dev_info_get(
+    port_id: u16,
+    dev_info: *mut rte_eth_dev_info,
+) -> c_int {
+    with_port(port_id, |port| {
+        let (max_rx_queues, max_tx_queues) = match &port.kind {
+            PortKind::Rings { rx, tx } => (rx.len() as u16, tx.len() as u16),
+            PortKind::Pcap { rx, tx, .. } => (rx.len() as u16, tx.len() as u16),
+            _ => (MAX_QUEUES, MAX_QUEUES),
+        };
+        *dev_info = std::mem::zeroed();
+        (*dev_info).device = &mut *port.device;
+        (*dev_info).driver_name = match &port.kind {
+            PortKind::Null => DRIVER_NULL,
+            PortKind::Pcap { .. } => DRIVER_PCAP,
+            PortKind::Iface { driver, .. } => driver,
+            _ => DRIVER_RING,
+        }
+        .as_ptr() as *const c_char;
+        (*dev_info).max_rx_queues = max_rx_queues;
+        (*dev_info).max_tx_queues = max_tx_queues;
+        (*dev_info).max_rx_pktlen = u16::MAX.into();
+        (*dev_info).max_mtu = u16::MAX;
+        0
+    })
+}
+
+pub unsafe extern "C" fn rte_eth_dev_get_name_by_port(port_id: u16, name: *mut c_char) -> c_int {
+    with_port(port_id, |port| {
+        let bytes = port.name.as_bytes();
+        ptr::copy_nonoverlapping(bytes.as_ptr() as *const c_char, name, bytes.len());
+        *name.add(bytes.len()) = 0;
+        0
+    })
+}
+
+pub unsafe extern "C" fn rte_eth_dev_get_port_by_name(
+    name: *const c_char,
+    port_id: *mut u16,
+) -> c_int {
+    let name = c_str(name);
+    match state()
+        .ports
+        .iter()
+        .position(|port| matches!(port, Some(port) if port.name == name))
+    {
+        Some(index) => {
+            *port_id = index as u16;
+            0
+        }
+        None => -(ENODEV as c_int),
+    }
+}
+
+pub unsafe extern "C" fn rte_eth_macaddr_get(port_id: u16, mac_addr: *mut rte_ether_addr) -> c_int {
+    with_port(port_id, |_| {
+        // Locally administered address derived from the port index.
+        (*mac_addr).addr_bytes = [0x02, 0, 0, 0, 0, port_id as u8];
+        0
+    })
+}
+
+pub unsafe extern "C" fn rte_eth_dev_get_mtu(port_id: u16, mtu: *mut u16) -> c_int {
+    with_port(port_id, |port| {
+        *mtu = port.mtu;
+        0
+    })
+}
+
+pub unsafe extern "C" fn rte_eth_promiscuous_enable(port_id: u16) -> c_int {
+    with_port(port_id, |port| {
+        port.promiscuous = true;
+        0
+    })
+}
+
+pub unsafe extern "C" fn rte_eth_promiscuous_disable(port_id: u16) -> c_int {
+    with_port(port_id, |port| {
+        port.promiscuous = false;
+        0
+    })
+}
+
+pub unsafe extern "C" fn rte_eth_promiscuous_get(port_id: u16) -> c_int {
+    with_port(port_id, |port| port.promiscuous as c_int)
+}
+
+pub unsafe extern "C" fn rte_eth_dev_owner_new(owner_id: *mut u64) -> c_int {
+    let mut st = state();
+    *owner_id = st.next_owner_id;
+    st.next_owner_id += 1;
+    0
+}
+
+pub unsafe extern "C" fn rte_eth_dev_owner_set(
+    port_id: u16,
+    owner: *const rte_eth_dev_owner,
+) -> c_int {
+    with_port(port_id, |port| {
+        if port.owner_id != 0 {
+            return -(EPERM as c_int);
+        }
+        port.owner_id = (*owner).id;
+        0
+    })
+}
+
+pub unsafe extern "C" fn rte_eth_dev_owner_unset(port_id: u16, owner_id: u64) -> c_int {
+    with_port(port_id, |port| {
+        if port.owner_id != owner_id {
+            return -(EPERM as c_int);
+        }
+        port.owner_id = 0;
+        0
+    })
+}
+
+pub unsafe extern "C" fn rte_eth_dev_configure(
+    port_id: u16,
+    nb_rx_queue: u16,
+    nb_tx_queue: u16,
+    eth_conf: *const rte_eth_conf,
+) -> c_int {
+    with_port(port_id, |port| {
+        if port.started || nb_rx_queue > MAX_QUEUES || nb_tx_queue > MAX_QUEUES {
+            return -(EINVAL as c_int);
+        }
+        port.nb_rx_queues = nb_rx_queue;
+        port.nb_tx_queues = nb_tx_queue;
+        port.rx_pools = vec![SendPtr(ptr::null_mut()); nb_rx_queue as usize];
+        #[cfg(not(dpdk_version_ge = "21.11"))]
+        {
+            let max_rx_pkt_len = (*eth_conf).rxmode.max_rx_pkt_len;
+            if max_rx_pkt_len > RTE_ETHER_HDR_LEN + RTE_ETHER_CRC_LEN {
+                port.mtu = (max_rx_pkt_len - RTE_ETHER_HDR_LEN - RTE_ETHER_CRC_LEN) as u16;
+            }
+        }
+        #[cfg(dpdk_version_ge = "21.11")]
+        if (*eth_conf).rxmode.mtu != 0 {
+            port.mtu = (*eth_conf).rxmode.mtu as u16;
+        }
+        // Only `net_tap` supports rx interrupts.
+        port.rx_intr = (*eth_conf).intr_conf.rxq() != 0
+            && matches!(port.kind, PortKind::Iface { driver, .. } if driver == DRIVER_TAP);
+        port.rx_intr_enabled = vec![false; nb_rx_queue as usize];
+        port.rx_intr_threads = vec![None; nb_rx_queue as usize];
+        port.rx_intr_fds = (0..nb_rx_queue).map(|_| None).collect();
+        0
+    })
+}
+
+pub unsafe extern "C" fn rte_eth_rx_queue_setup(
+    port_id: u16,
+    rx_queue_id: u16,
+    _nb_rx_desc: u16,
+    _socket_id: c_uint,
+    _rx_conf: *const rte_eth_rxconf,
+    mb_pool: *mut rte_mempool,
+) -> c_int {
+    with_port(port_id, |port| {
+        match port.rx_pools.get_mut(rx_queue_id as usize) {
+            Some(pool) => {
+                *pool = SendPtr(mb_pool);
+                0
+            }
+            None => -(EINVAL as c_int),
+        }
+    })
+}
+
+pub unsafe extern "C" fn rte_eth_tx_queue_setup(
+    port_id: u16,
+    tx_queue_id: u16,
+    _nb_tx_desc: u16,
+    _socket_id: c_uint,
+    _tx_conf: *const rte_eth_txconf,
+) -> c_int {
+    with_port(port_id, |port| {
+        if tx_queue_id < port.nb_tx_queues {
+            0
+        } else {
+            -(EINVAL as c_int)
+        }
+    })
+}
+
+pub unsafe extern "C" fn rte_eth_dev_start(port_id: u16) -> c_int {
+    with_port(port_id, |port| {
+        port.started = true;
+        0
+    })
+}
+
+pub unsafe extern "C" fn rte_eth_dev_stop(port_id: u16) -> c_int {
+    with_port(port_id, |port| {
+        port.started = false;
+        0
+    })
+}
+
+pub unsafe extern "C" fn rte_eth_dev_close(port_id: u16) -> c_int {
+    let mut st = state();
+    match st.ports.get_mut(port_id as usize) {
+        Some(slot @ Some(_)) => {
+            // Loopback and interface queues may still hold packets.
+            if let Some(MockPort {
+                kind: PortKind::Loopback(queues) | PortKind::Iface { rx: queues, .. },
+                ..
+            }) = slot.take()
+            {
+                drop(st);
+                for m in queues.into_iter().flatten() {
+                    rte_pktmbuf_free(m.0);
+                }
+            }
+            0
+        }
+        _ => -(ENODEV as c_int),
+    }
+}
+
+pub unsafe extern "C" fn rte_eth_dev_rx_queue_stop(port_id: u16, rx_queue_id: u16) -> c_int {
+    with_port(port_id, |port| {
+        if rx_queue_id < port.nb_rx_queues {
+            0
+        } else {
+            -(EINVAL as c_int)
+        }
+    })
+}
+
+pub unsafe extern "C" fn rte_eth_dev_tx_queue_stop(port_id: u16, tx_queue_id: u16) -> c_int {
+    with_port(port_id, |port| {
+        if tx_queue_id < port.nb_tx_queues {
+            0
+        } else {
+            -(EINVAL as c_int)
+        }
+    })
+}
+
+pub unsafe extern "C" fn rte_eth_stats_get(port_id: u16, stats: *mut rte_eth_stats) -> c_int {
+    with_port(port_id, |port| {
+        *stats = port.stats;
+        0
+    })
+}
+
+pub unsafe extern "C" fn rte_eth_stats_reset(port_id: u16) -> c_int {
+    with_port(port_id, |port| {
+        port.stats = Default::default();
+        0
+    })
+}
+
+pub unsafe extern "C" fn rte_eth_link_get(port_id: u16, link: *mut rte_eth_link) -> c_int {
+    with_port(port_id, |port| {
+        *link = rte_eth_link {
+            link_speed: 10_000,
+            link_status: if port.started {
+                crate::compat::RTE_ETH_LINK_UP
+            } else {
+                0
+            },
+        };
+        0
+    })
+}
+
+pub unsafe extern "C" fn rte_eth_link_get_nowait(port_id: u16, link: *mut rte_eth_link) -> c_int {
+    rte_eth_link_get(port_id, link)
+}
+
+/// Account received or transmitted packets in port statistics.
+unsafe fn account(stats: &mut rte_eth_stats, rx: bool, queue_id: u16, pkts: &[*mut rte_mbuf]) {
+    let bytes = pkts.iter().map(|m| u64::from((**m).pkt_len)).sum::<u64>();
+    let cnt = pkts.len() as u64;
+    let queue_id = queue_id as usize % RTE_ETHDEV_QUEUE_STAT_CNTRS as usize;
+    if rx {
+        stats.ipackets += cnt;
+        stats.ibytes += bytes;
+        stats.q_ipackets[queue_id] += cnt;
+        stats.q_ibytes[queue_id] += bytes;
+    } else {
+        stats.opackets += cnt;
+        stats.obytes += bytes;
+        stats.q_opackets[queue_id] += cnt;
+        stats.q_obytes[queue_id] += bytes;
+    }
+}
+
+pub unsafe extern "C" fn rte_eth_rx_burst(
+    port_id: u16,
+    queue_id: u16,
+    rx_pkts: *mut *mut rte_mbuf,
+    nb_pkts: u16,
+) -> u16 {
+    let mut st = state();
+    let port = match st.ports.get_mut(port_id as usize) {
+        Some(Some(port)) if queue_id < port.nb_rx_queues => port,
+        _ => return 0,
+    };
+    let cnt = match &mut port.kind {
+        PortKind::Null => {
+            let pool = port.rx_pools[queue_id as usize].0;
+            if pool.is_null() || rte_pktmbuf_alloc_bulk(pool, rx_pkts, nb_pkts.into()) != 0 {
+                port.stats.rx_nombuf += u64::from(nb_pkts);
+                return 0;
+            }
+            for i in 0..nb_pkts as usize {
+                let m = *rx_pkts.add(i);
+                (*m).data_len = NULL_PACKET_SIZE;
+                (*m).pkt_len = NULL_PACKET_SIZE.into();
+                (*m).port = port_id;
+            }
+            nb_pkts as usize
+        }
+        PortKind::Loopback(queues) | PortKind::Iface { rx: queues, .. } => {
+            let queue = &mut queues[queue_id as usize];
+            let cnt = std::cmp::min(queue.len(), nb_pkts as usize);
+            for (i, m) in queue.drain(..cnt).enumerate() {
+                (*m.0).port = port_id;
+                *rx_pkts.add(i) = m.0;
+            }
+            cnt
+        }
+        PortKind::Rings { rx, .. } => rte_ring_dequeue_burst(
+            rx[queue_id as usize].0,
+            rx_pkts as *mut *mut c_void,
+            nb_pkts.into(),
+            ptr::null_mut(),
+        ) as usize,
+        PortKind::Pcap {
+            rx, infinite_rx, ..
+        } => {
+            let (frames, next) = match rx.get_mut(queue_id as usize) {
+                Some(queue) => queue,
+                None => return 0,
+            };
+            let pool = port.rx_pools[queue_id as usize].0;
+            let mut cnt = 0;
+            while cnt < nb_pkts as usize && !frames.is_empty() {
+                if *next == frames.len() {
+                    if !*infinite_rx {
+                        break;
+                    }
+                    *next = 0;
+                }
+                let m = rte_pktmbuf_alloc(pool);
+                if m.is_null() {
+                    port.stats.rx_nombuf += 1;
+                    break;
+                }
+                let frame = &frames[*next];
+                let len = std::cmp::min(frame.len(), rte_pktmbuf_tailroom(m).into());
+                let data = ((*m).buf_addr as *mut u8).add((*m).data_off.into());
+                ptr::copy_nonoverlapping(frame.as_ptr(), data, len);
+                (*m).data_len = len as u16;
+                (*m).pkt_len = len as u32;
+                (*m).port = port_id;
+                *rx_pkts.add(cnt) = m;
+                *next += 1;
+                cnt += 1;
+            }
+            cnt
+        }
+    };
+    let pkts = std::slice::from_raw_parts(rx_pkts, cnt);
+    account(&mut port.stats, true, queue_id, pkts);
+    let callbacks = queue_callbacks(port, true, queue_id);
+    drop(st);
+    callbacks
+        .into_iter()
+        .fold(cnt as u16, |cnt, (cb, param)| match cb {
+            Callback::Rx(f) => f.unwrap()(port_id, queue_id, rx_pkts, cnt, nb_pkts, param),
+            Callback::Tx(_) => cnt,
+        })
+}
+
+pub unsafe extern "C" fn rte_eth_tx_burst(
+    port_id: u16,
+    queue_id: u16,
+    tx_pkts: *mut *mut rte_mbuf,
+    nb_pkts: u16,
+) -> u16 {
+    let callbacks = match state().ports.get(port_id as usize) {
+        Some(Some(port)) => queue_callbacks(port, false, queue_id),
+        _ => Vec::new(),
+    };
+    let nb_pkts = callbacks
+        .into_iter()
+        .fold(nb_pkts, |cnt, (cb, param)| match cb {
+            Callback::Tx(f) => f.unwrap()(port_id, queue_id, tx_pkts, cnt, param),
+            Callback::Rx(_) => cnt,
+        });
+
+    let mut st = state();
+    let port = match st.ports.get_mut(port_id as usize) {
+        Some(Some(port)) if queue_id < port.nb_tx_queues => port,
+        _ => return 0,
+    };
+    let cnt = match &mut port.kind {
+        PortKind::Null => nb_pkts as usize,
+        PortKind::Loopback(queues) => {
+            let queue = &mut queues[queue_id as usize];
+            let cnt = std::cmp::min(LOOPBACK_QUEUE_SIZE - queue.len(), nb_pkts as usize);
+            queue.extend((0..cnt).map(|i| SendPtr(*tx_pkts.add(i))));
+            cnt
+        }
+        PortKind::Rings { tx, .. } => rte_ring_enqueue_burst(
+            tx[queue_id as usize].0,
+            tx_pkts as *const *mut c_void,
+            nb_pkts.into(),
+            ptr::null_mut(),
+        ) as usize,
+        PortKind::Pcap { tx, .. } => match tx.get_mut(queue_id as usize) {
+            Some(file) => (0..nb_pkts as usize)
+                .take_while(|i| write_pcap(file, *tx_pkts.add(*i)))
+                .count(),
+            None => nb_pkts as usize,
+        },
+        PortKind::Iface { .. } => nb_pkts as usize,
+    };
+    let pkts = std::slice::from_raw_parts(tx_pkts, cnt);
+    account(&mut port.stats, false, queue_id, pkts);
+    let dropped = match &port.kind {
+        PortKind::Null | PortKind::Pcap { .. } => pkts.to_vec(),
+        PortKind::Iface { iface, .. } => {
+            let iface = iface.clone();
+            deliver_iface(&mut st, port_id, &iface, queue_id, pkts)
+        }
+        _ => Vec::new(),
+    };
+    drop(st);
+    RX_INTR.notify_all();
+    for m in dropped {
+        rte_pktmbuf_free(m);
+    }
+    cnt as u16
+}
+
+/// Deliver packets transmitted on an interface to the other started port on it, and return those
+/// which were dropped.
+fn deliver_iface(
+    st: &mut MockState,
+    port_id: u16,
+    iface: &str,
+    queue_id: u16,
+    pkts: &[*mut rte_mbuf],
+) -> Vec<*mut rte_mbuf> {
+    let peer = st
+        .ports
+        .iter_mut()
+        .enumerate()
+        .find_map(|(id, port)| match port {
+            Some(port) if id != port_id as usize && port.started => match &mut port.kind {
+                PortKind::Iface { iface: name, .. } if name == iface => Some(port),
+                _ => None,
+            },
+            _ => None,
+        });
+    let peer = match peer {
+        Some(peer) => peer,
+        None => return pkts.to_vec(),
+    };
+    let queue = match &mut peer.kind {
+        PortKind::Iface { rx, .. } => &mut rx[queue_id as usize],
+        _ => unreachable!(),
+    };
+    let cnt = std::cmp::min(LOOPBACK_QUEUE_SIZE - queue.len(), pkts.len());
+    queue.extend(pkts[..cnt].iter().map(|&m| SendPtr(m)));
+    signal_rx_intr_fds(peer);
+    pkts[cnt..].to_vec()
+}
+
+/* Rx/Tx callbacks */
+
+pub type rte_rx_callback_fn = Option<
+    unsafe extern "C" fn(
+        port_id: u16,
+        queue: u16,
+        pkts: *mut *mut rte_mbuf,
+        nb_pkts: u16,
+        max_pkts: u16,
+        user_param: *mut c_void,
+    ) -> u16,
+>;
+pub type rte_tx_callback_fn = Option<
+    unsafe extern "C" fn(
+        port_id: u16,
+        queue: u16,
+        pkts: *mut *mut rte_mbuf,
+        nb_pkts: u16,
+        user_param: *mut c_void,
+    ) -> u16,
+>;
+
+#[derive(Debug, Clone, Copy)]
+enum Callback {
+    Rx(rte_rx_callback_fn),
+    Tx(rte_tx_callback_fn),
+}
+
+/// Callback registered on an rx or a tx queue. It is freed with `rte_free`.
+#[derive(Debug)]
+pub struct rte_eth_rxtx_callback {
+    queue_id: u16,
+    cb: Callback,
+    param: *mut c_void,
+}
+
+/// Callbacks of a queue, in registration order.
+unsafe fn queue_callbacks(
+    port: &MockPort,
+    rx: bool,
+    queue_id: u16,
+) -> Vec<(Callback, *mut c_void)> {
+    port.callbacks
+        .iter()
+        .map(|cb| &*cb.0)
+        .filter(|cb| cb.queue_id == queue_id && matches!(cb.cb, Callback::Rx(_)) == rx)
+        .map(|cb| (cb.cb, cb.param))
+        .collect()
+}
+
+unsafe fn add_callback(
+    port_id: u16,
+    queue_id: u16,
+    cb: Callback,
+    param: *mut c_void,
+) -> *const rte_eth_rxtx_callback {
+    let mut st = state();
+    let port = match st.ports.get_mut(port_id as usize) {
+        Some(Some(port)) => port,
+        _ => return ptr::null(),
+    };
+    let nb_queues = match cb {
+        Callback::Rx(_) => port.nb_rx_queues,
+        Callback::Tx(_) => port.nb_tx_queues,
+    };
+    if queue_id >= nb_queues {
+        return ptr::null();
+    }
+    let cb = Box::into_raw(Box::new(rte_eth_rxtx_callback {
+        queue_id,
+        cb,
+        param,
+    }));
+    port.callbacks.push(SendPtr(cb));
+    cb
+}
+
+unsafe fn remove_callback(port_id: u16, user_cb: *const rte_eth_rxtx_callback) -> c_int {
+    with_port(port_id, |port| {
+        match port.callbacks.iter().position(|cb| ptr::eq(cb.0, user_cb)) {
+            Some(index) => {
+                port.callbacks.remove(index);
+                0
+            }
+            None => -(EINVAL as c_int),
+        }
+    })
+}
+
+pub unsafe extern "C" fn rte_eth_add_rx_callback(
+    port_id: u16,
+    queue_id: u16,
+    fn_: rte_rx_callback_fn,
+    user_param: *mut c_void,
+) -> *const rte_eth_rxtx_callback {
+    add_callback(port_id, queue_id, Callback::Rx(fn_), user_param)
+}
+
+pub unsafe extern "C" fn rte_eth_add_tx_callback(
+    port_id: u16,
+    queue_id: u16,
+    fn_: rte_tx_callback_fn,
+    user_param: *mut c_void,
+) -> *const rte_eth_rxtx_callback {
+    add_callback(port_id, queue_id, Callback::Tx(fn_), user_param)
+}
+
+pub unsafe extern "C" fn rte_eth_remove_rx_callback(
+    port_id: u16,
+    _queue_id: u16,
+    user_cb: *const rte_eth_rxtx_callback,
+) -> c_int {
+    remove_callback(port_id, user_cb)
+}
+
+pub unsafe extern "C" fn rte_eth_remove_tx_callback(
+    port_id: u16,
+    _queue_id: u16,
+    user_cb: *const rte_eth_rxtx_callback,
+) -> c_int {
+    remove_callback(port_id, user_cb)
+}
+
+/// Free memory from DPDK's heap. Only removed rx/tx callbacks are allocated there.
+pub unsafe extern "C" fn rte_free(ptr: *mut c_void) {
+    if !ptr.is_null() {
+        drop(Box::from_raw(ptr as *mut rte_eth_rxtx_callback));
+    }
+}
+
+/* Rx interrupts */
+
+/// Signaled when packets are transmitted, which may be received by a waiting thread.
+static RX_INTR: Condvar = Condvar::new();
+
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct rte_epoll_event {
+    pub status: u32,
+    pub fd: c_int,
+    pub epfd: c_int,
+}
+
+/// Returns whether an rx queue of an interface port has packets to receive.
+fn rx_pending(port: &MockPort, queue_id: usize) -> bool {
+    matches!(&port.kind, PortKind::Iface { rx, .. } if !rx[queue_id].is_empty())
+}
+
+pub unsafe extern "C" fn rte_eth_dev_rx_intr_ctl_q(
+    port_id: u16,
+    queue_id: u16,
+    epfd: c_int,
+    op: c_int,
+    _data: *mut c_void,
+) -> c_int {
+    with_port(port_id, |port| {
+        if !port.rx_intr {
+            return -(ENOTSUP as c_int);
+        }
+        // Only the per-thread epoll instance is supported.
+        let thread = match port.rx_intr_threads.get_mut(queue_id as usize) {
+            Some(thread) if epfd == RTE_EPOLL_PER_THREAD => thread,
+            _ => return -(EINVAL as c_int),
+        };
+        match op as u32 {
+            RTE_INTR_EVENT_ADD if thread.is_some() => -(EEXIST as c_int),
+            RTE_INTR_EVENT_ADD => {
+                *thread = Some(thread::current().id());
+                0
+            }
+            RTE_INTR_EVENT_DEL if thread.take().is_none() => -(EPERM as c_int),
+            RTE_INTR_EVENT_DEL => 0,
+            _ => -(EINVAL as c_int),
+        }
+    })
+}
+
+unsafe fn set_rx_intr(port_id: u16, queue_id: u16, enabled: bool) -> c_int {
+    with_port(port_id, |port| {
+        if !port.rx_intr {
+            return -(ENOTSUP as c_int);
+        }
+        match port.rx_intr_enabled.get_mut(queue_id as usize) {
+            Some(flag) => {
+                *flag = enabled;
+                0
+            }
+            None => -(EINVAL as c_int),
+        }
+    })
+}
+
+pub unsafe extern "C" fn rte_eth_dev_rx_intr_enable(port_id: u16, queue_id: u16) -> c_int {
+    set_rx_intr(port_id, queue_id, true)
+}
+
+pub unsafe extern "C" fn rte_eth_dev_rx_intr_disable(port_id: u16, queue_id: u16) -> c_int {
+    set_rx_intr(port_id, queue_id, false)
+}
+
+/// Returns a file descriptor which becomes readable when packets arrive while the interrupt of the
+/// queue is enabled. Reading it clears the event.
+pub unsafe extern "C" fn rte_eth_dev_rx_intr_ctl_q_get_fd(port_id: u16, queue_id: u16) -> c_int {
+    with_port(port_id, |port| {
+        if !port.rx_intr {
+            return -(ENOTSUP as c_int);
+        }
+        let fds = match port.rx_intr_fds.get_mut(queue_id as usize) {
+            Some(fds) => fds,
+            None => return -(EINVAL as c_int),
+        };
+        if fds.is_none() {
+            let pair = match UnixStream::pair() {
+                Ok(pair) => pair,
+                Err(_) => return -(ENOMEM as c_int),
+            };
+            if pair.0.set_nonblocking(true).is_err() || pair.1.set_nonblocking(true).is_err() {
+                return -(EINVAL as c_int);
+            }
+            *fds = Some(pair);
+        }
+        fds.as_ref().unwrap().0.as_raw_fd()
+    })
+}
+
+/// Make interrupt fds of enabled queues with pending packets readable.
+fn signal_rx_intr_fds(port: &MockPort) {
+    for (queue_id, fds) in port.rx_intr_fds.iter().enumerate() {
+        if let Some((_, writer)) = fds {
+            if port.rx_intr_enabled[queue_id] && rx_pending(port, queue_id) {
+                // A full socket is readable already.
+                let _ = (&*writer).write(&[1]);
+            }
+        }
+    }
+}
+
+/// Wait until enabled rx queues registered on this thread have packets, or until `timeout`
+/// milliseconds have passed (forever if negative).
+pub unsafe extern "C" fn rte_epoll_wait(
+    epfd: c_int,
+    events: *mut rte_epoll_event,
+    maxevents: c_int,
+    timeout: c_int,
+) -> c_int {
+    if epfd != RTE_EPOLL_PER_THREAD || maxevents <= 0 {
+        return -(EINVAL as c_int);
+    }
+    let deadline = if timeout >= 0 {
+        Some(Instant::now() + Duration::from_millis(timeout as u64))
+    } else {
+        None
+    };
+    let current = Some(thread::current().id());
+    let mut st = state();
+    loop {
+        let ready = st
+            .ports
+            .iter()
+            .flatten()
+            .map(|port| {
+                (0..port.rx_intr_threads.len())
+                    .filter(|&queue_id| {
+                        port.rx_intr_threads[queue_id] == current
+                            && port.rx_intr_enabled[queue_id]
+                            && rx_pending(port, queue_id)
+                    })
+                    .count()
+            })
+            .sum::<usize>();
+        if ready > 0 {
+            let ready = std::cmp::min(ready, maxevents as usize);
+            for i in 0..ready {
+                *events.add(i) = rte_epoll_event {
+                    status: 1,
+                    fd: -1,
+                    epfd,
+                };
+            }
+            return ready as c_int;
+        }
+        st = match deadline {
+            None => RX_INTR
+                .wait(st)
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+            Some(deadline) => {
+                let now = Instant::now();
+                if now >= deadline {
+                    return 0;
+                }
+                RX_INTR
+                    .wait_timeout(st, deadline - now)
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .0
+            }
+        };
+    }
+}
+
+/* Hotplug */
+
+pub unsafe extern "C" fn rte_eal_hotplug_add(
+    busname: *const c_char,
+    devname: *const c_char,
+    drvargs: *const c_char,
+) -> c_int {
+    if c_str(busname) != "vdev" {
+        return -(ENOTSUP as c_int);
+    }
+    let ret = create_vdev(&mut state(), c_str(devname), c_str(drvargs));
+    ret.min(0)
+}
+
+pub unsafe extern "C" fn rte_dev_probe(devargs: *const c_char) -> c_int {
+    let devargs = c_str(devargs);
+    let (name, args) = devargs.split_once(',').unwrap_or((devargs, ""));
+    let ret = create_vdev(&mut state(), name, args);
+    ret.min(0)
+}
+
+pub unsafe extern "C" fn rte_eal_hotplug_remove(
+    busname: *const c_char,
+    devname: *const c_char,
+) -> c_int {
+    if c_str(busname) != "vdev" {
+        return -(ENOTSUP as c_int);
+    }
+    let devname = c_str(devname);
+    let mut st = state();
+    match st
+        .ports
+        .iter_mut()
+        .find(|port| matches!(port, Some(port) if port.name == devname))
+    {
+        Some(slot) => {
+            *slot = None;
+            0
+        }
+        None => -(ENOENT as c_int),
+    }
+}
+
+pub unsafe extern "C" fn rte_dev_remove(dev: *mut rte_device) -> c_int {
+    let port_id = (*dev).port_id;
+    let mut st = state();
+    match st.ports.get_mut(port_id as usize) {
+        Some(slot @ Some(_)) => {
+            *slot = None;
+            0
+        }
+        _ => -(ENODEV as c_int),
+    }
+}
+
+pub unsafe extern "C" fn rte_eth_from_rings(
+    name: *const c_char,
+    rx_queues: *const *mut rte_ring,
+    nb_rx_queues: c_uint,
+    tx_queues: *const *mut rte_ring,
+    nb_tx_queues: c_uint,
+    _numa_node: c_uint,
+) -> c_int {
+    let rx = (0..nb_rx_queues as usize)
+        .map(|i| SendPtr(*rx_queues.add(i)))
+        .collect();
+    let tx = (0..nb_tx_queues as usize)
+        .map(|i| SendPtr(*tx_queues.add(i)))
+        .collect();
+    let ret = add_port(&mut state(), c_str(name), PortKind::Rings { rx, tx });
+    if ret < 0 {
+        -1
+    } else {
+        ret
+    }
+}
+
+/* Static EAL functions */
+
+static TSC_START: Mutex<Option<Instant>> = Mutex::new(None);
+static RAND_STATE: AtomicU64 = AtomicU64::new(0x853c_49e6_748f_ea9b);
+
+/// Software implementation of generated static EAL functions.
+///
+/// The timestamp counter ticks in nanoseconds.
+///
+/// # Safety
+/// This implementation must be used after `rte_eal_init` and never used after `rte_eal_cleanup`.
+pub unsafe trait EalStaticFunctions {
+    /// Get the measured frequency of the RDTSC counter
+    #[inline(always)]
+    fn get_tsc_hz(&self) -> u64 {
+        1_000_000_000
+    }
+
+    /// Return the number of TSC cycles since boot
+    #[inline(always)]
+    fn get_tsc_cycles(&self) -> u64 {
+        let mut start = TSC_START.lock().unwrap();
+        start.get_or_insert_with(Instant::now).elapsed().as_nanos() as u64
+    }
+
+    /// Get the number of cycles since boot from the default timer.
+    #[inline(always)]
+    fn get_timer_cycles(&self) -> u64 {
+        self.get_tsc_cycles()
+    }
+
+    /// Get the number of cycles in one second for the default timer.
+    #[inline(always)]
+    fn get_timer_hz(&self) -> u64 {
+        self.get_tsc_hz()
+    }
+
+    /// Read the time base register.
+    #[inline(always)]
+    fn rdtsc(&self) -> u64 {
+        self.get_tsc_cycles()
+    }
+
+    /// Wait at least us microseconds.
+    #[inline(always)]
+    fn delay_us_block(&self, us: u32) {
+        std::thread::sleep(std::time::Duration::from_micros(us.into()));
+    }
+
+    /// Pause CPU execution for a short while
+    #[inline(always)]
+    fn pause(&self) {
+        std::hint::spin_loop();
+    }
+
+    /// Generates a pseudo-random number.
+    #[inline(always)]
+    fn rand(&self) -> u64 {
+        // xorshift64*
+        let mut x = RAND_STATE.load(Ordering::Relaxed);
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        RAND_STATE.store(x, Ordering::Relaxed);
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Returns true if n is a power of 2
+    #[inline(always)]
+    fn is_power_of_2(&self, n: u32) -> i32 {
+        (n != 0 && n & (n - 1) == 0) as i32
+    }
+
+    /// Aligns input parameter to the next power of 2
+    #[inline(always)]
+    fn align32pow2(&self, x: u32) -> u32 {
+        x.next_power_of_two()
+    }
+
+    /// Aligns 64b input parameter to the next power of 2
+    #[inline(always)]
+    fn align64pow2(&self, v: u64) -> u64 {
+        v.next_power_of_two()
+    }
+}
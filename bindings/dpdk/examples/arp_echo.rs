@@ -120,7 +120,7 @@ fn main() -> Result<()> {
         DEFAULT_RX_PER_CORE_CACHE,
         DEFAULT_PACKET_DATA_LENGTH,
         None,
-    );
+    )?;
     let (port, (rxq, txq)) = eal.ports()?.swap_remove(0).init(1, 1, None);
     let lcores = eal.lcores();
 
@@ -0,0 +1,271 @@
+//! Async/await integration: rx queues as [`Stream`]s of bursts and tx queues as [`Sink`]s of
+//! packets (`async` feature).
+//!
+//! Streams are driven in one of two ways:
+//! - [`RxStream::with_interrupt`] registers the rx interrupt event fd of the queue
+//!   (`rte_eth_dev_rx_intr_ctl_q_get_fd`) to Tokio's reactor, and sleeps while the queue is idle.
+//! - [`RxStream::polling`] polls the queue every time the stream is polled, and wakes itself up
+//!   when the queue is empty. It is meant for [`block_on`] running on a dedicated lcore (see
+//!   [`LCoreId::launch`](crate::eal::LCoreId::launch)).
+//!
+//! Queues keep their threading guarantees: streams borrow `RxQ`, which is not `Sync`, thus they
+//! are not `Send` and must run on a single thread (e.g. Tokio's `LocalSet`). Sinks borrow `TxQ`
+//! mutably.
+use crate::eal::{ErrorCode, Packet, RxQ, TxQ};
+use crate::zeroable::Zeroable;
+use arrayvec::ArrayVec;
+use futures_core::Stream;
+use futures_sink::Sink;
+use log::warn;
+use std::convert::{Infallible, TryInto};
+use std::fs::File;
+use std::future::Future;
+use std::hint;
+use std::io::{self, Read};
+use std::mem::ManuallyDrop;
+use std::os::unix::io::{FromRawFd, RawFd};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use tokio::io::unix::AsyncFd;
+
+/// Stream of received bursts of at most `N` packets. It never ends.
+#[derive(Debug)]
+pub struct RxStream<'q, MPoolPriv: Zeroable, const N: usize> {
+    rxq: &'q RxQ<MPoolPriv>,
+    /// Rx interrupt event fd, owned by DPDK.
+    intr_fd: Option<AsyncFd<RawFd>>,
+    /// Whether the rx interrupt is enabled.
+    armed: bool,
+}
+
+impl<'q, MPoolPriv: Zeroable, const N: usize> RxStream<'q, MPoolPriv, N> {
+    /// Create a stream which polls the queue without sleeping.
+    #[inline]
+    pub fn polling(rxq: &'q RxQ<MPoolPriv>) -> Self {
+        Self {
+            rxq,
+            intr_fd: None,
+            armed: false,
+        }
+    }
+
+    /// Create a stream which sleeps on the rx interrupt of the queue while it is idle.
+    ///
+    /// Rx interrupts must be enabled when the port is configured (see
+    /// [`RteEthConf::set_rx_interrupts`](crate::eal::RteEthConf::set_rx_interrupts)).
+    ///
+    /// Panic: when called outside of a Tokio runtime.
+    #[inline]
+    pub fn with_interrupt(rxq: &'q RxQ<MPoolPriv>) -> Result<Self, ErrorCode> {
+        // Safety: foreign function.
+        let fd = unsafe {
+            dpdk_sys::rte_eth_dev_rx_intr_ctl_q_get_fd(rxq.port().port_id(), rxq.queue_id())
+        };
+        if fd < 0 {
+            return Err(fd.try_into().unwrap());
+        }
+        Ok(Self {
+            rxq,
+            intr_fd: Some(AsyncFd::new(fd).map_err(io_error)?),
+            armed: false,
+        })
+    }
+
+    fn disarm(&mut self) -> Result<(), ErrorCode> {
+        if self.armed {
+            self.armed = false;
+            self.rxq.disable_interrupt()?;
+        }
+        Ok(())
+    }
+}
+
+impl<'q, MPoolPriv: Zeroable, const N: usize> Stream for RxStream<'q, MPoolPriv, N> {
+    type Item = Result<ArrayVec<Packet<'q, MPoolPriv>, N>, ErrorCode>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            let mut burst = ArrayVec::new();
+            this.rxq.rx(&mut burst);
+            if !burst.is_empty() {
+                if let Err(err) = this.disarm() {
+                    warn!(
+                        "RxStream::poll_next, error({}) while disabling interrupt of queue {}:{}",
+                        err,
+                        this.rxq.port().port_id(),
+                        this.rxq.queue_id()
+                    );
+                }
+                return Poll::Ready(Some(Ok(burst)));
+            }
+            let intr_fd = match &this.intr_fd {
+                Some(intr_fd) => intr_fd,
+                None => {
+                    // Yield to other tasks, and poll again.
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+            };
+            if !this.armed {
+                if let Err(err) = this.rxq.arm_interrupt() {
+                    return Poll::Ready(Some(Err(err)));
+                }
+                this.armed = true;
+                // Packets may have arrived before the interrupt was armed.
+                continue;
+            }
+            match intr_fd.poll_read_ready(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(err)) => return Poll::Ready(Some(Err(io_error(err)))),
+                Poll::Ready(Ok(mut guard)) => {
+                    clear_intr_fd(*guard.get_inner());
+                    guard.clear_ready();
+                }
+            }
+            if let Err(err) = this.disarm() {
+                return Poll::Ready(Some(Err(err)));
+            }
+        }
+    }
+}
+
+impl<MPoolPriv: Zeroable, const N: usize> Drop for RxStream<'_, MPoolPriv, N> {
+    #[inline]
+    fn drop(&mut self) {
+        if let Err(err) = self.disarm() {
+            warn!(
+                "RxStream::drop, error({}) while disabling interrupt of queue {}:{}",
+                err,
+                self.rxq.port().port_id(),
+                self.rxq.queue_id()
+            );
+        }
+    }
+}
+
+/// Consume the event of an rx interrupt fd.
+fn clear_intr_fd(fd: RawFd) {
+    // Safety: the fd is valid while the queue exists, and it is not closed here.
+    let file = ManuallyDrop::new(unsafe { File::from_raw_fd(fd) });
+    // The fd is non-blocking, and it may have been consumed already.
+    let mut event = [0u8; 8];
+    let _ = (&*file).read(&mut event);
+}
+
+fn io_error(err: io::Error) -> ErrorCode {
+    let code = err.raw_os_error().unwrap_or(dpdk_sys::EIO as i32);
+    (code as u32).try_into().unwrap()
+}
+
+/// Sink of packets transmitted by bursts of at most `N` packets.
+///
+/// Packets are buffered until the buffer is full or the sink is flushed. NICs do not signal free
+/// tx descriptors, thus a full queue wakes the task up to retry. Packets remaining in the buffer
+/// are freed when the sink is dropped.
+#[derive(Debug)]
+pub struct TxSink<'a, 'pool, MPoolPriv: Zeroable, const N: usize> {
+    txq: &'a mut TxQ<'pool>,
+    buffer: ArrayVec<Packet<'pool, MPoolPriv>, N>,
+}
+
+impl<'a, 'pool, MPoolPriv: Zeroable, const N: usize> TxSink<'a, 'pool, MPoolPriv, N> {
+    /// Create a sink buffering up to `N` packets for `txq`.
+    #[inline]
+    pub fn new(txq: &'a mut TxQ<'pool>) -> Self {
+        Self {
+            txq,
+            buffer: ArrayVec::new(),
+        }
+    }
+
+    /// Returns the number of buffered packets.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Returns whether no packet is buffered.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Transmit buffered packets, and wake the task up if some packets remain.
+    fn poll_tx(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if !self.buffer.is_empty() {
+            self.txq.tx(&mut self.buffer);
+        }
+        if self.buffer.is_empty() {
+            return Poll::Ready(());
+        }
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+impl<'pool, MPoolPriv: Zeroable, const N: usize> Sink<Packet<'pool, MPoolPriv>>
+    for TxSink<'_, 'pool, MPoolPriv, N>
+{
+    type Error = Infallible;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        if this.buffer.is_full() {
+            let _ = this.poll_tx(cx);
+            if this.buffer.is_full() {
+                return Poll::Pending;
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Packet<'pool, MPoolPriv>) -> Result<(), Self::Error> {
+        self.get_mut().buffer.push(item);
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_tx(cx).map(Ok)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_flush(cx)
+    }
+}
+
+/// Waker of [`block_on`].
+struct Woken(AtomicBool);
+
+impl Wake for Woken {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.store(true, Ordering::Release);
+    }
+}
+
+/// Run a future to completion on the current thread, polling it again as soon as it is woken up.
+///
+/// This is a cooperative executor for a dedicated lcore: it never sleeps, and tasks such as
+/// [`RxStream::polling`] yield by waking themselves up. Several futures run concurrently when they
+/// are joined (e.g. with `futures::join!`).
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    let woken = Arc::new(Woken(AtomicBool::new(true)));
+    let waker = Waker::from(woken.clone());
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if woken.0.swap(false, Ordering::Acquire) {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        } else {
+            hint::spin_loop();
+        }
+    }
+}
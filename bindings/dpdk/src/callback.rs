@@ -0,0 +1,261 @@
+//! Closures called with every rx/tx burst of a queue (`rte_eth_add_rx_callback` and
+//! `rte_eth_add_tx_callback`), e.g. for latency stamping, sampling or filtering.
+use crate::eal::{ErrorCode, Packet, Port, RxQ, TxQ};
+use crate::zeroable::Zeroable;
+use log::warn;
+use std::convert::TryInto;
+use std::ffi::c_void;
+use std::fmt;
+use std::hint;
+use std::ptr;
+use std::slice;
+use std::sync::atomic::{fence, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Quiescent state of a queue, counting its rx/tx bursts.
+///
+/// The counter is odd while a burst is in progress. This works as RCU whose only reader is the
+/// thread polling the queue: once a callback is removed, it is not used after the burst in
+/// progress.
+#[derive(Debug, Default)]
+pub(crate) struct Bursts(AtomicUsize);
+
+impl Bursts {
+    /// Mark the start of a burst, which ends when the guard is dropped.
+    #[inline]
+    pub(crate) fn enter(&self) -> BurstGuard<'_> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        BurstGuard(self)
+    }
+
+    /// Wait until the burst in progress, if any, is finished.
+    fn synchronize(&self) {
+        fence(Ordering::SeqCst);
+        let current = self.0.load(Ordering::SeqCst);
+        if current % 2 == 1 {
+            while self.0.load(Ordering::Acquire) == current {
+                hint::spin_loop();
+            }
+        }
+    }
+}
+
+pub(crate) struct BurstGuard<'a>(&'a Bursts);
+
+impl Drop for BurstGuard<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        (self.0).0.fetch_add(1, Ordering::Release);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Rx,
+    Tx,
+}
+
+/// A closure registered on an rx or tx queue, which is removed when this handle is dropped.
+///
+/// Dropping the handle waits for the burst in progress on the queue, thus the closure is neither
+/// running nor called anymore once it is dropped.
+///
+/// Note: dropping the handle from a callback of the same queue never returns.
+pub struct QueueCallback {
+    port: Port,
+    queue_id: u16,
+    direction: Direction,
+    cb: *const dpdk_sys::rte_eth_rxtx_callback,
+    bursts: Arc<Bursts>,
+    closure: *mut c_void,
+    drop_closure: unsafe fn(*mut c_void),
+}
+
+/// # Safety
+/// Closures are `Send`, and they are called only from the thread polling the queue.
+unsafe impl Send for QueueCallback {}
+unsafe impl Sync for QueueCallback {}
+
+impl fmt::Debug for QueueCallback {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueueCallback")
+            .field("port_id", &self.port.port_id())
+            .field("queue_id", &self.queue_id)
+            .field("direction", &self.direction)
+            .finish()
+    }
+}
+
+impl QueueCallback {
+    /// Take the ownership of a registered closure, or free it if registration failed.
+    fn new(
+        port: &Port,
+        queue_id: u16,
+        direction: Direction,
+        cb: *const dpdk_sys::rte_eth_rxtx_callback,
+        bursts: &Arc<Bursts>,
+        closure: *mut c_void,
+        drop_closure: unsafe fn(*mut c_void),
+    ) -> Result<Self, ErrorCode> {
+        if cb.is_null() {
+            // Safety: the closure is not registered.
+            unsafe { drop_closure(closure) };
+            return Err(dpdk_sys::ENOTSUP.try_into().unwrap());
+        }
+        Ok(Self {
+            port: port.clone(),
+            queue_id,
+            direction,
+            cb,
+            bursts: bursts.clone(),
+            closure,
+            drop_closure,
+        })
+    }
+}
+
+impl Drop for QueueCallback {
+    #[inline]
+    fn drop(&mut self) {
+        let port_id = self.port.port_id();
+        // Safety: foreign function.
+        let ret = unsafe {
+            match self.direction {
+                Direction::Rx => {
+                    dpdk_sys::rte_eth_remove_rx_callback(port_id, self.queue_id, self.cb)
+                }
+                Direction::Tx => {
+                    dpdk_sys::rte_eth_remove_tx_callback(port_id, self.queue_id, self.cb)
+                }
+            }
+        };
+        if ret != 0 {
+            // The closure may still be called, thus it is leaked.
+            warn!(
+                "QueueCallback::drop, error code({}) while removing callback of queue {}:{}",
+                ret, port_id, self.queue_id
+            );
+            return;
+        }
+        self.bursts.synchronize();
+        // Safety: foreign function. The callback is not used by any burst anymore.
+        unsafe {
+            dpdk_sys::rte_free(self.cb as *mut c_void);
+            (self.drop_closure)(self.closure);
+        }
+    }
+}
+
+unsafe fn drop_closure<F>(closure: *mut c_void) {
+    drop(Box::from_raw(closure as *mut F));
+}
+
+unsafe extern "C" fn rx_callback<MPoolPriv: Zeroable, F>(
+    _port_id: u16,
+    _queue: u16,
+    pkts: *mut *mut dpdk_sys::rte_mbuf,
+    nb_pkts: u16,
+    _max_pkts: u16,
+    user_param: *mut c_void,
+) -> u16
+where
+    F: FnMut(&mut [Packet<'_, MPoolPriv>]) -> usize,
+{
+    let f = &mut *(user_param as *mut F);
+    let pkts = slice::from_raw_parts_mut(pkts as *mut Packet<'_, MPoolPriv>, nb_pkts.into());
+    let cnt = f(pkts).min(pkts.len());
+    // Filtered packets are freed.
+    for pkt in &mut pkts[cnt..] {
+        ptr::drop_in_place(pkt);
+    }
+    cnt as u16
+}
+
+unsafe extern "C" fn tx_callback<F>(
+    _port_id: u16,
+    _queue: u16,
+    pkts: *mut *mut dpdk_sys::rte_mbuf,
+    nb_pkts: u16,
+    user_param: *mut c_void,
+) -> u16
+where
+    F: FnMut(&mut [Packet<'_, ()>]) -> usize,
+{
+    let f = &mut *(user_param as *mut F);
+    let pkts = slice::from_raw_parts_mut(pkts as *mut Packet<'_, ()>, nb_pkts.into());
+    f(pkts).min(pkts.len()) as u16
+}
+
+impl<MPoolPriv: Zeroable> RxQ<MPoolPriv> {
+    /// Register a closure called with every burst of received packets
+    /// (`rte_eth_add_rx_callback`).
+    ///
+    /// The closure returns the number of packets to keep at the front of the slice, and the other
+    /// packets are freed. Closures are called in registration order, by [`RxQ::rx`].
+    ///
+    /// Returns `ENOTSUP` if the callback cannot be registered, e.g. when DPDK is built without
+    /// `RTE_ETHDEV_RXTX_CALLBACKS`.
+    #[inline]
+    pub fn add_callback<F>(&self, f: F) -> Result<QueueCallback, ErrorCode>
+    where
+        F: FnMut(&mut [Packet<'_, MPoolPriv>]) -> usize + Send + 'static,
+    {
+        let closure = Box::into_raw(Box::new(f)) as *mut c_void;
+        // Safety: foreign function. The closure is freed after the callback is removed.
+        let cb = unsafe {
+            dpdk_sys::rte_eth_add_rx_callback(
+                self.port().port_id(),
+                self.queue_id(),
+                Some(rx_callback::<MPoolPriv, F>),
+                closure,
+            )
+        };
+        QueueCallback::new(
+            self.port(),
+            self.queue_id(),
+            Direction::Rx,
+            cb,
+            &self.bursts,
+            closure,
+            drop_closure::<F>,
+        )
+    }
+}
+
+impl TxQ<'_> {
+    /// Register a closure called with every burst of packets to transmit
+    /// (`rte_eth_add_tx_callback`).
+    ///
+    /// The closure returns the number of packets to transmit from the front of the slice. The
+    /// other packets are not transmitted, and remain in the buffer given to [`TxQ::tx`].
+    /// Closures are called in registration order.
+    ///
+    /// Returns `ENOTSUP` if the callback cannot be registered, e.g. when DPDK is built without
+    /// `RTE_ETHDEV_RXTX_CALLBACKS`.
+    #[inline]
+    pub fn add_callback<F>(&self, f: F) -> Result<QueueCallback, ErrorCode>
+    where
+        F: FnMut(&mut [Packet<'_, ()>]) -> usize + Send + 'static,
+    {
+        let closure = Box::into_raw(Box::new(f)) as *mut c_void;
+        // Safety: foreign function. The closure is freed after the callback is removed.
+        let cb = unsafe {
+            dpdk_sys::rte_eth_add_tx_callback(
+                self.port().port_id(),
+                self.queue_id(),
+                Some(tx_callback::<F>),
+                closure,
+            )
+        };
+        QueueCallback::new(
+            self.port(),
+            self.queue_id(),
+            Direction::Tx,
+            cb,
+            &self.bursts,
+            closure,
+            drop_closure::<F>,
+        )
+    }
+}
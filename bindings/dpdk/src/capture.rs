@@ -0,0 +1,848 @@
+//! Packet capture on rx/tx queues, as DPDK's `dpdk-dumpcap`.
+//!
+//! Queue callbacks copy matching packets (at most the snap length) into a dedicated mempool and
+//! enqueue them to a ring, which a writer thread drains to a pcapng file. Each port is recorded as
+//! an interface, with the direction and the queue of every packet. Queues can be added and
+//! removed, and the filter replaced while capturing.
+//!
+//! Note: tx queues are captured before transmission, thus packets which do not fit into the queue
+//! are captured again when they are retried.
+use crate::callback::QueueCallback;
+use crate::eal::{Eal, ErrorCode, MPool, Packet, RxQ, TxQ, DEFAULT_RX_BURST};
+use crate::pcap::{self, PcapDirection, PcapFormat, PcapFrameInfo, PcapWriter};
+use crate::ring::Ring;
+use crate::zeroable::Zeroable;
+use arrayvec::ArrayVec;
+use log::{info, warn};
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fmt;
+use std::io::{self, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use thiserror::Error;
+
+/// How long the writer thread sleeps when the ring is empty.
+const WRITER_IDLE_TIMEOUT: Duration = Duration::from_millis(10);
+
+const ETHER_TYPE_IPV4: u16 = 0x0800;
+const ETHER_TYPE_ARP: u16 = 0x0806;
+const ETHER_TYPE_VLAN: u16 = 0x8100;
+const ETHER_TYPE_QINQ: u16 = 0x88a8;
+const ETHER_TYPE_IPV6: u16 = 0x86dd;
+const IP_PROTO_ICMP: u8 = 1;
+const IP_PROTO_TCP: u8 = 6;
+const IP_PROTO_UDP: u8 = 17;
+const IP_PROTO_ICMPV6: u8 = 58;
+
+/// Error of [`Filter`] parsing.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error("invalid capture filter: {0}")]
+pub struct FilterError(String);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dir {
+    Src,
+    Dst,
+    Any,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Proto {
+    Vlan,
+    Arp,
+    Ip,
+    Ip6,
+    Tcp,
+    Udp,
+    Icmp,
+    Icmp6,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Proto(Proto),
+    Ether(Dir, [u8; 6]),
+    Host(Dir, IpAddr),
+    Net(Dir, IpAddr, u8),
+    Port(Dir, u16),
+    Less(usize),
+    Greater(usize),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+/// Packet filter with a subset of the tcpdump (pcap-filter) syntax, evaluated on the first
+/// segment of packets.
+///
+/// Primitives:
+/// - protocols: `vlan`, `arp`, `ip`, `ip6`, `tcp`, `udp`, `icmp`, `icmp6`, optionally followed by
+///   an address or port primitive (`tcp port 80`)
+/// - `ether [src|dst] [host] MAC`
+/// - `[src|dst] host ADDR` and `[src|dst] net ADDR/LEN`, of IPv4 or IPv6
+/// - `[src|dst] port PORT` of TCP or UDP
+/// - `less LEN` and `greater LEN` of the frame
+///
+/// Primitives are combined with `not` (`!`), `and` (`&&`), `or` (`||`) and parentheses. VLAN tags
+/// are skipped before matching network headers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filter {
+    expr: Expr,
+}
+
+impl Filter {
+    /// Returns whether an Ethernet frame matches this filter.
+    #[inline]
+    pub fn matches(&self, frame: &[u8]) -> bool {
+        self.expr.eval(&Headers::parse(frame))
+    }
+}
+
+impl FromStr for Filter {
+    type Err = FilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(s);
+        let mut parser = Parser {
+            tokens: &tokens,
+            pos: 0,
+        };
+        let expr = parser.parse_or()?;
+        match parser.peek() {
+            None => Ok(Self { expr }),
+            Some(token) => Err(FilterError(format!("unexpected `{}`", token))),
+        }
+    }
+}
+
+fn tokenize(s: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (i, c) in s.char_indices() {
+        let single = matches!(c, '(' | ')') || (c == '!' && !s[i + 1..].starts_with('='));
+        if c.is_whitespace() || single {
+            if let Some(start) = start.take() {
+                tokens.push(&s[start..i]);
+            }
+            if single {
+                tokens.push(&s[i..i + 1]);
+            }
+        } else if start.is_none() {
+            start = Some(i);
+        }
+    }
+    if let Some(start) = start {
+        tokens.push(&s[start..]);
+    }
+    tokens
+}
+
+struct Parser<'a> {
+    tokens: &'a [&'a str],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Result<&'a str, FilterError> {
+        let token = self
+            .peek()
+            .ok_or_else(|| FilterError("unexpected end".to_string()))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn eat(&mut self, tokens: &[&str]) -> bool {
+        match self.peek() {
+            Some(token) if tokens.contains(&token) => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, FilterError> {
+        let mut expr = self.parse_and()?;
+        while self.eat(&["or", "||"]) {
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, FilterError> {
+        let mut expr = self.parse_unary()?;
+        while self.eat(&["and", "&&"]) {
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, FilterError> {
+        if self.eat(&["not", "!"]) {
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        if self.eat(&["("]) {
+            let expr = self.parse_or()?;
+            if !self.eat(&[")"]) {
+                return Err(FilterError("missing `)`".to_string()));
+            }
+            return Ok(expr);
+        }
+        self.parse_primitive()
+    }
+
+    fn parse_primitive(&mut self) -> Result<Expr, FilterError> {
+        let token = self.next()?;
+        let proto = match token {
+            "vlan" => Proto::Vlan,
+            "arp" => Proto::Arp,
+            "ip" => Proto::Ip,
+            "ip6" => Proto::Ip6,
+            "tcp" => Proto::Tcp,
+            "udp" => Proto::Udp,
+            "icmp" => Proto::Icmp,
+            "icmp6" => Proto::Icmp6,
+            "ether" => {
+                let dir = self.parse_dir();
+                self.eat(&["host"]);
+                return Ok(Expr::Ether(dir, parse_mac(self.next()?)?));
+            }
+            "less" => return Ok(Expr::Less(parse_number(self.next()?)?)),
+            "greater" => return Ok(Expr::Greater(parse_number(self.next()?)?)),
+            _ => {
+                self.pos -= 1;
+                return self.parse_qualified();
+            }
+        };
+        // `tcp port 80` stands for `tcp and port 80`.
+        if matches!(self.peek(), Some("src" | "dst" | "host" | "net" | "port")) {
+            let qualified = self.parse_qualified()?;
+            return Ok(Expr::And(Box::new(Expr::Proto(proto)), Box::new(qualified)));
+        }
+        Ok(Expr::Proto(proto))
+    }
+
+    fn parse_dir(&mut self) -> Dir {
+        if self.eat(&["src"]) {
+            Dir::Src
+        } else if self.eat(&["dst"]) {
+            Dir::Dst
+        } else {
+            Dir::Any
+        }
+    }
+
+    /// Parse `[src|dst] host|net|port VALUE`, or `[src|dst] ADDR`.
+    fn parse_qualified(&mut self) -> Result<Expr, FilterError> {
+        let dir = self.parse_dir();
+        let token = self.next()?;
+        match token {
+            "host" => Ok(Expr::Host(dir, parse_ip(self.next()?)?)),
+            "net" => {
+                let net = self.next()?;
+                let (addr, len) = net
+                    .split_once('/')
+                    .ok_or_else(|| FilterError(format!("invalid network `{}`", net)))?;
+                let addr = parse_ip(addr)?;
+                let len = len
+                    .parse::<u8>()
+                    .ok()
+                    .filter(|len| *len <= if addr.is_ipv4() { 32 } else { 128 })
+                    .ok_or_else(|| FilterError(format!("invalid network `{}`", net)))?;
+                Ok(Expr::Net(dir, addr, len))
+            }
+            "port" => {
+                let port = self.next()?;
+                let port = port
+                    .parse()
+                    .map_err(|_| FilterError(format!("invalid port `{}`", port)))?;
+                Ok(Expr::Port(dir, port))
+            }
+            _ if dir != Dir::Any => Ok(Expr::Host(dir, parse_ip(token)?)),
+            _ => Err(FilterError(format!("unknown primitive `{}`", token))),
+        }
+    }
+}
+
+fn parse_ip(s: &str) -> Result<IpAddr, FilterError> {
+    s.parse()
+        .map_err(|_| FilterError(format!("invalid address `{}`", s)))
+}
+
+fn parse_mac(s: &str) -> Result<[u8; 6], FilterError> {
+    let err = || FilterError(format!("invalid MAC address `{}`", s));
+    let mut mac = [0u8; 6];
+    let mut bytes = s.split(':');
+    for byte in &mut mac {
+        *byte = u8::from_str_radix(bytes.next().ok_or_else(err)?, 16).map_err(|_| err())?;
+    }
+    match bytes.next() {
+        None => Ok(mac),
+        Some(_) => Err(err()),
+    }
+}
+
+fn parse_number(s: &str) -> Result<usize, FilterError> {
+    s.parse()
+        .map_err(|_| FilterError(format!("invalid length `{}`", s)))
+}
+
+/// Headers of a frame, as far as they are present.
+#[derive(Debug, Default)]
+struct Headers {
+    len: usize,
+    /// Destination and source MAC addresses.
+    ether: Option<([u8; 6], [u8; 6])>,
+    vlan: bool,
+    ether_type: u16,
+    /// Source and destination addresses, and protocol.
+    ip: Option<(IpAddr, IpAddr, u8)>,
+    /// Source and destination ports of TCP or UDP.
+    ports: Option<(u16, u16)>,
+}
+
+impl Headers {
+    fn parse(frame: &[u8]) -> Self {
+        let mut headers = Self {
+            len: frame.len(),
+            ..Default::default()
+        };
+        if frame.len() < 14 {
+            return headers;
+        }
+        headers.ether = Some((
+            frame[0..6].try_into().unwrap(),
+            frame[6..12].try_into().unwrap(),
+        ));
+        let mut offset = 12;
+        let mut ether_type = u16::from_be_bytes([frame[12], frame[13]]);
+        while matches!(ether_type, ETHER_TYPE_VLAN | ETHER_TYPE_QINQ) && frame.len() >= offset + 8 {
+            headers.vlan = true;
+            offset += 4;
+            ether_type = u16::from_be_bytes([frame[offset], frame[offset + 1]]);
+        }
+        headers.ether_type = ether_type;
+        let l3 = &frame[offset + 2..];
+
+        let (l4, first_fragment) = match ether_type {
+            ETHER_TYPE_IPV4 if l3.len() >= 20 => {
+                let src: [u8; 4] = l3[12..16].try_into().unwrap();
+                let dst: [u8; 4] = l3[16..20].try_into().unwrap();
+                headers.ip = Some((
+                    Ipv4Addr::from(src).into(),
+                    Ipv4Addr::from(dst).into(),
+                    l3[9],
+                ));
+                let fragment_offset = u16::from_be_bytes([l3[6], l3[7]]) & 0x1fff;
+                let header_len = usize::from(l3[0] & 0x0f) * 4;
+                (l3.get(header_len..), fragment_offset == 0)
+            }
+            ETHER_TYPE_IPV6 if l3.len() >= 40 => {
+                let src: [u8; 16] = l3[8..24].try_into().unwrap();
+                let dst: [u8; 16] = l3[24..40].try_into().unwrap();
+                headers.ip = Some((
+                    Ipv6Addr::from(src).into(),
+                    Ipv6Addr::from(dst).into(),
+                    l3[6],
+                ));
+                (l3.get(40..), true)
+            }
+            _ => (None, false),
+        };
+        if let (Some((_, _, IP_PROTO_TCP | IP_PROTO_UDP)), Some(l4), true) =
+            (headers.ip, l4, first_fragment)
+        {
+            if l4.len() >= 4 {
+                headers.ports = Some((
+                    u16::from_be_bytes([l4[0], l4[1]]),
+                    u16::from_be_bytes([l4[2], l4[3]]),
+                ));
+            }
+        }
+        headers
+    }
+}
+
+/// Returns whether `src` or `dst` matches according to `dir`.
+#[inline]
+fn dir_matches<T>(dir: Dir, src: T, dst: T, f: impl Fn(T) -> bool) -> bool {
+    match dir {
+        Dir::Src => f(src),
+        Dir::Dst => f(dst),
+        Dir::Any => f(src) || f(dst),
+    }
+}
+
+fn in_net(addr: IpAddr, net: IpAddr, len: u8) -> bool {
+    match (addr, net) {
+        (IpAddr::V4(addr), IpAddr::V4(net)) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(len)).unwrap_or(0);
+            u32::from(addr) & mask == u32::from(net) & mask
+        }
+        (IpAddr::V6(addr), IpAddr::V6(net)) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(len)).unwrap_or(0);
+            u128::from(addr) & mask == u128::from(net) & mask
+        }
+        _ => false,
+    }
+}
+
+impl Expr {
+    fn eval(&self, headers: &Headers) -> bool {
+        let ip_proto = headers.ip.map(|(_, _, proto)| proto);
+        match self {
+            Expr::Proto(proto) => match proto {
+                Proto::Vlan => headers.vlan,
+                Proto::Arp => headers.ether.is_some() && headers.ether_type == ETHER_TYPE_ARP,
+                Proto::Ip => matches!(headers.ip, Some((IpAddr::V4(_), _, _))),
+                Proto::Ip6 => matches!(headers.ip, Some((IpAddr::V6(_), _, _))),
+                Proto::Tcp => ip_proto == Some(IP_PROTO_TCP),
+                Proto::Udp => ip_proto == Some(IP_PROTO_UDP),
+                Proto::Icmp => {
+                    matches!(headers.ip, Some((IpAddr::V4(_), _, IP_PROTO_ICMP)))
+                }
+                Proto::Icmp6 => {
+                    matches!(headers.ip, Some((IpAddr::V6(_), _, IP_PROTO_ICMPV6)))
+                }
+            },
+            Expr::Ether(dir, mac) => headers
+                .ether
+                .is_some_and(|(dst, src)| dir_matches(*dir, src, dst, |addr| addr == *mac)),
+            Expr::Host(dir, host) => headers
+                .ip
+                .is_some_and(|(src, dst, _)| dir_matches(*dir, src, dst, |addr| addr == *host)),
+            Expr::Net(dir, net, len) => headers.ip.is_some_and(|(src, dst, _)| {
+                dir_matches(*dir, src, dst, |addr| in_net(addr, *net, *len))
+            }),
+            Expr::Port(dir, port) => headers
+                .ports
+                .is_some_and(|(src, dst)| dir_matches(*dir, src, dst, |addr| addr == *port)),
+            Expr::Less(len) => headers.len <= *len,
+            Expr::Greater(len) => headers.len >= *len,
+            Expr::Not(expr) => !expr.eval(headers),
+            Expr::And(lhs, rhs) => lhs.eval(headers) && rhs.eval(headers),
+            Expr::Or(lhs, rhs) => lhs.eval(headers) || rhs.eval(headers),
+        }
+    }
+}
+
+/// Configuration of a [`Capture`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptureConf {
+    /// Maximum number of bytes copied from each packet, which must fit into a single mbuf.
+    pub snap_len: usize,
+    /// Capture only matching packets, all packets if `None`.
+    pub filter: Option<Filter>,
+    /// Number of copies buffered between queues and the writer thread.
+    pub ring_size: usize,
+    /// Number of copies in the capture mempool, which should exceed `ring_size`.
+    pub pool_size: usize,
+}
+
+impl Default for CaptureConf {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            snap_len: 2048,
+            filter: None,
+            ring_size: 4095,
+            pool_size: 8191,
+        }
+    }
+}
+
+/// Counters of a [`Capture`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CaptureStats {
+    /// Packets copied to the ring.
+    pub captured: u64,
+    /// Packets rejected by the filter.
+    pub filtered: u64,
+    /// Packets lost because the mempool or the ring was full.
+    pub dropped: u64,
+    /// Packets written to the file.
+    pub written: u64,
+}
+
+/// Metadata of a captured packet, stored in the private data of its copy.
+#[derive(Debug, Clone, Copy)]
+struct CaptureMeta {
+    timestamp_ns: u64,
+    orig_len: u32,
+    port_id: u16,
+    queue_id: u16,
+    outbound: bool,
+}
+
+// Safety: all-zero is a valid value, and there is no destructor.
+unsafe impl Zeroable for CaptureMeta {}
+
+/// State shared by queue callbacks and the writer thread.
+struct CaptureShared {
+    ring: Ring<CaptureMeta>,
+    pool: MPool<CaptureMeta>,
+    snap_len: usize,
+    filter: RwLock<Option<Filter>>,
+    paused: AtomicBool,
+    stopped: AtomicBool,
+    captured: AtomicU64,
+    filtered: AtomicU64,
+    dropped: AtomicU64,
+    written: AtomicU64,
+}
+
+impl CaptureShared {
+    /// Copy matching packets of a burst to the ring.
+    fn capture<MPoolPriv: Zeroable>(
+        &self,
+        pkts: &[Packet<'_, MPoolPriv>],
+        port_id: u16,
+        queue_id: u16,
+        outbound: bool,
+    ) {
+        if pkts.is_empty() || self.paused.load(Ordering::Relaxed) {
+            return;
+        }
+        let timestamp_ns = pcap::now().as_nanos() as u64;
+        let filter = self.filter.read().unwrap();
+        let (mut captured, mut filtered, mut dropped) = (0, 0, 0);
+        let mut copies = ArrayVec::<Packet<'_, CaptureMeta>, DEFAULT_RX_BURST>::new();
+        for pkt in pkts {
+            if filter
+                .as_ref()
+                .is_none_or(|filter| filter.matches(pkt.data()))
+            {
+                match pkt.copy_data(&self.pool, 0, self.snap_len) {
+                    Some(mut copy) => {
+                        *copy.priv_data_mut() = CaptureMeta {
+                            timestamp_ns,
+                            orig_len: pkt.len() as u32,
+                            port_id,
+                            queue_id,
+                            outbound,
+                        };
+                        copies.push(copy);
+                    }
+                    None => dropped += 1,
+                }
+            } else {
+                filtered += 1;
+            }
+            if copies.is_full() {
+                captured += self.ring.enqueue(&mut copies);
+                dropped += copies.len();
+                copies.clear();
+            }
+        }
+        if !copies.is_empty() {
+            captured += self.ring.enqueue(&mut copies);
+            dropped += copies.len();
+        }
+        self.captured.fetch_add(captured as u64, Ordering::Relaxed);
+        self.filtered.fetch_add(filtered, Ordering::Relaxed);
+        self.dropped.fetch_add(dropped as u64, Ordering::Relaxed);
+    }
+
+    /// Write captured packets until the capture is stopped and the ring is drained.
+    fn write_loop<W: Write>(&self, mut writer: PcapWriter<W>) -> io::Result<()> {
+        // pcapng interface of each port.
+        let mut interfaces = HashMap::new();
+        // The header is flushed as soon as the ring is empty, thus the file is readable.
+        let mut unflushed = true;
+        loop {
+            let stopped = self.stopped.load(Ordering::Acquire);
+            let mut burst = ArrayVec::<Packet<'_, CaptureMeta>, DEFAULT_RX_BURST>::new();
+            if self.ring.dequeue(&mut burst) == 0 {
+                if unflushed {
+                    writer.flush()?;
+                    unflushed = false;
+                }
+                if stopped {
+                    return Ok(());
+                }
+                thread::park_timeout(WRITER_IDLE_TIMEOUT);
+                continue;
+            }
+            for pkt in &burst {
+                let meta = *pkt.priv_data();
+                let interface = match interfaces.get(&meta.port_id) {
+                    Some(interface) => *interface,
+                    None if writer.format() == PcapFormat::PcapNg => {
+                        let interface = writer.add_interface(&format!("port{}", meta.port_id))?;
+                        interfaces.insert(meta.port_id, interface);
+                        interface
+                    }
+                    None => 0,
+                };
+                let info = PcapFrameInfo {
+                    interface,
+                    direction: Some(if meta.outbound {
+                        PcapDirection::Outbound
+                    } else {
+                        PcapDirection::Inbound
+                    }),
+                    queue: Some(meta.queue_id.into()),
+                };
+                writer.write_frame_with(
+                    pkt.data(),
+                    meta.orig_len as usize,
+                    Duration::from_nanos(meta.timestamp_ns),
+                    &info,
+                )?;
+            }
+            self.written
+                .fetch_add(burst.len() as u64, Ordering::Relaxed);
+            unflushed = true;
+        }
+    }
+}
+
+/// A running capture, stopped when dropped.
+///
+/// Methods take `&self`, thus a capture can be controlled from any thread.
+pub struct Capture {
+    shared: Arc<CaptureShared>,
+    /// Callbacks of captured queues, with their port id.
+    callbacks: Mutex<Vec<(u16, QueueCallback)>>,
+    writer: Option<JoinHandle<io::Result<()>>>,
+}
+
+impl fmt::Debug for Capture {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Capture")
+            .field("queues", &self.callbacks.lock().unwrap().len())
+            .field("paused", &self.is_paused())
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+impl Capture {
+    /// Capture packets received by `rxq`.
+    ///
+    /// Returns `ENOTSUP` if rx/tx callbacks are not supported.
+    #[inline]
+    pub fn add_rx<MPoolPriv: Zeroable>(&self, rxq: &RxQ<MPoolPriv>) -> Result<(), ErrorCode> {
+        let (port_id, queue_id) = (rxq.port().port_id(), rxq.queue_id());
+        let shared = self.shared.clone();
+        let cb = rxq.add_callback(move |pkts| {
+            shared.capture(pkts, port_id, queue_id, false);
+            pkts.len()
+        })?;
+        self.callbacks.lock().unwrap().push((port_id, cb));
+        Ok(())
+    }
+
+    /// Capture packets transmitted on `txq`.
+    ///
+    /// Returns `ENOTSUP` if rx/tx callbacks are not supported.
+    #[inline]
+    pub fn add_tx(&self, txq: &TxQ<'_>) -> Result<(), ErrorCode> {
+        let (port_id, queue_id) = (txq.port().port_id(), txq.queue_id());
+        let shared = self.shared.clone();
+        let cb = txq.add_callback(move |pkts| {
+            shared.capture(pkts, port_id, queue_id, true);
+            pkts.len()
+        })?;
+        self.callbacks.lock().unwrap().push((port_id, cb));
+        Ok(())
+    }
+
+    /// Stop capturing the queues of a port.
+    ///
+    /// Note: it waits for the bursts in progress on these queues, thus it must not be called from
+    /// their polling threads.
+    #[inline]
+    pub fn remove_port(&self, port_id: u16) {
+        let removed = {
+            let mut callbacks = self.callbacks.lock().unwrap();
+            let (removed, kept) = callbacks.drain(..).partition(|(id, _)| *id == port_id);
+            *callbacks = kept;
+            removed
+        };
+        drop::<Vec<_>>(removed);
+    }
+
+    /// Replace the filter, `None` to capture every packet.
+    #[inline]
+    pub fn set_filter(&self, filter: Option<Filter>) {
+        *self.shared.filter.write().unwrap() = filter;
+    }
+
+    /// Stop copying packets, without removing queues.
+    #[inline]
+    pub fn pause(&self) {
+        self.shared.paused.store(true, Ordering::Relaxed);
+    }
+
+    /// Copy packets again after [`Capture::pause`].
+    #[inline]
+    pub fn resume(&self) {
+        self.shared.paused.store(false, Ordering::Relaxed);
+    }
+
+    /// Returns whether the capture is paused.
+    #[inline]
+    pub fn is_paused(&self) -> bool {
+        self.shared.paused.load(Ordering::Relaxed)
+    }
+
+    /// Returns counters of the capture since its creation.
+    #[inline]
+    pub fn stats(&self) -> CaptureStats {
+        CaptureStats {
+            captured: self.shared.captured.load(Ordering::Relaxed),
+            filtered: self.shared.filtered.load(Ordering::Relaxed),
+            dropped: self.shared.dropped.load(Ordering::Relaxed),
+            written: self.shared.written.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Remove every queue, write the remaining packets and flush the file.
+    ///
+    /// Returns the final counters, or the first write error.
+    #[inline]
+    pub fn stop(mut self) -> io::Result<CaptureStats> {
+        self.shutdown()?;
+        Ok(self.stats())
+    }
+
+    fn shutdown(&mut self) -> io::Result<()> {
+        self.callbacks.lock().unwrap().clear();
+        self.shared.stopped.store(true, Ordering::Release);
+        match self.writer.take() {
+            Some(writer) => {
+                writer.thread().unpark();
+                writer
+                    .join()
+                    .unwrap_or_else(|_| Err(io::Error::other("capture writer panicked")))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Capture {
+    #[inline]
+    fn drop(&mut self) {
+        if let Err(err) = self.shutdown() {
+            warn!("Capture::drop, error({}) while writing packets", err);
+        }
+    }
+}
+
+impl Eal {
+    /// Start a capture to `writer`, without queues.
+    ///
+    /// `name` prefixes the names of the ring and of the mempool of the capture. Returns `EINVAL`
+    /// if the snap length does not fit into an mbuf.
+    pub fn create_capture<S: AsRef<str>, W: Write + Send + 'static>(
+        &self,
+        name: S,
+        writer: PcapWriter<W>,
+        conf: CaptureConf,
+    ) -> Result<Capture, ErrorCode> {
+        let name = name.as_ref();
+        let data_room_size = conf.snap_len + dpdk_sys::RTE_PKTMBUF_HEADROOM as usize;
+        if conf.snap_len == 0 || data_room_size > u16::MAX.into() {
+            return Err(dpdk_sys::EINVAL.try_into().unwrap());
+        }
+        let ring = self.create_ring(format!("{}_ring", name), conf.ring_size, None)?;
+        let pool = self.create_mpool(
+            format!("{}_pool", name),
+            conf.pool_size,
+            0,
+            data_room_size,
+            None,
+        )?;
+        let shared = Arc::new(CaptureShared {
+            ring,
+            pool,
+            snap_len: conf.snap_len,
+            filter: RwLock::new(conf.filter),
+            paused: AtomicBool::new(false),
+            stopped: AtomicBool::new(false),
+            captured: AtomicU64::new(0),
+            filtered: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            written: AtomicU64::new(0),
+        });
+        let writer_shared = shared.clone();
+        let writer = thread::Builder::new()
+            .name(format!("{}_writer", name))
+            .spawn(move || writer_shared.write_loop(writer))
+            .map_err(|_| -> ErrorCode { dpdk_sys::ENOMEM.try_into().unwrap() })?;
+        info!("Capture {} started", name);
+        Ok(Capture {
+            shared,
+            callbacks: Mutex::new(Vec::new()),
+            writer: Some(writer),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Filter;
+
+    /// Ethernet frame of `len` bytes from 02:00:00:00:00:01 carrying an IPv4 packet from 10.0.0.1 to
+    /// 10.0.0.2, with TCP/UDP ports.
+    fn ipv4_frame(proto: u8, src_port: u16, dst_port: u16, len: usize) -> Vec<u8> {
+        let mut frame = vec![0u8; len];
+        frame[6..12].copy_from_slice(&[2, 0, 0, 0, 0, 1]);
+        frame[12..14].copy_from_slice(&0x0800u16.to_be_bytes());
+        frame[14] = 0x45;
+        frame[23] = proto;
+        frame[26..30].copy_from_slice(&[10, 0, 0, 1]);
+        frame[30..34].copy_from_slice(&[10, 0, 0, 2]);
+        frame[34..36].copy_from_slice(&src_port.to_be_bytes());
+        frame[36..38].copy_from_slice(&dst_port.to_be_bytes());
+        frame
+    }
+
+    #[test]
+    fn filter() {
+        let dns = ipv4_frame(17, 1000, 53, 80);
+        let http = ipv4_frame(6, 1000, 80, 120);
+        let mut tagged = dns[..12].to_vec();
+        tagged.extend_from_slice(&[0x81, 0x00, 0x00, 0x05]);
+        tagged.extend_from_slice(&dns[12..]);
+
+        let matches = |filter: &str, frame: &[u8]| filter.parse::<Filter>().unwrap().matches(frame);
+        assert!(matches("udp port 53", &dns));
+        assert!(!matches("udp port 53", &http));
+        assert!(matches("tcp and dst port 80", &http));
+        assert!(!matches("tcp and src port 80", &http));
+        assert!(matches("src host 10.0.0.1 && !icmp", &http));
+        assert!(matches("dst 10.0.0.2", &dns));
+        assert!(matches("net 10.0.0.0/24 and ip", &dns));
+        assert!(!matches("net 10.0.1.0/24 or ip6", &dns));
+        assert!(matches("ether src 02:00:00:00:00:01", &dns));
+        assert!(!matches("ether dst host 02:00:00:00:00:01", &dns));
+        assert!(matches("vlan and (udp port 53)", &tagged));
+        assert!(!matches("vlan", &dns));
+        assert!(matches("less 80 and greater 80", &dns));
+        assert!(!matches("arp or icmp", &dns));
+
+        for invalid in &[
+            "port",
+            "udp and",
+            "(udp",
+            "foo",
+            "net 10.0.0.0/33",
+            "ether 1:2:3",
+        ] {
+            assert!(invalid.parse::<Filter>().is_err(), "{}", invalid);
+        }
+    }
+}
@@ -1,9 +1,16 @@
 //! Wrapper for DPDK's environment abstraction layer (EAL).
+use crate::callback::Bursts;
 use crate::ffi;
+use crate::mempool::MempoolOps;
+use crate::mp::ProcessType;
 use crate::zeroable::Zeroable;
 use arrayvec::ArrayVec;
 use crossbeam_utils::thread::{Scope, ScopedJoinHandle};
+use dpdk_sys::compat;
+pub use dpdk_sys::{MbufFlags, RssHf, RxOffload, TxOffload};
 use log::{info, warn};
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
 use std::convert::{TryFrom, TryInto};
 use std::ffi::CString;
 use std::fmt;
@@ -11,7 +18,10 @@ use std::marker::PhantomData;
 use std::mem::{size_of, MaybeUninit};
 use std::ptr::{self, NonNull};
 use std::slice;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
 use thiserror::Error;
 
 const MAGIC: &str = "be0dd4ab";
@@ -34,16 +44,39 @@ trait Garbage {
     /// # Safety
     /// `try_collect` must not be called after it returned `true`.
     unsafe fn try_collect(&mut self) -> bool;
+
+    /// Describe the resource which is not collected yet.
+    fn pending(&self) -> PendingGarbage;
+}
+
+/// A mempool whose release is deferred, because it was dropped while some of its objects (e.g.
+/// mbufs) were still in use.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct PendingGarbage {
+    /// Name of the mempool.
+    pub name: String,
+    /// Number of objects which are not returned to the mempool yet.
+    pub in_use: usize,
+}
+
+impl fmt::Display for PendingGarbage {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "mempool {} ({} objects in use)", self.name, self.in_use)
+    }
 }
 
 /// Shared mutating states that all `Eal` instances share.
 struct EalGlobalInner {
     // Whether `setup` has been successfully invoked.
     setup_initialized: bool,
+    // Ports created by hotplug (`attach`, `attach_vdev`). `ports` does not return them again.
+    hotplugged_ports: HashSet<u16>,
+    // Packet pools created by `create_mpool`, by name (for `MPool::lookup`).
+    mpools: HashMap<String, Weak<MPoolInner>>,
     // List of garbage collection requrests.
     // Each req tries garbage collection and returns true on success.
-    // (e.g. `try_free`).
-    // TODO: periodically do cleanup.
+    // (e.g. `try_free`). Retried by `Eal::collect_garbage` and at EAL cleanup.
     garbages: Vec<Box<dyn Garbage>>,
 } // TODO Remove this if unnecessary
 
@@ -52,6 +85,8 @@ impl fmt::Debug for EalGlobalInner {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("EalGlobalInner")
             .field("setup_initialized", &self.setup_initialized)
+            .field("hotplugged_ports", &self.hotplugged_ports)
+            .field("mpools", &self.mpools.keys())
             .field("garbages (count)", &self.garbages.len())
             .finish()
     }
@@ -66,14 +101,30 @@ impl Default for EalGlobalInner {
     fn default() -> Self {
         Self {
             setup_initialized: false,
+            hotplugged_ports: Default::default(),
+            mpools: Default::default(),
             garbages: Default::default(),
         }
     }
 }
 
+impl EalGlobalInner {
+    // Try every garbage collection request, and return the number of succeeded ones.
+    #[inline]
+    fn collect_garbage(&mut self) -> usize {
+        let before = self.garbages.len();
+        // Safety: collected requests are removed, thus never tried again.
+        self.garbages
+            .retain_mut(|gc_req| !unsafe { gc_req.try_collect() });
+        before - self.garbages.len()
+    }
+}
+
 #[derive(Debug)]
 struct EalInner {
-    shared: Mutex<EalGlobalInner>,
+    // Shared with garbage collector threads, which must never own `EalInner` itself: otherwise
+    // the last reference (thus `rte_eal_cleanup`) could be dropped on such a thread.
+    shared: Arc<Mutex<EalGlobalInner>>,
 }
 
 /// DPDK's environment abstraction layer (EAL).
@@ -334,30 +385,104 @@ impl Port {
         }
     }
 
+    /// Returns offloads supported by the port's rx queues.
+    #[inline]
+    pub fn rx_offload_capa(&self) -> Result<RxOffload, ErrorCode> {
+        let dev_info = self.dev_info()?;
+        Ok(RxOffload::from_bits_retain(dev_info.rx_offload_capa))
+    }
+
+    /// Returns offloads supported by the port's tx queues.
+    #[inline]
+    pub fn tx_offload_capa(&self) -> Result<TxOffload, ErrorCode> {
+        let dev_info = self.dev_info()?;
+        Ok(TxOffload::from_bits_retain(dev_info.tx_offload_capa))
+    }
+
+    /// Returns offloads enabled on the port's rx queues (`rxmode.offloads`).
+    #[inline]
+    pub fn rx_offloads(&self) -> RxOffload {
+        self.inner.rx_offloads
+    }
+
+    /// Returns offloads enabled on the port's tx queues (`txmode.offloads`).
+    #[inline]
+    pub fn tx_offloads(&self) -> TxOffload {
+        self.inner.tx_offloads
+    }
+
+    /// Returns the MTU of the port, i.e. the maximum IP packet size.
+    #[inline]
+    pub fn mtu(&self) -> Result<u16, ErrorCode> {
+        let mut mtu = 0;
+        // Safety: foreign function.
+        let ret = unsafe { dpdk_sys::rte_eth_dev_get_mtu(self.inner.port_id, &mut mtu) };
+        if ret != 0 {
+            return Err(ret.try_into().unwrap());
+        }
+        Ok(mtu)
+    }
+
+    #[inline]
+    fn dev_info(&self) -> Result<dpdk_sys::rte_eth_dev_info, ErrorCode> {
+        // Safety: foreign function. `rte_eth_dev_info` allows zeroed structure.
+        unsafe {
+            let mut dev_info: dpdk_sys::rte_eth_dev_info = std::mem::zeroed();
+            let ret = dpdk_sys::rte_eth_dev_info_get(self.inner.port_id, &mut dev_info);
+            if ret != 0 {
+                return Err(ret.try_into().unwrap());
+            }
+            Ok(dev_info)
+        }
+    }
+
     /// Returns true if link is up (connected), false if down.
     #[inline]
     pub fn is_link_up(&self) -> bool {
-        self.get_link().link_status() == dpdk_sys::ETH_LINK_UP as u16
+        self.get_link().link_status() == compat::RTE_ETH_LINK_UP
     }
 }
 
 use dpdk_sys::rte_eth_link as LinkStatus;
 pub use dpdk_sys::rte_eth_stats as PortStat;
 
+/// Resources which must outlive a port (e.g. rings backing a `net_ring` port).
+#[derive(Default)]
+pub(crate) struct KeepAlive(pub(crate) Vec<Arc<dyn Any + Send + Sync>>);
+
+impl fmt::Debug for KeepAlive {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeepAlive")
+            .field("resources (count)", &self.0.len())
+            .finish()
+    }
+}
+
 #[derive(Debug)]
 struct PortInner {
     port_id: u16,
-    owner_id: u64,
+    // Owner claimed by `UninitPort::init`, or `None` if the port is configured by another process.
+    owner_id: Option<u64>,
     has_stats_reset: bool,
     prev_stat: Mutex<PortStat>,
+    rx_offloads: RxOffload,
+    tx_offloads: TxOffload,
     eal: Eal,
+    // Dropped after the port is closed.
+    _keep_alive: KeepAlive,
 }
 
 impl Drop for PortInner {
     #[inline]
     fn drop(&mut self) {
+        let owner_id = match self.owner_id {
+            Some(owner_id) => owner_id,
+            // The port is left to the process which configured it.
+            None => return,
+        };
         // Safety: foreign function.
-        let ret = unsafe { dpdk_sys::rte_eth_dev_owner_unset(self.port_id, self.owner_id) };
+        let ret = unsafe { dpdk_sys::rte_eth_dev_owner_unset(self.port_id, owner_id) };
         assert_eq!(ret, 0);
         unsafe {
             dpdk_sys::rte_eth_dev_stop(self.port_id);
@@ -377,6 +502,7 @@ impl Drop for PortInner {
 pub struct UninitPort {
     port_id: u16,
     eal: Eal,
+    keep_alive: KeepAlive,
 }
 
 pub struct RteEthConf {
@@ -389,6 +515,30 @@ impl RteEthConf {
             data: unsafe { std::mem::zeroed() },
         }
     }
+
+    /// Returns the configuration used by [`UninitPort::init`] when none is given, i.e. RSS is
+    /// enabled if there are multiple rx queues.
+    pub fn for_rx_queues(rx_queue_count: u16) -> RteEthConf {
+        let mut port_conf = RteEthConf::new();
+        compat::set_max_rx_frame_len(&mut port_conf.data, dpdk_sys::RTE_ETHER_MAX_LEN);
+        port_conf.data.rxmode.mq_mode = dpdk_sys::rte_eth_rx_mq_mode::RTE_ETH_MQ_RX_NONE;
+        port_conf.data.txmode.mq_mode = dpdk_sys::rte_eth_tx_mq_mode::RTE_ETH_MQ_TX_NONE;
+        if rx_queue_count > 1 {
+            // Enable RSS.
+            port_conf.data.rxmode.mq_mode = dpdk_sys::rte_eth_rx_mq_mode::RTE_ETH_MQ_RX_RSS;
+            port_conf.data.rx_adv_conf.rss_conf.rss_hf =
+                (RssHf::NONFRAG_IPV4_UDP | RssHf::NONFRAG_IPV4_TCP).bits();
+            // TODO set symmetric RSS for TCP/IP
+        }
+        port_conf
+    }
+
+    /// Enable or disable rx interrupts of the port's queues (`intr_conf.rxq`).
+    ///
+    /// See [`RxQ::enable_interrupt`].
+    pub fn set_rx_interrupts(&mut self, enable: bool) {
+        self.data.intr_conf.set_rxq(enable.into());
+    }
 }
 
 impl UninitPort {
@@ -398,6 +548,12 @@ impl UninitPort {
         self.port_id
     }
 
+    /// Keep a resource alive until the port is closed.
+    #[inline]
+    pub(crate) fn keep_alive(&mut self, resource: Arc<dyn Any + Send + Sync>) {
+        self.keep_alive.0.push(resource);
+    }
+
     /// Initialize port. Configure specified number of rx and tx queues.
     pub fn init<MPoolPriv: Zeroable>(
         self,
@@ -433,35 +589,22 @@ impl UninitPort {
         let ret = unsafe { dpdk_sys::rte_eth_dev_owner_set(self.port_id, &owner) };
         assert_eq!(ret, 0);
 
+        let port_conf = opt_port_conf.unwrap_or_else(|| RteEthConf::for_rx_queues(rx_queue_count));
+
         let mut port = Port {
             inner: Arc::new(PortInner {
                 port_id: self.port_id,
-                owner_id,
+                owner_id: Some(owner_id),
                 has_stats_reset: true,
                 // Safety: PortStat allows zeroed structure.
                 prev_stat: Mutex::new(unsafe { MaybeUninit::zeroed().assume_init() }),
+                rx_offloads: RxOffload::from_bits_retain(port_conf.data.rxmode.offloads),
+                tx_offloads: TxOffload::from_bits_retain(port_conf.data.txmode.offloads),
                 eal: self.eal,
+                _keep_alive: self.keep_alive,
             }),
         };
 
-        let port_conf = if let Some(some_port_conf) = opt_port_conf {
-            some_port_conf
-        } else {
-            let mut port_conf = RteEthConf::new();
-            port_conf.data.rxmode.max_rx_pkt_len = dpdk_sys::RTE_ETHER_MAX_LEN;
-            port_conf.data.rxmode.mq_mode = dpdk_sys::rte_eth_rx_mq_mode_ETH_MQ_RX_NONE;
-            port_conf.data.txmode.mq_mode = dpdk_sys::rte_eth_tx_mq_mode_ETH_MQ_TX_NONE;
-            if rx_queue_count > 1 {
-                // Enable RSS.
-                port_conf.data.rxmode.mq_mode = dpdk_sys::rte_eth_rx_mq_mode_ETH_MQ_RX_RSS;
-                port_conf.data.rx_adv_conf.rss_conf.rss_hf = (dpdk_sys::ETH_RSS_NONFRAG_IPV4_UDP
-                    | dpdk_sys::ETH_RSS_NONFRAG_IPV4_TCP)
-                    .into();
-                // TODO set symmetric RSS for TCP/IP
-            }
-            port_conf
-        };
-
         // Safety: foreign function.
         let ret = unsafe {
             dpdk_sys::rte_eth_dev_configure(
@@ -475,13 +618,17 @@ impl UninitPort {
 
         let rxq = (0..rx_queue_count)
             .map(|queue_id| {
-                let mpool = port.inner.eal.create_mpool(
-                    format!("rxq_{}_{}_{}", MAGIC, port.inner.port_id, queue_id),
-                    DEFAULT_RX_POOL_SIZE,
-                    DEFAULT_RX_PER_CORE_CACHE,
-                    DEFAULT_PACKET_DATA_LENGTH,
-                    Some(port.socket_id()),
-                );
+                let mpool: MPool<MPoolPriv> = port
+                    .inner
+                    .eal
+                    .create_mpool(
+                        format!("rxq_{}_{}_{}", MAGIC, port.inner.port_id, queue_id),
+                        DEFAULT_RX_POOL_SIZE,
+                        DEFAULT_RX_PER_CORE_CACHE,
+                        DEFAULT_PACKET_DATA_LENGTH,
+                        Some(port.socket_id()),
+                    )
+                    .unwrap();
                 let ret = unsafe {
                     dpdk_sys::rte_eth_rx_queue_setup(
                         port.inner.port_id,
@@ -496,7 +643,10 @@ impl UninitPort {
                 RxQ {
                     queue_id,
                     port: port.clone(),
-                    mpool: mpool.inner,
+                    _mpool: mpool.inner,
+                    bursts: Default::default(),
+                    intr_thread: Default::default(),
+                    _phantom: PhantomData,
                     _not_threadsafe: PhantomData,
                 }
             })
@@ -517,6 +667,7 @@ impl UninitPort {
                 TxQ {
                     queue_id,
                     port: port.clone(),
+                    bursts: Default::default(),
                     _pool: PhantomData,
                 }
             })
@@ -535,29 +686,69 @@ impl UninitPort {
 /// Abstract type for DPDK MPool
 #[derive(Debug, Clone)]
 pub struct MPool<MPoolPriv: Zeroable> {
-    inner: Arc<MPoolInner<MPoolPriv>>,
+    inner: Arc<MPoolInner>,
+    _phantom: PhantomData<MPoolPriv>,
 }
 
+/// An owned DPDK mempool, shared by packet pools (`MPool`) and object pools (`ObjectPool`).
 #[derive(Debug)]
-struct MPoolInner<MPoolPriv: Zeroable> {
-    ptr: NonNull<dpdk_sys::rte_mempool>,
+pub(crate) struct MPoolInner {
+    pub(crate) ptr: NonNull<dpdk_sys::rte_mempool>,
+    name: String,
     eal: Arc<EalInner>,
-    _phantom: PhantomData<MPoolPriv>,
+    // Size of mbuf private data, if this is a packet pool.
+    mbuf_priv_size: Option<usize>,
+    // Whether the pool is freed with its last handle. Pools created by another process are not.
+    owned: bool,
 }
 
 /// # Safety
 /// Mempools are thread-safe.
 /// https://doc.dpdk.org/guides/prog_guide/thread_safety_dpdk_functions.html
-unsafe impl<MPoolPriv: Zeroable> Send for MPoolInner<MPoolPriv> {}
-unsafe impl<MPoolPriv: Zeroable> Sync for MPoolInner<MPoolPriv> {}
+unsafe impl Send for MPoolInner {}
+unsafe impl Sync for MPoolInner {}
+
+impl MPoolInner {
+    /// Returns the number of objects which can be allocated.
+    #[inline]
+    pub(crate) fn available(&self) -> usize {
+        // Safety: foreign function.
+        unsafe { dpdk_sys::rte_mempool_avail_count(self.ptr.as_ptr()) as usize }
+    }
+
+    /// Returns the number of allocated objects.
+    #[inline]
+    pub(crate) fn in_use(&self) -> usize {
+        // Safety: foreign function.
+        unsafe { dpdk_sys::rte_mempool_in_use_count(self.ptr.as_ptr()) as usize }
+    }
+
+    /// Check the consistency of the pool. DPDK panics if the pool is corrupted.
+    #[inline]
+    pub(crate) fn audit(&self) {
+        // Safety: foreign function.
+        unsafe { dpdk_sys::rte_mempool_audit(self.ptr.as_ptr()) };
+    }
+
+    /// Returns a human-readable description of the pool (`rte_mempool_dump`).
+    #[inline]
+    pub(crate) fn dump(&self) -> String {
+        // Safety: foreign function. It only prints to the given stream.
+        unsafe { ffi::print_to_string(|file| dpdk_sys::rte_mempool_dump(file, self.ptr.as_ptr())) }
+    }
+}
 
-impl<MPoolPriv: Zeroable> Drop for MPoolInner<MPoolPriv> {
+impl Drop for MPoolInner {
     #[inline]
     fn drop(&mut self) {
+        if !self.owned {
+            return;
+        }
         // Check whether the pool can be destroyed now.
         // Note: I am the only reference to the pool object.
         struct MPoolGcReq {
             ptr: NonNull<dpdk_sys::rte_mempool>,
+            name: String,
         }
         impl Garbage for MPoolGcReq {
             #[inline]
@@ -569,8 +760,21 @@ impl<MPoolPriv: Zeroable> Drop for MPoolInner<MPoolPriv> {
                     false
                 }
             }
+
+            #[inline]
+            fn pending(&self) -> PendingGarbage {
+                PendingGarbage {
+                    name: self.name.clone(),
+                    // Safety: foreign function. The pool is not freed yet.
+                    in_use: unsafe { dpdk_sys::rte_mempool_in_use_count(self.ptr.as_ptr()) }
+                        as usize,
+                }
+            }
         }
-        let mut ret = MPoolGcReq { ptr: self.ptr };
+        let mut ret = MPoolGcReq {
+            ptr: self.ptr,
+            name: std::mem::take(&mut self.name),
+        };
         if !unsafe { ret.try_collect() } {
             // Case: with dangling mbufs
             // Note: deferred free via Eal
@@ -580,6 +784,55 @@ impl<MPoolPriv: Zeroable> Drop for MPoolInner<MPoolPriv> {
 }
 
 impl<MPoolPriv: Zeroable> MPool<MPoolPriv> {
+    /// Find a pool created by [`Eal::create_mpool`] or attached by [`Eal::attach_mpool`] with the
+    /// given name.
+    ///
+    /// Returns `ENOENT` if no such pool is alive, and `EINVAL` if the private data of its packets
+    /// is smaller than `MPoolPriv`.
+    #[inline]
+    pub fn lookup<S: AsRef<str>>(eal: &Eal, name: S) -> Result<Self, ErrorCode> {
+        let mut shared_mut = eal.inner.shared.lock().unwrap();
+        shared_mut.mpools.retain(|_, pool| pool.strong_count() > 0);
+        let inner = match shared_mut.mpools.get(name.as_ref()).and_then(Weak::upgrade) {
+            Some(inner) => inner,
+            None => return Err(dpdk_sys::ENOENT.try_into().unwrap()),
+        };
+        if inner.mbuf_priv_size.unwrap() < size_of::<MPoolPriv>() {
+            return Err(dpdk_sys::EINVAL.try_into().unwrap());
+        }
+        Ok(MPool {
+            inner,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Returns the number of packets which can be allocated.
+    #[inline]
+    pub fn available(&self) -> usize {
+        self.inner.available()
+    }
+
+    /// Returns the number of allocated packets.
+    #[inline]
+    pub fn in_use(&self) -> usize {
+        self.inner.in_use()
+    }
+
+    /// Check the consistency of the pool.
+    ///
+    /// # Panic
+    /// DPDK panics if the pool is corrupted.
+    #[inline]
+    pub fn audit(&self) {
+        self.inner.audit()
+    }
+
+    /// Returns a human-readable description of the pool (`rte_mempool_dump`).
+    #[inline]
+    pub fn dump(&self) -> String {
+        self.inner.dump()
+    }
+
     /// Allocate a `Packet` from the pool.
     #[inline]
     pub fn alloc(&self) -> Option<Packet<'_, MPoolPriv>> {
@@ -626,6 +879,10 @@ impl<MPoolPriv: Zeroable> MPool<MPoolPriv> {
 /// An owned reference to `Packet`.
 ///
 /// Equivalent to Mbuf
+///
+/// Packets may share their buffer with other packets (see [`Packet::clone_shallow`]). Mutable
+/// accessors copy a shared packet into a new mbuf from the same pool first (copy-on-write), and
+/// panic if the pool is exhausted. Use [`Packet::make_unique`] to handle this case.
 #[derive(Debug)]
 #[repr(transparent)]
 pub struct Packet<'pool, MPoolPriv: Zeroable> {
@@ -636,6 +893,8 @@ pub struct Packet<'pool, MPoolPriv: Zeroable> {
 
 unsafe impl<MPoolPriv: Zeroable> Send for Packet<'_, MPoolPriv> {}
 unsafe impl<MPoolPriv: Zeroable> Sync for Packet<'_, MPoolPriv> {}
+// The private data lives in the mbuf, not in `Packet`.
+impl<MPoolPriv: Zeroable> Unpin for Packet<'_, MPoolPriv> {}
 
 impl<MPoolPriv: Zeroable> Packet<'_, MPoolPriv> {
     /// Returns whether `data_len` is zero.
@@ -668,8 +927,24 @@ impl<MPoolPriv: Zeroable> Packet<'_, MPoolPriv> {
     /// TODO we will save non-public, FPS-specific metadata to `MPoolPriv`.
     #[inline]
     pub fn priv_data_mut(&mut self) -> &mut MPoolPriv {
+        let mbuf_ptr = self.header_mut();
         // Safety: All MPool instances have reserved private data for `MPoolPriv`.
-        unsafe { &mut *(dpdk_sys::rte_mbuf_to_priv(self.ptr.as_ptr()) as *mut MPoolPriv) }
+        unsafe { &mut *(dpdk_sys::rte_mbuf_to_priv(mbuf_ptr) as *mut MPoolPriv) }
+    }
+
+    /// Returns offload flags of this packet (`ol_flags`).
+    #[inline]
+    pub fn ol_flags(&self) -> MbufFlags {
+        // Safety: `ptr` is a valid mbuf.
+        MbufFlags::from_bits_retain(unsafe { (*self.ptr.as_ptr()).ol_flags })
+    }
+
+    /// Set offload flags of this packet (`ol_flags`), e.g. to request tx checksum offloads.
+    #[inline]
+    pub fn set_ol_flags(&mut self, flags: MbufFlags) {
+        let mbuf_ptr = self.header_mut();
+        // Safety: `ptr` is a valid mbuf, exclusively owned by this packet.
+        unsafe { (*mbuf_ptr).ol_flags = flags.bits() };
     }
 
     /// Retrieve read-only slice of packet buffer (regardless of `data_offset`).
@@ -682,7 +957,7 @@ impl<MPoolPriv: Zeroable> Packet<'_, MPoolPriv> {
                 (*mbuf_ptr)
                     .buf_addr
                     .add((*mbuf_ptr).data_off.try_into().unwrap()) as *const u8,
-                (*mbuf_ptr).buf_len.into(),
+                ((*mbuf_ptr).buf_len - (*mbuf_ptr).data_off).into(),
             )
         }
     }
@@ -690,13 +965,15 @@ impl<MPoolPriv: Zeroable> Packet<'_, MPoolPriv> {
     /// Retrieve writable slice of packet buffer (regardless of `data_offset`).
     #[inline]
     pub fn buffer_mut(&mut self) -> &mut [u8] {
+        self.make_unique()
+            .expect("failed to copy a shared packet: pool exhausted");
         unsafe {
             let mbuf_ptr = self.ptr.as_ptr();
             slice::from_raw_parts_mut(
                 (*mbuf_ptr)
                     .buf_addr
                     .add((*mbuf_ptr).data_off.try_into().unwrap()) as *mut u8,
-                (*mbuf_ptr).buf_len.into(),
+                ((*mbuf_ptr).buf_len - (*mbuf_ptr).data_off).into(),
             )
         }
     }
@@ -706,8 +983,8 @@ impl<MPoolPriv: Zeroable> Packet<'_, MPoolPriv> {
     #[inline]
     pub fn set_len(&mut self, size: usize) {
         // Safety: buffer boundary is guarded by the assert statement.
+        let mbuf_ptr = self.header_mut();
         unsafe {
-            let mbuf_ptr = self.ptr.as_ptr();
             assert!((*mbuf_ptr).buf_len >= size as u16);
             (*mbuf_ptr).data_len = size as u16;
             (*mbuf_ptr).pkt_len = size as u32;
@@ -732,9 +1009,10 @@ impl<MPoolPriv: Zeroable> Packet<'_, MPoolPriv> {
     /// Panic: when size is out of bound.
     #[inline]
     pub fn trim_head(&mut self, size: usize) {
+        let mbuf_ptr = self.header_mut();
         // Safety: foreign function.
         unsafe {
-            let ret = dpdk_sys::rte_pktmbuf_adj(self.ptr.as_ptr(), size as u16);
+            let ret = dpdk_sys::rte_pktmbuf_adj(mbuf_ptr, size as u16);
             assert_ne!(ret, ptr::null_mut());
         }
     }
@@ -743,9 +1021,10 @@ impl<MPoolPriv: Zeroable> Packet<'_, MPoolPriv> {
     /// Panic: when size is out of bound.
     #[inline]
     pub fn trim_tail(&mut self, size: usize) {
+        let mbuf_ptr = self.header_mut();
         // Safety: foreign function.
         unsafe {
-            let ret = dpdk_sys::rte_pktmbuf_trim(self.ptr.as_ptr(), size as u16);
+            let ret = dpdk_sys::rte_pktmbuf_trim(mbuf_ptr, size as u16);
             assert_eq!(ret, 0);
         }
     }
@@ -754,9 +1033,10 @@ impl<MPoolPriv: Zeroable> Packet<'_, MPoolPriv> {
     /// Note: tail can be reset by setting `data_len` to its buffer capacity.
     #[inline]
     pub fn reset_headroom(&mut self) {
+        let mbuf_ptr = self.header_mut();
         // Safety: foreign function.
         unsafe {
-            dpdk_sys::rte_pktmbuf_reset_headroom(self.ptr.as_ptr());
+            dpdk_sys::rte_pktmbuf_reset_headroom(mbuf_ptr);
         }
     }
 
@@ -764,9 +1044,10 @@ impl<MPoolPriv: Zeroable> Packet<'_, MPoolPriv> {
     /// Panic: when size is out of bound.
     #[inline]
     pub fn prepend(&mut self, size: usize) {
+        let mbuf_ptr = self.header_mut();
         // Safety: foreign function.
         unsafe {
-            let ret = dpdk_sys::rte_pktmbuf_prepend(self.ptr.as_ptr(), size as u16);
+            let ret = dpdk_sys::rte_pktmbuf_prepend(mbuf_ptr, size as u16);
             assert_ne!(ret, ptr::null_mut());
         }
     }
@@ -775,12 +1056,164 @@ impl<MPoolPriv: Zeroable> Packet<'_, MPoolPriv> {
     /// Panic: when size is out of bound.
     #[inline]
     pub fn append(&mut self, size: usize) {
+        let mbuf_ptr = self.header_mut();
         // Safety: foreign function.
         unsafe {
-            let ret = dpdk_sys::rte_pktmbuf_append(self.ptr.as_ptr(), size as u16);
+            let ret = dpdk_sys::rte_pktmbuf_append(mbuf_ptr, size as u16);
             assert_ne!(ret, ptr::null_mut());
         }
     }
+
+    /// Returns the reference count of this packet's mbuf (`rte_mbuf_refcnt_read`).
+    ///
+    /// The count is larger than one while the mbuf is also referenced elsewhere, e.g. by a tx
+    /// queue after [`TxQ::tx_cloned`], or by packets cloned with [`Packet::clone_shallow`].
+    #[inline]
+    pub fn refcnt(&self) -> u16 {
+        // Safety: foreign function.
+        unsafe { dpdk_sys::rte_mbuf_refcnt_read(self.ptr.as_ptr()) }
+    }
+
+    /// Returns whether the data buffer of this packet is shared with other packets.
+    ///
+    /// External buffers (see [`Packet::attach_extbuf`]) are read-only, thus always shared.
+    ///
+    /// Note: only the first segment is checked, as it is the only one accessible via `buffer`.
+    #[inline]
+    pub fn is_shared(&self) -> bool {
+        if self.refcnt() > 1 || self.ol_flags().contains(MbufFlags::EXTERNAL) {
+            return true;
+        }
+        if !self.ol_flags().contains(MbufFlags::INDIRECT) {
+            return false;
+        }
+        // Safety: foreign function. Indirect mbufs keep their direct mbuf alive.
+        unsafe {
+            let direct = dpdk_sys::rte_mbuf_from_indirect(self.ptr.as_ptr());
+            dpdk_sys::rte_mbuf_refcnt_read(direct) > 1
+        }
+    }
+
+    /// Make sure that the data buffer of this packet is not shared, by replacing a shared packet
+    /// with a deep copy allocated from the pool of the mbuf owning the buffer.
+    ///
+    /// Returns `ENOMEM` if the pool is exhausted.
+    #[inline]
+    pub fn make_unique(&mut self) -> Result<(), ErrorCode> {
+        if self.is_shared() {
+            self.copy_on_write()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Create a packet sharing the data buffer of this packet (`rte_pktmbuf_clone`).
+    ///
+    /// The clone is an indirect mbuf allocated from `pool`, and gets a copy of the private data.
+    /// The buffer is released when both packets are dropped.
+    /// Returns `None` if `pool` is exhausted.
+    #[inline]
+    pub fn clone_shallow<'a>(&self, pool: &'a MPool<MPoolPriv>) -> Option<Packet<'a, MPoolPriv>> {
+        // Safety: foreign function.
+        let pkt_ptr =
+            unsafe { dpdk_sys::rte_pktmbuf_clone(self.ptr.as_ptr(), pool.inner.ptr.as_ptr()) };
+        let ptr = NonNull::new(pkt_ptr)?;
+        // Safety: both mbufs have reserved private data for `MPoolPriv`.
+        unsafe { Self::copy_priv_data(self.ptr, ptr) };
+        Some(Packet {
+            ptr,
+            _phantom: PhantomData {},
+            _pool: PhantomData {},
+        })
+    }
+
+    /// Create a deep copy of at most `len` bytes of this packet from `offset`
+    /// (`rte_pktmbuf_copy`).
+    ///
+    /// The copy is allocated from `pool` and gets a copy of the private data.
+    /// Returns `None` if `pool` is exhausted or `offset` is larger than the packet length.
+    #[inline]
+    pub fn copy<'a>(
+        &self,
+        pool: &'a MPool<MPoolPriv>,
+        offset: usize,
+        len: usize,
+    ) -> Option<Packet<'a, MPoolPriv>> {
+        let copy = self.copy_data(pool, offset, len)?;
+        // Safety: both mbufs have reserved private data for `MPoolPriv`.
+        unsafe { Self::copy_priv_data(self.ptr, copy.ptr) };
+        Some(copy)
+    }
+
+    /// Create a deep copy like [`Packet::copy`] into a pool of any private data type, whose
+    /// private data is left as allocated.
+    #[inline]
+    pub(crate) fn copy_data<'a, T: Zeroable>(
+        &self,
+        pool: &'a MPool<T>,
+        offset: usize,
+        len: usize,
+    ) -> Option<Packet<'a, T>> {
+        let offset = offset.try_into().ok()?;
+        let len = len.try_into().unwrap_or(u32::MAX);
+        // Safety: foreign function.
+        let pkt_ptr = unsafe {
+            dpdk_sys::rte_pktmbuf_copy(self.ptr.as_ptr(), pool.inner.ptr.as_ptr(), offset, len)
+        };
+        Some(Packet {
+            ptr: NonNull::new(pkt_ptr)?,
+            _phantom: PhantomData {},
+            _pool: PhantomData {},
+        })
+    }
+
+    /// Returns the mbuf for modifying its header, copying it first if it is referenced elsewhere.
+    #[inline]
+    pub(crate) fn header_mut(&mut self) -> *mut dpdk_sys::rte_mbuf {
+        if self.refcnt() > 1 {
+            self.copy_on_write()
+                .expect("failed to copy a shared packet: pool exhausted");
+        }
+        self.ptr.as_ptr()
+    }
+
+    /// Replace the mbuf with a deep copy from the pool of the mbuf owning the buffer.
+    ///
+    /// Note: pools of indirect mbufs usually have no data room, thus they are not used.
+    fn copy_on_write(&mut self) -> Result<(), ErrorCode> {
+        let mbuf_ptr = self.ptr.as_ptr();
+        // Safety: foreign function. Pools are not freed while their mbufs are in use.
+        let pkt_ptr = unsafe {
+            let direct = if self.ol_flags().contains(MbufFlags::INDIRECT) {
+                dpdk_sys::rte_mbuf_from_indirect(mbuf_ptr)
+            } else {
+                mbuf_ptr
+            };
+            dpdk_sys::rte_pktmbuf_copy(mbuf_ptr, (*direct).pool, 0, u32::MAX)
+        };
+        let ptr = match NonNull::new(pkt_ptr) {
+            Some(ptr) => ptr,
+            None => return Err(dpdk_sys::ENOMEM.try_into().unwrap()),
+        };
+        // Safety: both mbufs have reserved private data for `MPoolPriv`.
+        // Safety: foreign function. Our reference to the old mbuf is not used anymore.
+        unsafe {
+            Self::copy_priv_data(self.ptr, ptr);
+            dpdk_sys::rte_pktmbuf_free(mbuf_ptr);
+        }
+        self.ptr = ptr;
+        Ok(())
+    }
+
+    /// Copy private data (`MPoolPriv`) of mbuf `src` to mbuf `dst`.
+    #[inline]
+    unsafe fn copy_priv_data(src: NonNull<dpdk_sys::rte_mbuf>, dst: NonNull<dpdk_sys::rte_mbuf>) {
+        ptr::copy_nonoverlapping(
+            dpdk_sys::rte_mbuf_to_priv(src.as_ptr()) as *const MPoolPriv,
+            dpdk_sys::rte_mbuf_to_priv(dst.as_ptr()) as *mut MPoolPriv,
+            1,
+        );
+    }
 }
 
 impl<MPoolPriv: Zeroable> Drop for Packet<'_, MPoolPriv> {
@@ -800,7 +1233,12 @@ impl<MPoolPriv: Zeroable> Drop for Packet<'_, MPoolPriv> {
 pub struct RxQ<MPoolPriv: Zeroable> {
     queue_id: u16,
     port: Port,
-    mpool: Arc<MPoolInner<MPoolPriv>>,
+    /// Keeps the pool alive, as the driver allocates received packets from it.
+    _mpool: Arc<MPoolInner>,
+    pub(crate) bursts: Arc<Bursts>,
+    /// Thread whose epoll instance waits for the rx interrupt.
+    pub(crate) intr_thread: std::cell::Cell<Option<thread::ThreadId>>,
+    _phantom: PhantomData<MPoolPriv>,
     /// !Sync marker. RxQ is supposed to be accessed only from a single thread
     // Note: This single-threaded limitation could also be implemented by making rx() take
     // exclusive reference (`&mut self`), but currently `rx` takes `&self`.
@@ -810,6 +1248,7 @@ pub struct RxQ<MPoolPriv: Zeroable> {
 impl<MPoolPriv: Zeroable> Drop for RxQ<MPoolPriv> {
     #[inline]
     fn drop(&mut self) {
+        self.unregister_interrupt();
         // Safety: foreign function.
         //
         // Note: dynamically starting/stopping queue may not be supported by the driver.
@@ -846,6 +1285,7 @@ impl<MPoolPriv: Zeroable> RxQ<MPoolPriv> {
     ) {
         let current = buffer.len();
         let remaining = buffer.capacity() - current;
+        let _burst = self.bursts.enter();
         unsafe {
             let pkt_buffer = buffer.as_mut_ptr() as *mut *mut dpdk_sys::rte_mbuf;
             let cnt = dpdk_sys::rte_eth_rx_burst(
@@ -876,6 +1316,7 @@ impl<MPoolPriv: Zeroable> RxQ<MPoolPriv> {
 pub struct TxQ<'pool> {
     queue_id: u16,
     port: Port,
+    pub(crate) bursts: Arc<Bursts>,
     _pool: PhantomData<&'pool MPool<()>>,
 }
 
@@ -930,6 +1371,7 @@ impl<'pool> TxQ<'pool> {
         // Safety: foreign function.
         // Safety: `pkt_buffer` is safe to read till `pkt_buffer[current]`.
         let cnt = unsafe {
+            let _burst = self.bursts.enter();
             dpdk_sys::rte_eth_tx_burst(
                 self.port.inner.port_id,
                 self.queue_id,
@@ -950,46 +1392,59 @@ impl<'pool> TxQ<'pool> {
 
     /// Make copies of MBufs and transmit them
     ///
-    /// Returns number of packets sent (transmitted to the send queue)
+    /// Returns number of packets sent (transmitted to the send queue). Packets are sent in bursts
+    /// of [`DEFAULT_TX_BURST`], and sending stops at the first burst the queue cannot take fully.
     ///
     /// See [`TxQ::tx()`]
     #[inline]
     pub fn tx_cloned<MPoolPriv: Zeroable>(&mut self, buffer: &[Packet<'pool, MPoolPriv>]) -> usize {
-        let current = buffer.len();
+        let mut sent = 0;
 
-        for pkt in buffer {
-            // Safety: foreign function.
-            // Note: It does not cause memory leak as tx_burst decreases the reference count.
-            unsafe { dpdk_sys::rte_pktmbuf_refcnt_update(pkt.ptr.as_ptr(), 1) };
-        }
+        // Transmit in bursts of `DEFAULT_TX_BURST`, copying mbuf pointers on the stack, as tx
+        // callbacks may reorder them.
+        for chunk in buffer.chunks(DEFAULT_TX_BURST) {
+            let mut pkts = ArrayVec::<*mut dpdk_sys::rte_mbuf, DEFAULT_TX_BURST>::new();
+            for pkt in chunk {
+                // Safety: foreign function.
+                // Note: It does not cause memory leak as tx_burst decreases the reference count.
+                unsafe { dpdk_sys::rte_pktmbuf_refcnt_update(pkt.ptr.as_ptr(), 1) };
+                pkts.push(pkt.ptr.as_ptr());
+            }
+            let current = pkts.len();
+            let pkt_buffer = pkts.as_mut_ptr();
 
-        // Get raw pointer of arrayvec
-        let pkt_buffer = buffer.as_ptr() as *mut *mut dpdk_sys::rte_mbuf;
+            // Try transmit packets. It will return number of successfully transmitted packets.
+            // Successfully transmitted packets are automatically dropped by `rte_eth_tx_burst`.
+            //
+            // Safety: foreign function.
+            // Safety: `pkt_buffer` is safe to read till `pkt_buffer[current]`.
+            let cnt = unsafe {
+                let _burst = self.bursts.enter();
+                dpdk_sys::rte_eth_tx_burst(
+                    self.port.inner.port_id,
+                    self.queue_id,
+                    pkt_buffer,
+                    current as u16,
+                )
+            };
+            let cnt = usize::from(cnt);
 
-        // Try transmit packets. It will return number of successfully transmitted packets.
-        // Successfully transmitted packets are automatically dropped by `rte_eth_tx_burst`.
-        //
-        // Safety: foreign function.
-        // Safety: `pkt_buffer` is safe to read till `pkt_buffer[current]`.
-        let cnt = unsafe {
-            dpdk_sys::rte_eth_tx_burst(
-                self.port.inner.port_id,
-                self.queue_id,
-                pkt_buffer,
-                current as u16,
-            )
-        };
-        let cnt = usize::from(cnt);
+            // We have to manually free unsent packets, or some packets will leak.
+            for i in cnt..current {
+                // Safety: foreign function.
+                // Safety: pkt's refcount is already increased thus there is no use-after-free.
+                unsafe { dpdk_sys::rte_pktmbuf_free(*(pkt_buffer.add(i))) };
+            }
+            // As all mbuf's references are already increases, we do not have to free the arrayvec.
 
-        // We have to manually free unsent packets, or some packets will leak.
-        for i in cnt..current {
-            // Safety: foreign function.
-            // Safety: pkt's refcount is already increased thus there is no use-after-free.
-            unsafe { dpdk_sys::rte_pktmbuf_free(*(pkt_buffer.add(i))) };
+            sent += cnt;
+            if cnt < current {
+                // The queue is full; do not try the remaining bursts.
+                break;
+            }
         }
-        // As all mbuf's references are already increases, we do not have to free the arrayvec.
 
-        cnt
+        sent
     }
 
     /// Get port of this queue.
@@ -1010,10 +1465,33 @@ impl Eal {
         })
     }
 
-    /// Create a new `MPool`.
+    /// Add EAL arguments loading PMD plugins (`-d <path>`) to `args`.
     ///
-    /// # Panic
-    /// Pool name must be globally unique, otherwise it will panic.
+    /// Each path can be either a shared object or a folder of shared objects. This is required to
+    /// use PMDs with shared DPDK builds (`dynamic` feature), where PMDs are not linked to the
+    /// application. If `plugins` is empty, the PMD folder found at build time
+    /// (`dpdk_sys::PMD_PATH`) is used, if any.
+    ///
+    /// Arguments are inserted right after the program name (`args[0]`), so that they are consumed
+    /// by [`Eal::new`] along with other EAL arguments.
+    #[inline]
+    pub fn add_plugin_args<S: AsRef<str>>(args: &mut Vec<String>, plugins: &[S]) {
+        let plugins: Vec<&str> = if plugins.is_empty() {
+            dpdk_sys::PMD_PATH.into_iter().collect()
+        } else {
+            plugins.iter().map(AsRef::as_ref).collect()
+        };
+        let position = std::cmp::min(1, args.len());
+        let plugin_args = plugins
+            .into_iter()
+            .flat_map(|plugin| vec!["-d".to_string(), plugin.to_string()]);
+        let _ = args.splice(position..position, plugin_args);
+    }
+
+    /// Create a new `MPool` with the default mempool ops.
+    ///
+    /// Returns `EEXIST` if a mempool with the given name already exists, as pool names must be
+    /// globally unique.
     ///
     /// @param n The number of elements in the mbuf pool.
     ///
@@ -1032,57 +1510,364 @@ impl Eal {
         cache_size: usize,
         data_room_size: usize,
         socket_id: Option<SocketId>,
-    ) -> MPool<MPoolPriv> {
+    ) -> Result<MPool<MPoolPriv>, ErrorCode> {
+        self.create_mpool_inner(name, n, cache_size, data_room_size, socket_id, None)
+    }
+
+    /// Create a new `MPool` storing free packets with the given mempool ops.
+    ///
+    /// See [`Eal::create_mpool`] for parameters. Returns the error reported by DPDK if the pool
+    /// cannot be created with the ops (e.g. `EINVAL` if the mempool driver is not linked).
+    #[inline]
+    pub fn create_mpool_by_ops<S: AsRef<str>, MPoolPriv: Zeroable>(
+        &self,
+        name: S,
+        n: usize,
+        cache_size: usize,
+        data_room_size: usize,
+        socket_id: Option<SocketId>,
+        ops: MempoolOps,
+    ) -> Result<MPool<MPoolPriv>, ErrorCode> {
+        self.create_mpool_inner(name, n, cache_size, data_room_size, socket_id, Some(ops))
+    }
+
+    fn create_mpool_inner<S: AsRef<str>, MPoolPriv: Zeroable>(
+        &self,
+        name: S,
+        n: usize,
+        cache_size: usize,
+        data_room_size: usize,
+        socket_id: Option<SocketId>,
+        ops: Option<MempoolOps>,
+    ) -> Result<MPool<MPoolPriv>, ErrorCode> {
         let pool_name = CString::new(name.as_ref()).unwrap();
 
+        // Safety: foreign function.
+        if !unsafe { dpdk_sys::rte_mempool_lookup(pool_name.as_ptr()) }.is_null() {
+            // Mempool names must be globally unique.
+            return Err(dpdk_sys::EEXIST.try_into().unwrap());
+        }
+        let priv_size = size_of::<MPoolPriv>().div_ceil(8) * 8;
+        let socket_id = socket_id
+            .map(|x| x.0 as i32)
+            .unwrap_or(dpdk_sys::SOCKET_ID_ANY);
         // Safety: foreign function.
         let ptr = unsafe {
-            dpdk_sys::rte_pktmbuf_pool_create(
-                pool_name.into_bytes_with_nul().as_ptr() as *mut i8,
-                n.try_into().unwrap(),
-                cache_size as u32,
-                (((size_of::<MPoolPriv>() + 7) / 8) * 8) as u16,
-                data_room_size.try_into().unwrap(),
-                socket_id
-                    .map(|x| x.0 as i32)
-                    .unwrap_or(dpdk_sys::SOCKET_ID_ANY),
-            )
+            match ops {
+                Some(ops) => dpdk_sys::rte_pktmbuf_pool_create_by_ops(
+                    pool_name.as_ptr(),
+                    n.try_into().unwrap(),
+                    cache_size as u32,
+                    priv_size as u16,
+                    data_room_size.try_into().unwrap(),
+                    socket_id,
+                    ops.name().as_ptr(),
+                ),
+                None => dpdk_sys::rte_pktmbuf_pool_create(
+                    pool_name.as_ptr(),
+                    n.try_into().unwrap(),
+                    cache_size as u32,
+                    priv_size as u16,
+                    data_room_size.try_into().unwrap(),
+                    socket_id,
+                ),
+            }
+        };
+        let ptr = match NonNull::new(ptr) {
+            Some(ptr) => ptr,
+            None => return Err(rte_errno()),
         };
 
         let inner = Arc::new(MPoolInner {
-            ptr: NonNull::new(ptr).unwrap(), // will panic if the given name is not unique.
+            ptr,
+            name: name.as_ref().to_owned(),
             eal: self.inner.clone(),
-            _phantom: PhantomData {},
+            mbuf_priv_size: Some(priv_size),
+            owned: true,
         });
+        self.inner
+            .shared
+            .lock()
+            .unwrap()
+            .mpools
+            .insert(name.as_ref().to_owned(), Arc::downgrade(&inner));
+        Ok(MPool {
+            inner,
+            _phantom: PhantomData,
+        })
+    }
 
-        // The pointer to the new allocated mempool, on success. NULL on error with rte_errno set appropriately.
-        // https://doc.dpdk.org/api/rte__mbuf_8h.html
-        MPool { inner }
+    /// Create an `MPoolInner` owning the given mempool, which is not a packet pool.
+    #[inline]
+    pub(crate) fn own_mempool(
+        &self,
+        ptr: NonNull<dpdk_sys::rte_mempool>,
+        name: String,
+    ) -> MPoolInner {
+        MPoolInner {
+            ptr,
+            name,
+            eal: self.inner.clone(),
+            mbuf_priv_size: None,
+            owned: true,
+        }
+    }
+
+    /// Attach to a packet pool created by another process (e.g. the primary) with the given name
+    /// (`rte_mempool_lookup`).
+    ///
+    /// The pool is not freed when the returned handles are dropped, and it is found by
+    /// [`MPool::lookup`] while they are alive. Returns `ENOENT` if there is no such pool, and
+    /// `EINVAL` if the private data of its packets is smaller than `MPoolPriv`.
+    ///
+    /// # Safety
+    ///
+    /// The pool must be a packet pool (e.g. created by `rte_pktmbuf_pool_create`), as the private
+    /// data size of its packets is read from the private data of the pool.
+    #[inline]
+    pub unsafe fn attach_mpool<S: AsRef<str>, MPoolPriv: Zeroable>(
+        &self,
+        name: S,
+    ) -> Result<MPool<MPoolPriv>, ErrorCode> {
+        let pool_name = CString::new(name.as_ref()).unwrap();
+        // Safety: foreign function.
+        let ptr = match NonNull::new(dpdk_sys::rte_mempool_lookup(pool_name.as_ptr())) {
+            Some(ptr) => ptr,
+            None => return Err(dpdk_sys::ENOENT.try_into().unwrap()),
+        };
+        // Safety: foreign function. The caller guarantees that the pool is a packet pool.
+        let priv_size = usize::from(dpdk_sys::rte_pktmbuf_priv_size(ptr.as_ptr()));
+        if priv_size < size_of::<MPoolPriv>() {
+            return Err(dpdk_sys::EINVAL.try_into().unwrap());
+        }
+        let inner = Arc::new(MPoolInner {
+            ptr,
+            name: name.as_ref().to_owned(),
+            eal: self.inner.clone(),
+            mbuf_priv_size: Some(priv_size),
+            owned: false,
+        });
+        // Pools created or attached before by this process are kept.
+        let mut shared_mut = self.inner.shared.lock().unwrap();
+        shared_mut.mpools.retain(|_, pool| pool.strong_count() > 0);
+        shared_mut
+            .mpools
+            .entry(name.as_ref().to_owned())
+            .or_insert_with(|| Arc::downgrade(&inner));
+        Ok(MPool {
+            inner,
+            _phantom: PhantomData,
+        })
     }
 
     /// Get list of available, uninitialized ports.
-    /// Should be called once.
+    /// Should be called once. Ports hotplugged later are returned by [`Eal::attach`] and
+    /// [`Eal::attach_vdev`].
+    ///
+    /// Returns `EPERM` in secondary processes, which use ports configured by the primary
+    /// ([`Eal::attach_port`]).
     #[inline]
     pub fn ports(&self) -> Result<Vec<UninitPort>, ErrorCode> {
+        if self.process_type() == ProcessType::Secondary {
+            return Err(dpdk_sys::EPERM.try_into().unwrap());
+        }
         let mut shared_mut = self.inner.shared.lock().unwrap();
         if shared_mut.setup_initialized {
             // Already initialized.
             return Err(dpdk_sys::EALREADY.try_into().unwrap());
         }
-        let port_list = (0..u16::try_from(dpdk_sys::RTE_MAX_ETHPORTS).unwrap())
-            .filter(|index| {
-                // Safety: foreign function.
-                unsafe { dpdk_sys::rte_eth_dev_is_valid_port(*index) > 0 }
-            })
+        let port_list = valid_port_ids()
+            .filter(|port_id| !shared_mut.hotplugged_ports.contains(port_id))
             .map(|port_id| UninitPort {
                 port_id,
                 eal: self.clone(),
+                keep_alive: Default::default(),
             })
             .collect::<Vec<_>>();
         shared_mut.setup_initialized = true;
         Ok(port_list)
     }
 
+    /// Use a port configured by another process (e.g. the primary), given its device name
+    /// (`rte_eth_dev_get_port_by_name`).
+    ///
+    /// Unlike [`Eal::attach`], no device is probed. The port is neither claimed nor configured,
+    /// and it is not closed when the returned handles are dropped. Offloads enabled by the other
+    /// process are unknown, thus [`Port::rx_offloads`] and [`Port::tx_offloads`] are empty.
+    /// [`Port::reset_stat`] only resets the statistics seen by this process.
+    ///
+    /// The port gives no access to rx and tx queues, which belong to the other process: it is
+    /// meant for statistics and link status.
+    ///
+    /// Returns `ENODEV` if there is no such port.
+    #[inline]
+    pub fn attach_port<S: AsRef<str>>(&self, name: S) -> Result<Port, ErrorCode> {
+        let dev_name = CString::new(name.as_ref()).unwrap();
+        let mut port_id = 0;
+        // Safety: foreign function.
+        let ret =
+            unsafe { dpdk_sys::rte_eth_dev_get_port_by_name(dev_name.as_ptr(), &mut port_id) };
+        if ret < 0 {
+            return Err(dpdk_sys::ENODEV.try_into().unwrap());
+        }
+        Ok(Port {
+            inner: Arc::new(PortInner {
+                port_id,
+                owner_id: None,
+                has_stats_reset: false,
+                // Safety: PortStat allows zeroed structure.
+                prev_stat: Mutex::new(unsafe { MaybeUninit::zeroed().assume_init() }),
+                rx_offloads: RxOffload::empty(),
+                tx_offloads: TxOffload::empty(),
+                eal: self.clone(),
+                _keep_alive: Default::default(),
+            }),
+        })
+    }
+
+    /// Hotplug a virtual device and return the ports it created.
+    ///
+    /// `name` is the device name which also selects the driver (e.g. `net_null0`, `net_tap1`).
+    /// `devargs` are driver-specific arguments (e.g. `iface=dtap0`) and may be empty.
+    #[inline]
+    pub fn attach_vdev<S: AsRef<str>, T: AsRef<str>>(
+        &self,
+        name: S,
+        devargs: T,
+    ) -> Result<Vec<UninitPort>, ErrorCode> {
+        let bus_name = CString::new("vdev").unwrap();
+        let dev_name = c_string(name.as_ref())?;
+        let dev_args = c_string(devargs.as_ref())?;
+        // Safety: foreign function. All strings outlive the call.
+        self.probe_with(|| unsafe {
+            dpdk_sys::rte_eal_hotplug_add(bus_name.as_ptr(), dev_name.as_ptr(), dev_args.as_ptr())
+        })
+    }
+
+    /// Hotplug a device described by a full devargs string and return the ports it created.
+    ///
+    /// The string has the same format as EAL's `--vdev`/`-a` options, e.g.
+    /// `net_ring0,nodeaction=r0:0:CREATE` or `0000:01:00.0`.
+    #[inline]
+    pub fn attach<S: AsRef<str>>(&self, devargs: S) -> Result<Vec<UninitPort>, ErrorCode> {
+        let dev_args = c_string(devargs.as_ref())?;
+        // Safety: foreign function. The string outlives the call.
+        self.probe_with(|| unsafe { dpdk_sys::rte_dev_probe(dev_args.as_ptr()) })
+    }
+
+    /// Hot-unplug the device backing an uninitialized port.
+    ///
+    /// Note: a device may expose more than one port, all of them are removed.
+    #[inline]
+    pub fn detach(&self, port: UninitPort) -> Result<(), ErrorCode> {
+        // Safety: foreign function. `rte_eth_dev_info` allows zeroed structure.
+        let device = unsafe {
+            let mut dev_info: dpdk_sys::rte_eth_dev_info = std::mem::zeroed();
+            let ret = dpdk_sys::rte_eth_dev_info_get(port.port_id, &mut dev_info);
+            if ret < 0 {
+                return Err(ret.try_into().unwrap());
+            }
+            dev_info.device
+        };
+        // Safety: foreign function. The port is not configured, thus nothing refers to it.
+        self.remove_with(|| unsafe { dpdk_sys::rte_dev_remove(device) })?;
+        info!("Port {} detached", port.port_id);
+        Ok(())
+    }
+
+    /// Run a device removal function and forget hotplugged ports that disappeared.
+    ///
+    /// Every port of the removed device is forgotten, not only the one used to find it.
+    pub(crate) fn remove_with(&self, remove: impl FnOnce() -> i32) -> Result<(), ErrorCode> {
+        let mut shared_mut = self.inner.shared.lock().unwrap();
+        let ret = remove();
+        if ret < 0 {
+            return Err(ret.try_into().unwrap());
+        }
+        let remaining_ports = valid_port_ids().collect::<HashSet<_>>();
+        shared_mut
+            .hotplugged_ports
+            .retain(|port_id| remaining_ports.contains(port_id));
+        Ok(())
+    }
+
+    /// Run a probing function and collect ports that appeared during the probe.
+    pub(crate) fn probe_with(
+        &self,
+        probe: impl FnOnce() -> i32,
+    ) -> Result<Vec<UninitPort>, ErrorCode> {
+        // Holding the lock serializes concurrent probes, so new ports are attributed correctly.
+        let mut shared_mut = self.inner.shared.lock().unwrap();
+        let known_ports = valid_port_ids().collect::<HashSet<_>>();
+        let ret = probe();
+        if ret < 0 {
+            return Err(ret.try_into().unwrap());
+        }
+        let port_list = valid_port_ids()
+            .filter(|port_id| !known_ports.contains(port_id))
+            .map(|port_id| UninitPort {
+                port_id,
+                eal: self.clone(),
+                keep_alive: Default::default(),
+            })
+            .collect::<Vec<_>>();
+        shared_mut
+            .hotplugged_ports
+            .extend(port_list.iter().map(|port| port.port_id));
+        Ok(port_list)
+    }
+
+    /// Free mempools which were dropped while their objects were in use, if all objects have
+    /// returned since then.
+    ///
+    /// Returns the number of freed mempools. Remaining ones are reported by
+    /// [`Eal::pending_garbage`]. Collection is also tried when EAL is cleaned up.
+    #[inline]
+    pub fn collect_garbage(&self) -> usize {
+        self.inner.shared.lock().unwrap().collect_garbage()
+    }
+
+    /// Returns mempools waiting for garbage collection, with the number of their objects in use.
+    #[inline]
+    pub fn pending_garbage(&self) -> Vec<PendingGarbage> {
+        let shared = self.inner.shared.lock().unwrap();
+        shared
+            .garbages
+            .iter()
+            .map(|gc_req| gc_req.pending())
+            .collect()
+    }
+
+    /// Spawn a thread running [`Eal::collect_garbage`] every `interval`.
+    ///
+    /// The thread stops when the returned handle is dropped. It does not keep EAL alive, and EAL
+    /// is never cleaned up on it: the thread only references the garbage list, which is drained
+    /// before `rte_eal_cleanup` is called.
+    #[inline]
+    pub fn spawn_garbage_collector(&self, interval: Duration) -> GarbageCollector {
+        let stop = Arc::new(AtomicBool::new(false));
+        let shared = Arc::downgrade(&self.inner.shared);
+        let thread_stop = stop.clone();
+        let thread = thread::Builder::new()
+            .name("dpdk-gc".into())
+            .spawn(move || {
+                while !thread_stop.load(Ordering::Acquire) {
+                    thread::park_timeout(interval);
+                    match shared.upgrade() {
+                        Some(shared) => {
+                            shared.lock().unwrap().collect_garbage();
+                        }
+                        None => break,
+                    }
+                }
+            })
+            .expect("failed to spawn garbage collector");
+        GarbageCollector {
+            stop,
+            thread: Some(thread),
+        }
+    }
+
     /// Get a vector of enabled lcores.
     #[inline]
     pub fn lcores(&self) -> Vec<LCoreId> {
@@ -1106,10 +1891,52 @@ impl Eal {
     }
 }
 
+/// Returns `rte_errno` of the calling thread.
+///
+/// DPDK-specific codes (`E_RTE_*`) do not fit in `ErrorCode`, thus they are reported as `EINVAL`.
+pub(crate) fn rte_errno() -> ErrorCode {
+    // Safety: foreign function.
+    let errno = unsafe { dpdk_sys::rte_errno() };
+    u8::try_from(errno).map_or_else(|_| dpdk_sys::EINVAL.try_into().unwrap(), ErrorCode::from)
+}
+
+/// Convert a string to a C string, or return `EINVAL` if it contains a NUL byte.
+fn c_string(s: &str) -> Result<CString, ErrorCode> {
+    CString::new(s).map_err(|_| dpdk_sys::EINVAL.try_into().unwrap())
+}
+
+/// Iterate over ids of all valid ethdev ports.
+fn valid_port_ids() -> impl Iterator<Item = u16> {
+    (0..u16::try_from(dpdk_sys::RTE_MAX_ETHPORTS).unwrap()).filter(|index| {
+        // Safety: foreign function.
+        unsafe { dpdk_sys::rte_eth_dev_is_valid_port(*index) > 0 }
+    })
+}
+
 pub use dpdk_sys::EalStaticFunctions as EalGlobalApi;
 
 unsafe impl EalGlobalApi for Eal {}
 
+/// A background thread collecting garbage periodically. See [`Eal::spawn_garbage_collector`].
+///
+/// The thread is stopped when this handle is dropped.
+#[derive(Debug)]
+pub struct GarbageCollector {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for GarbageCollector {
+    #[inline]
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
 impl EalInner {
     // Create `EalInner`.
     #[inline]
@@ -1135,7 +1962,7 @@ impl EalInner {
         args.drain(..ret as usize);
 
         Ok(EalInner {
-            shared: Mutex::new(Default::default()),
+            shared: Arc::new(Mutex::new(Default::default())),
         })
     }
 }
@@ -1143,11 +1970,16 @@ impl EalInner {
 impl Drop for EalInner {
     #[inline]
     fn drop(&mut self) {
+        // Garbage collector threads may still hold `shared`, thus lock it instead of `get_mut`.
+        // Once drained, they find nothing to collect after `rte_eal_cleanup`.
+        let garbages = std::mem::take(&mut self.shared.lock().unwrap().garbages);
         // Safety: foriegn function (safe unless there is a bug)
         unsafe {
-            for mut gc_req in self.shared.get_mut().unwrap().garbages.drain(..) {
-                let ret = gc_req.try_collect();
-                assert_eq!(ret, true);
+            for mut gc_req in garbages {
+                if !gc_req.try_collect() {
+                    // Freeing the pool would invalidate objects which are still in use.
+                    warn!("Leaked {}", gc_req.pending());
+                }
             }
 
             let ret = dpdk_sys::rte_eal_cleanup();
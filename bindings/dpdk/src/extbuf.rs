@@ -0,0 +1,165 @@
+//! Application memory attached to packets without copying (`rte_pktmbuf_attach_extbuf`).
+use crate::eal::{MbufFlags, Packet};
+use crate::zeroable::Zeroable;
+use std::convert::TryInto;
+use std::ffi::c_void;
+use std::fmt;
+use std::mem;
+use std::ops::Deref;
+use std::ptr::{self, NonNull};
+use std::slice;
+
+/// Shared info of an external buffer, followed by the owner of its memory.
+struct ExtBufInner {
+    shinfo: dpdk_sys::rte_mbuf_ext_shared_info,
+    _owner: Box<dyn AsRef<[u8]> + Send + Sync>,
+    addr: NonNull<u8>,
+    len: usize,
+}
+
+/// Application memory (e.g. a file cache) which packets can refer to without copying.
+///
+/// Every handle and every packet attached to the buffer holds a reference (`refcnt` of
+/// `rte_mbuf_ext_shared_info`). The memory is freed when the last reference is gone, which may be
+/// when a driver frees the last transmitted packet.
+///
+/// Note: NICs read the memory by DMA, thus it may have to be registered to DPDK
+/// (`rte_extmem_register`) and mapped to devices.
+pub struct ExtBuf {
+    inner: NonNull<ExtBufInner>,
+}
+
+/// # Safety
+/// The memory is `Send + Sync`, and reference counting is atomic.
+unsafe impl Send for ExtBuf {}
+unsafe impl Sync for ExtBuf {}
+
+/// Free callback of external buffers, called by DPDK when the last reference is gone.
+unsafe extern "C" fn free_extbuf(_addr: *mut c_void, opaque: *mut c_void) {
+    drop(Box::from_raw(opaque as *mut ExtBufInner));
+}
+
+impl ExtBuf {
+    /// Take the ownership of `data` to attach it to packets.
+    #[inline]
+    pub fn new<T: AsRef<[u8]> + Send + Sync + 'static>(data: T) -> Self {
+        let owner: Box<dyn AsRef<[u8]> + Send + Sync> = Box::new(data);
+        // The data is boxed, thus it does not move anymore.
+        let (addr, len) = {
+            let slice = (*owner).as_ref();
+            (
+                NonNull::new(slice.as_ptr() as *mut u8).unwrap(),
+                slice.len(),
+            )
+        };
+        let inner = Box::into_raw(Box::new(ExtBufInner {
+            // Safety: all-zero is a valid value of this C struct.
+            shinfo: unsafe { mem::zeroed() },
+            _owner: owner,
+            addr,
+            len,
+        }));
+        // Safety: `inner` is a valid pointer, not shared yet.
+        unsafe {
+            (*inner).shinfo.free_cb = Some(free_extbuf);
+            (*inner).shinfo.fcb_opaque = inner as *mut c_void;
+            dpdk_sys::rte_mbuf_ext_refcnt_set(ptr::addr_of_mut!((*inner).shinfo), 1);
+        }
+        Self {
+            inner: NonNull::new(inner).unwrap(),
+        }
+    }
+
+    /// Returns the number of handles and packets referring to this buffer.
+    #[inline]
+    pub fn refcnt(&self) -> u16 {
+        // Safety: foreign function.
+        unsafe { dpdk_sys::rte_mbuf_ext_refcnt_read(self.shinfo()) }
+    }
+
+    #[inline]
+    fn shinfo(&self) -> *mut dpdk_sys::rte_mbuf_ext_shared_info {
+        // Safety: `inner` is alive while this handle exists.
+        unsafe { ptr::addr_of_mut!((*self.inner.as_ptr()).shinfo) }
+    }
+}
+
+impl Deref for ExtBuf {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        // Safety: `inner` is alive while this handle exists.
+        unsafe {
+            let inner = self.inner.as_ptr();
+            slice::from_raw_parts((*inner).addr.as_ptr(), (*inner).len)
+        }
+    }
+}
+
+impl Clone for ExtBuf {
+    #[inline]
+    fn clone(&self) -> Self {
+        // Safety: foreign function.
+        unsafe { dpdk_sys::rte_mbuf_ext_refcnt_update(self.shinfo(), 1) };
+        Self { inner: self.inner }
+    }
+}
+
+impl fmt::Debug for ExtBuf {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExtBuf")
+            .field("addr", &self.as_ptr())
+            .field("len", &self.len())
+            .field("refcnt", &self.refcnt())
+            .finish()
+    }
+}
+
+impl Drop for ExtBuf {
+    #[inline]
+    fn drop(&mut self) {
+        // Safety: foreign function. The memory is freed only by the last reference.
+        unsafe {
+            if dpdk_sys::rte_mbuf_ext_refcnt_update(self.shinfo(), -1) == 0 {
+                free_extbuf(ptr::null_mut(), self.inner.as_ptr() as *mut c_void);
+            }
+        }
+    }
+}
+
+impl<MPoolPriv: Zeroable> Packet<'_, MPoolPriv> {
+    /// Replace the data of this packet with `len` bytes of `extbuf` from `offset`
+    /// (`rte_pktmbuf_attach_extbuf`).
+    ///
+    /// The packet keeps the buffer alive until it is freed. External buffers are read-only, thus
+    /// mutable access to the data copies the packet first (see [`Packet::make_unique`]).
+    ///
+    /// Panic: when the range is out of bound, or longer than `u16::MAX`.
+    #[inline]
+    pub fn attach_extbuf(&mut self, extbuf: &ExtBuf, offset: usize, len: usize) {
+        assert!(matches!(offset.checked_add(len), Some(end) if end <= extbuf.len()));
+        let buf_len: u16 = len.try_into().unwrap();
+        let mbuf_ptr = self.header_mut();
+        // Safety: foreign function. The range is in bound of `extbuf`, which gets a reference
+        // for this packet.
+        unsafe {
+            // Release the buffer of a clone or a previously attached buffer.
+            if (*mbuf_ptr).ol_flags & (MbufFlags::INDIRECT | MbufFlags::EXTERNAL).bits() != 0 {
+                dpdk_sys::rte_pktmbuf_detach(mbuf_ptr);
+            }
+            dpdk_sys::rte_mbuf_ext_refcnt_update(extbuf.shinfo(), 1);
+            let buf_addr = extbuf.as_ptr().add(offset) as *mut c_void;
+            dpdk_sys::rte_pktmbuf_attach_extbuf(
+                mbuf_ptr,
+                buf_addr,
+                dpdk_sys::rte_mem_virt2iova(buf_addr),
+                buf_len,
+                extbuf.shinfo(),
+            );
+            (*mbuf_ptr).data_len = buf_len;
+            (*mbuf_ptr).pkt_len = len as u32;
+        }
+    }
+}
@@ -2,8 +2,9 @@
 
 use std::ffi::CString;
 use std::ops;
-use std::os::raw::{c_char, c_int};
+use std::os::raw::{c_char, c_int, c_void};
 use std::ptr;
+use std::slice;
 
 /// Call a main-like function with an argument vector
 ///
@@ -56,3 +57,23 @@ pub unsafe fn run_with_args(
 
     ret
 }
+
+/// Call a function printing to a C stream, and return the printed text
+///
+/// The stream is an in-memory buffer (`open_memstream`), which is passed to `func`.
+///
+/// # Safety
+///
+/// `func` must not close the stream or keep it after returning.
+pub unsafe fn print_to_string(func: impl FnOnce(*mut dpdk_sys::FILE)) -> String {
+    let mut buf: *mut c_char = ptr::null_mut();
+    let mut size = 0;
+    let file = dpdk_sys::open_memstream(&mut buf, &mut size);
+    assert!(!file.is_null(), "open_memstream failed");
+    func(file);
+    // `buf` and `size` are updated when the stream is closed.
+    dpdk_sys::fclose(file);
+    let text = String::from_utf8_lossy(slice::from_raw_parts(buf as *const u8, size)).into_owned();
+    dpdk_sys::free(buf as *mut c_void);
+    text
+}
@@ -0,0 +1,177 @@
+//! Rx interrupts (`rte_eth_dev_rx_intr_enable` and `rte_epoll_wait`), to sleep instead of
+//! polling idle queues, as in DPDK's l3fwd-power example.
+//!
+//! Rx interrupts must be enabled when the port is configured (see
+//! [`RteEthConf::set_rx_interrupts`](crate::eal::RteEthConf::set_rx_interrupts)).
+use crate::eal::{ErrorCode, Packet, RxQ};
+use crate::zeroable::Zeroable;
+use arrayvec::ArrayVec;
+use log::warn;
+use std::convert::TryInto;
+use std::mem;
+use std::ptr;
+use std::thread;
+use std::time::Duration;
+
+impl<MPoolPriv: Zeroable> RxQ<MPoolPriv> {
+    /// Arm the rx interrupt of this queue (`rte_eth_dev_rx_intr_enable`), which wakes up
+    /// [`wait_rx_interrupt`] on the current thread when packets arrive.
+    ///
+    /// The queue is registered to the epoll instance of the current thread on first use, and
+    /// moved if it is later enabled from another thread.
+    ///
+    /// Note: packets received before the interrupt is armed may not trigger it, thus the queue
+    /// should be polled once more before waiting.
+    #[inline]
+    pub fn enable_interrupt(&self) -> Result<(), ErrorCode> {
+        let current = thread::current().id();
+        if self.intr_thread.get() != Some(current) {
+            self.unregister_interrupt();
+            self.intr_ctl(dpdk_sys::RTE_INTR_EVENT_ADD)?;
+            self.intr_thread.set(Some(current));
+        }
+        self.arm_interrupt()
+    }
+
+    /// Arm the rx interrupt without registering the queue to an epoll instance.
+    pub(crate) fn arm_interrupt(&self) -> Result<(), ErrorCode> {
+        // Safety: foreign function.
+        let ret =
+            unsafe { dpdk_sys::rte_eth_dev_rx_intr_enable(self.port().port_id(), self.queue_id()) };
+        if ret != 0 {
+            return Err(ret.try_into().unwrap());
+        }
+        Ok(())
+    }
+
+    /// Disarm the rx interrupt of this queue (`rte_eth_dev_rx_intr_disable`).
+    #[inline]
+    pub fn disable_interrupt(&self) -> Result<(), ErrorCode> {
+        // Safety: foreign function.
+        let ret = unsafe {
+            dpdk_sys::rte_eth_dev_rx_intr_disable(self.port().port_id(), self.queue_id())
+        };
+        if ret != 0 {
+            return Err(ret.try_into().unwrap());
+        }
+        Ok(())
+    }
+
+    /// Remove this queue from the epoll instance it is registered to, if any.
+    pub(crate) fn unregister_interrupt(&self) {
+        if self.intr_thread.take().is_some() {
+            if let Err(err) = self.intr_ctl(dpdk_sys::RTE_INTR_EVENT_DEL) {
+                warn!(
+                    "RxQ::unregister_interrupt, error({}) while unregistering queue {}:{}",
+                    err,
+                    self.port().port_id(),
+                    self.queue_id()
+                );
+            }
+        }
+    }
+
+    fn intr_ctl(&self, op: u32) -> Result<(), ErrorCode> {
+        // Safety: foreign function.
+        let ret = unsafe {
+            dpdk_sys::rte_eth_dev_rx_intr_ctl_q(
+                self.port().port_id(),
+                self.queue_id(),
+                dpdk_sys::RTE_EPOLL_PER_THREAD,
+                op as i32,
+                ptr::null_mut(),
+            )
+        };
+        if ret != 0 {
+            return Err(ret.try_into().unwrap());
+        }
+        Ok(())
+    }
+}
+
+/// Sleep until an rx queue enabled from the current thread has an interrupt, or until `timeout`
+/// (forever if `None`).
+///
+/// Returns `false` on timeout.
+#[inline]
+pub fn wait_rx_interrupt(timeout: Option<Duration>) -> Result<bool, ErrorCode> {
+    let timeout_ms = timeout.map_or(-1, |timeout| {
+        timeout.as_millis().try_into().unwrap_or(i32::MAX)
+    });
+    // Safety: all-zero is a valid value of this C struct.
+    let mut event: dpdk_sys::rte_epoll_event = unsafe { mem::zeroed() };
+    // Safety: foreign function.
+    let ret = unsafe {
+        dpdk_sys::rte_epoll_wait(dpdk_sys::RTE_EPOLL_PER_THREAD, &mut event, 1, timeout_ms)
+    };
+    if ret < 0 {
+        return Err(ret.try_into().unwrap());
+    }
+    Ok(ret > 0)
+}
+
+/// Rx loop which polls a queue while there is traffic, and sleeps on its rx interrupt once the
+/// queue has been idle for a number of polls.
+///
+/// Note: a thread sleeping on one queue is not woken up by other queues. To serve several queues
+/// from one thread, enable the interrupt of every queue and call [`wait_rx_interrupt`] directly.
+#[derive(Debug)]
+pub struct AdaptiveRx<'q, MPoolPriv: Zeroable> {
+    rxq: &'q RxQ<MPoolPriv>,
+    idle_polls: usize,
+    max_idle_polls: usize,
+    timeout: Option<Duration>,
+}
+
+impl<'q, MPoolPriv: Zeroable> AdaptiveRx<'q, MPoolPriv> {
+    /// Sleep after `max_idle_polls` consecutive polls without packets.
+    #[inline]
+    pub fn new(rxq: &'q RxQ<MPoolPriv>, max_idle_polls: usize) -> Self {
+        Self {
+            rxq,
+            idle_polls: 0,
+            max_idle_polls,
+            timeout: None,
+        }
+    }
+
+    /// Limit the duration of a sleep, e.g. to handle timers (forever by default).
+    #[inline]
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Receive packets like [`RxQ::rx`], but sleep first if the queue has been idle.
+    ///
+    /// The buffer may be left empty after a sleep, e.g. on timeout.
+    #[inline]
+    pub fn rx<const N: usize>(
+        &mut self,
+        buffer: &mut ArrayVec<Packet<'q, MPoolPriv>, N>,
+    ) -> Result<(), ErrorCode> {
+        let current = buffer.len();
+        self.rxq.rx(buffer);
+        if buffer.len() > current {
+            self.idle_polls = 0;
+            return Ok(());
+        }
+        self.idle_polls += 1;
+        if self.idle_polls < self.max_idle_polls {
+            return Ok(());
+        }
+        self.idle_polls = 0;
+        self.rxq.enable_interrupt()?;
+        // Packets may have arrived before the interrupt was armed.
+        self.rxq.rx(buffer);
+        let waited = if buffer.len() == current {
+            wait_rx_interrupt(self.timeout)
+        } else {
+            Ok(false)
+        };
+        self.rxq.disable_interrupt()?;
+        if waited? {
+            self.rxq.rx(buffer);
+        }
+        Ok(())
+    }
+}
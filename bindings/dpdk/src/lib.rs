@@ -2,7 +2,19 @@
 
 mod ffi;
 
+#[cfg(feature = "async")]
+pub mod async_io;
+pub mod callback;
+pub mod capture;
 pub mod eal;
+pub mod extbuf;
+pub mod interrupt;
+pub mod mempool;
+pub mod mp;
+pub mod pcap;
+pub mod ring;
+#[cfg(feature = "smoltcp")]
+pub mod smoltcp;
 pub mod tx_buffer;
 pub mod zeroable;
 
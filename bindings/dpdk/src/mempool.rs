@@ -0,0 +1,312 @@
+//! Wrapper for DPDK's mempools (`rte_mempool`) of fixed-size, non-packet objects.
+use crate::eal::{rte_errno, Eal, ErrorCode, MPoolInner, SocketId};
+use std::convert::TryInto;
+use std::ffi::{CStr, CString};
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::{align_of, size_of, ManuallyDrop};
+use std::ops::{Deref, DerefMut};
+use std::ptr::{self, NonNull};
+use std::sync::Arc;
+
+/// Mempool handlers (`rte_mempool_ops`) storing free objects of a pool.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum MempoolOps {
+    /// Lockless multi-producer/multi-consumer ring (`ring_mp_mc`). This is DPDK's default.
+    Ring,
+    /// Stack protected by a spinlock (`stack`). Recently freed objects are reused first, thus they
+    /// are likely to be in CPU caches.
+    Stack,
+    /// Buckets of contiguous objects (`bucket`). Objects allocated together are likely to be
+    /// close in memory.
+    Bucket,
+}
+
+impl MempoolOps {
+    /// Returns the name which DPDK registers the ops with.
+    #[inline]
+    pub fn name(self) -> &'static CStr {
+        let name: &'static [u8] = match self {
+            MempoolOps::Ring => b"ring_mp_mc\0",
+            MempoolOps::Stack => b"stack\0",
+            MempoolOps::Bucket => b"bucket\0",
+        };
+        CStr::from_bytes_with_nul(name).unwrap()
+    }
+}
+
+/// A typed pool of fixed-size objects (e.g. flow records).
+///
+/// Like packet pools, each lcore keeps a cache of free objects, thus objects are allocated and
+/// freed without contention. Objects are returned to the pool when their [`PoolBox`] is dropped.
+pub struct ObjectPool<T> {
+    inner: Arc<MPoolInner>,
+    _phantom: PhantomData<T>,
+}
+
+/// # Safety
+/// Mempools are thread-safe, and values of `T` are only moved in and out of the pool.
+unsafe impl<T: Send> Send for ObjectPool<T> {}
+unsafe impl<T: Send> Sync for ObjectPool<T> {}
+
+impl<T> fmt::Debug for ObjectPool<T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ObjectPool")
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<T> Clone for ObjectPool<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> ObjectPool<T> {
+    /// Move `value` into an object allocated from the pool.
+    ///
+    /// Returns `value` back if the pool is exhausted.
+    #[inline]
+    pub fn alloc(&self, value: T) -> Result<PoolBox<'_, T>, T> {
+        let mut obj = ptr::null_mut();
+        // Safety: foreign function.
+        let ret = unsafe { dpdk_sys::rte_mempool_get(self.inner.ptr.as_ptr(), &mut obj) };
+        if ret < 0 {
+            return Err(value);
+        }
+        let ptr = NonNull::new(obj as *mut T).unwrap();
+        // Safety: objects are large and aligned enough for `T` (checked at pool creation).
+        unsafe { ptr.as_ptr().write(value) };
+        Ok(PoolBox { ptr, pool: self })
+    }
+
+    /// Returns the number of objects which can be allocated.
+    #[inline]
+    pub fn available(&self) -> usize {
+        self.inner.available()
+    }
+
+    /// Returns the number of allocated objects.
+    #[inline]
+    pub fn in_use(&self) -> usize {
+        self.inner.in_use()
+    }
+
+    /// Check the consistency of the pool.
+    ///
+    /// # Panic
+    /// DPDK panics if the pool is corrupted.
+    #[inline]
+    pub fn audit(&self) {
+        self.inner.audit()
+    }
+
+    /// Returns a human-readable description of the pool (`rte_mempool_dump`).
+    #[inline]
+    pub fn dump(&self) -> String {
+        self.inner.dump()
+    }
+
+    /// Return an object to the pool without dropping its value.
+    #[inline]
+    fn put(&self, ptr: NonNull<T>) {
+        // Safety: foreign function. The object was allocated from this pool.
+        unsafe { dpdk_sys::rte_mempool_put(self.inner.ptr.as_ptr(), ptr.as_ptr() as *mut _) };
+    }
+}
+
+/// An owned object allocated from an [`ObjectPool`].
+///
+/// The value is dropped and the object is returned to the pool when the box is dropped.
+pub struct PoolBox<'pool, T> {
+    ptr: NonNull<T>,
+    pool: &'pool ObjectPool<T>,
+}
+
+unsafe impl<T: Send> Send for PoolBox<'_, T> {}
+unsafe impl<T: Sync> Sync for PoolBox<'_, T> {}
+
+impl<T> PoolBox<'_, T> {
+    /// Move the value out of the box, and return the object to the pool.
+    #[inline]
+    pub fn into_inner(this: Self) -> T {
+        let this = ManuallyDrop::new(this);
+        // Safety: the object holds an initialized value, which is not used anymore.
+        let value = unsafe { this.ptr.as_ptr().read() };
+        this.pool.put(this.ptr);
+        value
+    }
+}
+
+impl<T> Deref for PoolBox<'_, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        // Safety: the object holds an initialized value owned by this box.
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T> DerefMut for PoolBox<'_, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        // Safety: the object holds an initialized value owned by this box.
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for PoolBox<'_, T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T> Drop for PoolBox<'_, T> {
+    #[inline]
+    fn drop(&mut self) {
+        // Safety: the object holds an initialized value, which is not used anymore.
+        unsafe { ptr::drop_in_place(self.ptr.as_ptr()) };
+        self.pool.put(self.ptr);
+    }
+}
+
+impl Eal {
+    /// Create a new `ObjectPool` with the default mempool ops.
+    ///
+    /// Returns `EEXIST` if a mempool with the given name already exists, as pool names must be
+    /// globally unique.
+    ///
+    /// # Panic
+    /// `T` must not require alignment larger than a cache line.
+    ///
+    /// @param n The number of objects in the pool.
+    ///
+    /// @param cache_size Size of the per-core object cache.
+    ///
+    /// @param socket_id The socket identifier where the memory should be allocated. The value can
+    /// be `None` (corresponds to DPDK's *SOCKET_ID_ANY*) if there is no NUMA constraint for the
+    /// reserved zone.
+    #[inline]
+    pub fn create_object_pool<S: AsRef<str>, T>(
+        &self,
+        name: S,
+        n: usize,
+        cache_size: usize,
+        socket_id: Option<SocketId>,
+    ) -> Result<ObjectPool<T>, ErrorCode> {
+        self.create_object_pool_inner(name, n, cache_size, socket_id, None)
+    }
+
+    /// Create a new `ObjectPool` storing free objects with the given mempool ops.
+    ///
+    /// See [`Eal::create_object_pool`] for parameters. Returns `EINVAL` if the ops are not
+    /// available (e.g. the mempool driver is not linked).
+    #[inline]
+    pub fn create_object_pool_by_ops<S: AsRef<str>, T>(
+        &self,
+        name: S,
+        n: usize,
+        cache_size: usize,
+        socket_id: Option<SocketId>,
+        ops: MempoolOps,
+    ) -> Result<ObjectPool<T>, ErrorCode> {
+        self.create_object_pool_inner(name, n, cache_size, socket_id, Some(ops))
+    }
+
+    fn create_object_pool_inner<S: AsRef<str>, T>(
+        &self,
+        name: S,
+        n: usize,
+        cache_size: usize,
+        socket_id: Option<SocketId>,
+        ops: Option<MempoolOps>,
+    ) -> Result<ObjectPool<T>, ErrorCode> {
+        // Objects are aligned to cache lines.
+        assert!(align_of::<T>() <= dpdk_sys::RTE_CACHE_LINE_SIZE as usize);
+        let pool_name = CString::new(name.as_ref()).unwrap();
+
+        // Safety: foreign function.
+        if !unsafe { dpdk_sys::rte_mempool_lookup(pool_name.as_ptr()) }.is_null() {
+            // Mempool names must be globally unique.
+            return Err(dpdk_sys::EEXIST.try_into().unwrap());
+        }
+        let n = n.try_into().unwrap();
+        let elt_size = size_of::<T>().max(1).try_into().unwrap();
+        let socket_id = socket_id
+            .map(|x| Into::<u32>::into(x) as i32)
+            .unwrap_or(dpdk_sys::SOCKET_ID_ANY);
+
+        let ptr = match ops {
+            // Safety: foreign function.
+            None => unsafe {
+                dpdk_sys::rte_mempool_create(
+                    pool_name.as_ptr(),
+                    n,
+                    elt_size,
+                    cache_size as u32,
+                    0,
+                    None,
+                    ptr::null_mut(),
+                    None,
+                    ptr::null_mut(),
+                    socket_id,
+                    0,
+                )
+            },
+            Some(ops) => {
+                // Safety: foreign function.
+                let ptr = unsafe {
+                    dpdk_sys::rte_mempool_create_empty(
+                        pool_name.as_ptr(),
+                        n,
+                        elt_size,
+                        cache_size as u32,
+                        0,
+                        socket_id,
+                        0,
+                    )
+                };
+                let ptr = match NonNull::new(ptr) {
+                    Some(ptr) => ptr,
+                    None => return Err(rte_errno()),
+                };
+                // Safety: foreign function. The pool is not populated yet.
+                let ret = unsafe {
+                    dpdk_sys::rte_mempool_set_ops_byname(
+                        ptr.as_ptr(),
+                        ops.name().as_ptr(),
+                        ptr::null_mut(),
+                    )
+                };
+                // Safety: foreign function.
+                let ret = match ret {
+                    0 => unsafe { dpdk_sys::rte_mempool_populate_default(ptr.as_ptr()) },
+                    ret => ret,
+                };
+                if ret < 0 {
+                    // Safety: foreign function. The pool is not used by anyone.
+                    unsafe { dpdk_sys::rte_mempool_free(ptr.as_ptr()) };
+                    return Err(ret.try_into().unwrap());
+                }
+                ptr.as_ptr()
+            }
+        };
+        let ptr = match NonNull::new(ptr) {
+            Some(ptr) => ptr,
+            None => return Err(rte_errno()),
+        };
+
+        Ok(ObjectPool {
+            inner: Arc::new(self.own_mempool(ptr, name.as_ref().to_owned())),
+            _phantom: PhantomData,
+        })
+    }
+}
@@ -0,0 +1,267 @@
+//! Multi-process support: process type and IPC between primary and secondary processes
+//! (`rte_mp_*`).
+//!
+//! Secondary processes (EAL option `--proc-type=secondary`) share the memory of the primary. They
+//! use its mempools, rings and ports by name ([`Eal::attach_mpool`], [`Eal::attach_ring`] and
+//! [`Eal::attach_port`]) without reconfiguring them, e.g. to monitor a forwarding primary.
+//!
+//! Processes exchange requests, which are handled by the action registered with the request's
+//! name in peer processes ([`Eal::register_mp_action`]). The primary's requests are sent to every
+//! secondary, and secondaries' requests are sent to the primary ([`Eal::mp_request`]).
+use crate::eal::{rte_errno, Eal, ErrorCode};
+use log::warn;
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_void};
+use std::slice;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Maximum size of an encoded IPC message.
+pub const MP_MAX_PARAM_LEN: usize = dpdk_sys::RTE_MP_MAX_PARAM_LEN as usize;
+
+/// Type of the current process (`rte_eal_process_type`).
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum ProcessType {
+    /// The process which configured the shared memory, i.e. its pools, rings and ports.
+    Primary,
+    /// A process attached to the shared memory of the primary.
+    Secondary,
+}
+
+/// Payload of IPC messages, encoded into at most [`MP_MAX_PARAM_LEN`] bytes.
+///
+/// File descriptors cannot be sent.
+pub trait MpMessage: Sized {
+    /// Append the encoded message to `param`.
+    fn encode(&self, param: &mut Vec<u8>);
+
+    /// Decode a message, or return `None` if it is malformed.
+    fn decode(param: &[u8]) -> Option<Self>;
+}
+
+impl MpMessage for () {
+    #[inline]
+    fn encode(&self, _param: &mut Vec<u8>) {}
+
+    #[inline]
+    fn decode(param: &[u8]) -> Option<Self> {
+        param.is_empty().then_some(())
+    }
+}
+
+impl MpMessage for Vec<u8> {
+    #[inline]
+    fn encode(&self, param: &mut Vec<u8>) {
+        param.extend_from_slice(self);
+    }
+
+    #[inline]
+    fn decode(param: &[u8]) -> Option<Self> {
+        Some(param.to_vec())
+    }
+}
+
+impl MpMessage for String {
+    #[inline]
+    fn encode(&self, param: &mut Vec<u8>) {
+        param.extend_from_slice(self.as_bytes());
+    }
+
+    #[inline]
+    fn decode(param: &[u8]) -> Option<Self> {
+        String::from_utf8(param.to_vec()).ok()
+    }
+}
+
+/// A request sent to peer processes, which is answered by their action named [`MpRequest::NAME`].
+pub trait MpRequest: MpMessage {
+    /// Name of the action, shorter than `RTE_MP_MAX_NAME_LEN` (64) bytes.
+    const NAME: &'static str;
+
+    /// Reply of each peer process.
+    type Reply: MpMessage;
+}
+
+type ActionFn = dyn Fn(&[u8]) -> Option<Vec<u8>> + Send + Sync;
+
+/// Closures of registered actions by name, which are called by `mp_action`.
+static ACTIONS: Mutex<BTreeMap<String, Arc<ActionFn>>> = Mutex::new(BTreeMap::new());
+
+/// An action handling requests of peer processes, which is unregistered when this handle is
+/// dropped.
+///
+/// Note: the closure may still be running on DPDK's IPC thread after the handle is dropped.
+#[derive(Debug)]
+pub struct MpAction {
+    name: CString,
+    _eal: Eal,
+}
+
+impl Drop for MpAction {
+    #[inline]
+    fn drop(&mut self) {
+        // Safety: foreign function.
+        unsafe { dpdk_sys::rte_mp_action_unregister(self.name.as_ptr()) };
+        let name = self.name.to_str().unwrap();
+        ACTIONS.lock().unwrap().remove(name);
+    }
+}
+
+/// Returns the name of an action, or `EINVAL` if DPDK does not accept it.
+fn action_name(name: &str) -> Result<CString, ErrorCode> {
+    match CString::new(name) {
+        Ok(name)
+            if !name.as_bytes().is_empty()
+                && name.as_bytes().len() < dpdk_sys::RTE_MP_MAX_NAME_LEN as usize =>
+        {
+            Ok(name)
+        }
+        _ => Err(dpdk_sys::EINVAL.try_into().unwrap()),
+    }
+}
+
+/// Build an IPC message, or return `EINVAL` if the parameter is too large.
+fn new_msg(name: &CStr, param: &[u8]) -> Result<dpdk_sys::rte_mp_msg, ErrorCode> {
+    if param.len() > MP_MAX_PARAM_LEN {
+        return Err(dpdk_sys::EINVAL.try_into().unwrap());
+    }
+    // Safety: `rte_mp_msg` allows zeroed structure.
+    let mut msg: dpdk_sys::rte_mp_msg = unsafe { std::mem::zeroed() };
+    for (dst, src) in msg.name.iter_mut().zip(name.to_bytes()) {
+        *dst = *src as c_char;
+    }
+    msg.len_param = param.len() as c_int;
+    msg.param[..param.len()].copy_from_slice(param);
+    Ok(msg)
+}
+
+/// Returns the parameter of an IPC message.
+fn msg_param(msg: &dpdk_sys::rte_mp_msg) -> &[u8] {
+    let len = (msg.len_param.max(0) as usize).min(MP_MAX_PARAM_LEN);
+    &msg.param[..len]
+}
+
+unsafe extern "C" fn mp_action(msg: *const dpdk_sys::rte_mp_msg, peer: *const c_void) -> c_int {
+    let msg = &*msg;
+    let name = CStr::from_ptr(msg.name.as_ptr());
+    let action = name
+        .to_str()
+        .ok()
+        .and_then(|name| ACTIONS.lock().unwrap().get(name).cloned());
+    let action = match action {
+        Some(action) => action,
+        None => return -1,
+    };
+    let mut reply = match action(msg_param(msg)).map(|param| new_msg(name, &param)) {
+        Some(Ok(reply)) => reply,
+        Some(Err(_)) => {
+            warn!("mp_action, reply to {:?} is too large", name);
+            return -1;
+        }
+        None => {
+            warn!("mp_action, malformed request {:?}", name);
+            return -1;
+        }
+    };
+    // Safety: foreign function. `peer` is the name of the requesting process.
+    dpdk_sys::rte_mp_reply(&mut reply, peer as *const c_char)
+}
+
+impl Eal {
+    /// Returns whether this process is the primary or a secondary process.
+    #[inline]
+    pub fn process_type(&self) -> ProcessType {
+        // Safety: foreign function.
+        let proc_type = unsafe { dpdk_sys::rte_eal_process_type() };
+        if proc_type == dpdk_sys::rte_proc_type_t::RTE_PROC_SECONDARY {
+            ProcessType::Secondary
+        } else {
+            ProcessType::Primary
+        }
+    }
+
+    /// Register a closure answering requests of type `R` sent by peer processes
+    /// (`rte_mp_action_register`).
+    ///
+    /// The closure is called on DPDK's IPC thread. Malformed requests are not answered.
+    ///
+    /// Returns `EINVAL` if the name of `R` is invalid, `EEXIST` if an action with the same name is
+    /// already registered, and `ENOTSUP` if IPC is disabled (e.g. with `--in-memory`).
+    #[inline]
+    pub fn register_mp_action<R, F>(&self, f: F) -> Result<MpAction, ErrorCode>
+    where
+        R: MpRequest,
+        F: Fn(R) -> R::Reply + Send + Sync + 'static,
+    {
+        let name = action_name(R::NAME)?;
+        let mut actions = ACTIONS.lock().unwrap();
+        if actions.contains_key(R::NAME) {
+            return Err(dpdk_sys::EEXIST.try_into().unwrap());
+        }
+        // Safety: foreign function.
+        let ret = unsafe { dpdk_sys::rte_mp_action_register(name.as_ptr(), Some(mp_action)) };
+        if ret < 0 {
+            return Err(rte_errno());
+        }
+        let action = move |param: &[u8]| {
+            let mut reply = Vec::new();
+            f(R::decode(param)?).encode(&mut reply);
+            Some(reply)
+        };
+        actions.insert(R::NAME.to_owned(), Arc::new(action));
+        Ok(MpAction {
+            name,
+            _eal: self.clone(),
+        })
+    }
+
+    /// Send a request to peer processes and wait for their replies (`rte_mp_request_sync`).
+    ///
+    /// Returns the replies of peers handling the request, which is empty if there is no such
+    /// peer. Returns `EINVAL` if the request is invalid or a reply is malformed, `ETIMEDOUT` if a
+    /// peer did not reply within `timeout`, and `ENOTSUP` if the request could not be sent.
+    #[inline]
+    pub fn mp_request<R: MpRequest>(
+        &self,
+        request: &R,
+        timeout: Duration,
+    ) -> Result<Vec<R::Reply>, ErrorCode> {
+        let name = action_name(R::NAME)?;
+        let mut param = Vec::new();
+        request.encode(&mut param);
+        let mut msg = new_msg(&name, &param)?;
+        let ts = dpdk_sys::timespec {
+            tv_sec: timeout.as_secs() as _,
+            tv_nsec: timeout.subsec_nanos() as _,
+        };
+        // Safety: `rte_mp_reply` allows zeroed structure.
+        let mut reply: dpdk_sys::rte_mp_reply = unsafe { std::mem::zeroed() };
+        // Safety: foreign function.
+        let ret = unsafe { dpdk_sys::rte_mp_request_sync(&mut msg, &mut reply, &ts) };
+        let replies = if reply.msgs.is_null() {
+            Some(Vec::new())
+        } else {
+            // Safety: DPDK allocated `nb_received` messages, which are freed by the caller.
+            unsafe {
+                let msgs = slice::from_raw_parts(reply.msgs, reply.nb_received as usize);
+                let replies = msgs
+                    .iter()
+                    .map(|msg| R::Reply::decode(msg_param(msg)))
+                    .collect::<Option<Vec<_>>>();
+                dpdk_sys::free(reply.msgs as *mut c_void);
+                replies
+            }
+        };
+        if ret < 0 {
+            let code = if reply.nb_received < reply.nb_sent {
+                dpdk_sys::ETIMEDOUT
+            } else {
+                dpdk_sys::ENOTSUP
+            };
+            return Err(code.try_into().unwrap());
+        }
+        replies.ok_or_else(|| dpdk_sys::EINVAL.try_into().unwrap())
+    }
+}
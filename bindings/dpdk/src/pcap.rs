@@ -0,0 +1,808 @@
+//! Pcap files: `net_pcap` ports replaying and recording files (or capturing kernel interfaces),
+//! and a pure-Rust pcap/pcapng writer and reader, so that the traffic of any queue can be recorded
+//! and replayed offline.
+//!
+//! Only Ethernet frames (`LINKTYPE_ETHERNET`) are supported. Written files use nanosecond
+//! timestamps and little-endian fields, and files of either byte order and resolution are read.
+use crate::callback::QueueCallback;
+use crate::eal::{Eal, ErrorCode, MPool, Packet, RxQ, TxQ, UninitPort};
+use crate::zeroable::Zeroable;
+use arrayvec::ArrayVec;
+use log::{info, warn};
+use std::convert::TryInto;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Default snap length of [`PcapWriter`], as in tcpdump.
+pub const DEFAULT_SNAP_LEN: u32 = 262_144;
+
+const LINKTYPE_ETHERNET: u16 = 1;
+const PCAP_MAGIC_MICROS: u32 = 0xa1b2_c3d4;
+const PCAP_MAGIC_NANOS: u32 = 0xa1b2_3c4d;
+const PCAPNG_SECTION_HEADER: u32 = 0x0a0d_0d0a;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+const PCAPNG_INTERFACE_DESCRIPTION: u32 = 1;
+const PCAPNG_ENHANCED_PACKET: u32 = 6;
+const PCAPNG_OPT_END: u16 = 0;
+const PCAPNG_OPT_IF_NAME: u16 = 2;
+const PCAPNG_OPT_IF_TSRESOL: u16 = 9;
+const PCAPNG_OPT_EPB_FLAGS: u16 = 2;
+const PCAPNG_OPT_EPB_QUEUE: u16 = 6;
+/// Upper bound of pcapng blocks, to reject corrupted lengths before allocating.
+const PCAPNG_MAX_BLOCK_LEN: usize = 16 * 1024 * 1024;
+
+/// Rx queue of a `net_pcap` port.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PcapRx {
+    /// Replay a pcap file (`rx_pcap=`).
+    File(PathBuf),
+    /// Capture a kernel interface (`rx_iface=`).
+    Iface(String),
+}
+
+/// Tx queue of a `net_pcap` port.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PcapTx {
+    /// Write a pcap file (`tx_pcap=`).
+    File(PathBuf),
+    /// Send to a kernel interface (`tx_iface=`).
+    Iface(String),
+}
+
+/// Configuration of a `net_pcap` port. Its queues are created in order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PcapPortConf {
+    /// Sources of rx queues, one queue each.
+    pub rx: Vec<PcapRx>,
+    /// Destinations of tx queues, one queue each.
+    pub tx: Vec<PcapTx>,
+    /// Kernel interface read by one rx queue and written by one tx queue (`iface=`). It cannot be
+    /// combined with `rx` and `tx`.
+    pub iface: Option<String>,
+    /// Replay rx files endlessly (`infinite_rx=1`), e.g. for throughput tests.
+    pub infinite_rx: bool,
+}
+
+impl PcapPortConf {
+    /// Returns the driver arguments of this configuration, e.g. `rx_pcap=in.pcap,tx_pcap=out.pcap`.
+    ///
+    /// Returns `EINVAL` if `iface` is combined with other queues, or if a path or an interface
+    /// name is not UTF-8 or contains a comma.
+    pub fn devargs(&self) -> Result<String, ErrorCode> {
+        if self.iface.is_some() && !(self.rx.is_empty() && self.tx.is_empty()) {
+            return Err(dpdk_sys::EINVAL.try_into().unwrap());
+        }
+        let mut args = Vec::new();
+        if let Some(iface) = &self.iface {
+            args.push(("iface", iface.as_str()));
+        }
+        for rx in &self.rx {
+            args.push(match rx {
+                PcapRx::File(path) => ("rx_pcap", path.to_str().unwrap_or(",")),
+                PcapRx::Iface(iface) => ("rx_iface", iface.as_str()),
+            });
+        }
+        for tx in &self.tx {
+            args.push(match tx {
+                PcapTx::File(path) => ("tx_pcap", path.to_str().unwrap_or(",")),
+                PcapTx::Iface(iface) => ("tx_iface", iface.as_str()),
+            });
+        }
+        if self.infinite_rx {
+            args.push(("infinite_rx", "1"));
+        }
+        if args.iter().any(|(_, value)| value.contains(',')) {
+            return Err(dpdk_sys::EINVAL.try_into().unwrap());
+        }
+        Ok(args
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join(","))
+    }
+}
+
+impl Eal {
+    /// Hotplug a `net_pcap` port.
+    ///
+    /// `name` selects the driver, thus it must start with `net_pcap` (e.g. `net_pcap0`). Files are
+    /// opened by the driver: rx files must exist, and tx files are truncated.
+    #[inline]
+    pub fn create_pcap_port<S: AsRef<str>>(
+        &self,
+        name: S,
+        conf: &PcapPortConf,
+    ) -> Result<UninitPort, ErrorCode> {
+        let mut ports = self.attach_vdev(name, conf.devargs()?)?;
+        if ports.len() != 1 {
+            // The driver did not create exactly one port for the device.
+            return Err(dpdk_sys::ENODEV.try_into().unwrap());
+        }
+        let port = ports.pop().unwrap();
+        info!("Pcap port {} created", port.port_id());
+        Ok(port)
+    }
+}
+
+/// File format of pcap files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PcapFormat {
+    /// Classic libpcap format.
+    Pcap,
+    /// pcapng format.
+    PcapNg,
+}
+
+/// Direction of a captured frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PcapDirection {
+    /// Received by the interface.
+    Inbound,
+    /// Sent by the interface.
+    Outbound,
+}
+
+/// Where a frame was captured. Only pcapng files record it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct PcapFrameInfo {
+    /// Interface id, in the order of [`PcapWriter::add_interface`].
+    pub interface: u32,
+    /// Direction of the frame (`epb_flags`).
+    pub direction: Option<PcapDirection>,
+    /// Queue of the interface (`epb_queue`).
+    pub queue: Option<u32>,
+}
+
+/// Writer of Ethernet frames to a pcap or pcapng file.
+///
+/// Writes are not buffered: wrap files in a [`BufWriter`](std::io::BufWriter).
+#[derive(Debug)]
+pub struct PcapWriter<W: Write> {
+    writer: W,
+    format: PcapFormat,
+    snap_len: u32,
+    interfaces: u32,
+}
+
+impl<W: Write> PcapWriter<W> {
+    /// Write the file header, with [`DEFAULT_SNAP_LEN`].
+    #[inline]
+    pub fn new(writer: W, format: PcapFormat) -> io::Result<Self> {
+        Self::with_snap_len(writer, format, DEFAULT_SNAP_LEN)
+    }
+
+    /// Write the file header. Frames are truncated to `snap_len` bytes.
+    pub fn with_snap_len(mut writer: W, format: PcapFormat, snap_len: u32) -> io::Result<Self> {
+        let mut header = Vec::with_capacity(60);
+        match format {
+            PcapFormat::Pcap => {
+                header.extend_from_slice(&PCAP_MAGIC_NANOS.to_le_bytes());
+                header.extend_from_slice(&2u16.to_le_bytes());
+                header.extend_from_slice(&4u16.to_le_bytes());
+                // Time zone and timestamp accuracy, always zero.
+                header.extend_from_slice(&[0; 8]);
+                header.extend_from_slice(&snap_len.to_le_bytes());
+                header.extend_from_slice(&u32::from(LINKTYPE_ETHERNET).to_le_bytes());
+            }
+            PcapFormat::PcapNg => {
+                let mut section = Vec::with_capacity(16);
+                section.extend_from_slice(&PCAPNG_BYTE_ORDER_MAGIC.to_le_bytes());
+                section.extend_from_slice(&1u16.to_le_bytes());
+                section.extend_from_slice(&0u16.to_le_bytes());
+                // Unknown section length.
+                section.extend_from_slice(&(-1i64).to_le_bytes());
+                push_block(&mut header, PCAPNG_SECTION_HEADER, &section);
+                push_interface(&mut header, snap_len, None);
+            }
+        }
+        writer.write_all(&header)?;
+        Ok(Self {
+            writer,
+            format,
+            snap_len,
+            interfaces: 1,
+        })
+    }
+
+    /// Describe another interface named `name`, and return its id.
+    ///
+    /// Files have a single unnamed interface (id 0) by default. Returns `InvalidInput` for classic
+    /// pcap files.
+    pub fn add_interface(&mut self, name: &str) -> io::Result<u32> {
+        if self.format != PcapFormat::PcapNg {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "pcap files have a single interface",
+            ));
+        }
+        let mut block = Vec::new();
+        push_interface(&mut block, self.snap_len, Some(name));
+        self.writer.write_all(&block)?;
+        self.interfaces += 1;
+        Ok(self.interfaces - 1)
+    }
+
+    /// Returns the format of the file.
+    #[inline]
+    pub fn format(&self) -> PcapFormat {
+        self.format
+    }
+
+    /// Returns the maximum number of bytes written per frame.
+    #[inline]
+    pub fn snap_len(&self) -> u32 {
+        self.snap_len
+    }
+
+    /// Write a frame of `orig_len` bytes captured at `timestamp` (since the Unix epoch), of which
+    /// `data` was captured.
+    #[inline]
+    pub fn write_frame(
+        &mut self,
+        data: &[u8],
+        orig_len: usize,
+        timestamp: Duration,
+    ) -> io::Result<()> {
+        self.write_frame_with(data, orig_len, timestamp, &PcapFrameInfo::default())
+    }
+
+    /// Write a frame like [`PcapWriter::write_frame`], recording where it was captured.
+    ///
+    /// Returns `InvalidInput` if the interface was not added.
+    pub fn write_frame_with(
+        &mut self,
+        data: &[u8],
+        orig_len: usize,
+        timestamp: Duration,
+        info: &PcapFrameInfo,
+    ) -> io::Result<()> {
+        if info.interface >= self.interfaces {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "unknown pcapng interface",
+            ));
+        }
+        let data = &data[..data.len().min(self.snap_len as usize)];
+        let cap_len = data.len() as u32;
+        let orig_len = orig_len.max(data.len()) as u32;
+        let mut record = Vec::with_capacity(32 + data.len());
+        match self.format {
+            PcapFormat::Pcap => {
+                record.extend_from_slice(&(timestamp.as_secs() as u32).to_le_bytes());
+                record.extend_from_slice(&timestamp.subsec_nanos().to_le_bytes());
+                record.extend_from_slice(&cap_len.to_le_bytes());
+                record.extend_from_slice(&orig_len.to_le_bytes());
+                record.extend_from_slice(data);
+            }
+            PcapFormat::PcapNg => {
+                let nanos = timestamp.as_nanos() as u64;
+                let mut packet = Vec::with_capacity(48 + data.len());
+                packet.extend_from_slice(&info.interface.to_le_bytes());
+                packet.extend_from_slice(&((nanos >> 32) as u32).to_le_bytes());
+                packet.extend_from_slice(&(nanos as u32).to_le_bytes());
+                packet.extend_from_slice(&cap_len.to_le_bytes());
+                packet.extend_from_slice(&orig_len.to_le_bytes());
+                packet.extend_from_slice(data);
+                if info.direction.is_some() || info.queue.is_some() {
+                    packet.resize(packet.len() + padding(data.len()), 0);
+                    if let Some(direction) = info.direction {
+                        let flags: u32 = match direction {
+                            PcapDirection::Inbound => 1,
+                            PcapDirection::Outbound => 2,
+                        };
+                        push_option(&mut packet, PCAPNG_OPT_EPB_FLAGS, &flags.to_le_bytes());
+                    }
+                    if let Some(queue) = info.queue {
+                        push_option(&mut packet, PCAPNG_OPT_EPB_QUEUE, &queue.to_le_bytes());
+                    }
+                    push_option(&mut packet, PCAPNG_OPT_END, &[]);
+                }
+                push_block(&mut record, PCAPNG_ENHANCED_PACKET, &packet);
+            }
+        }
+        self.writer.write_all(&record)
+    }
+
+    /// Write the data of a packet captured at `timestamp` (since the Unix epoch).
+    ///
+    /// Note: only the first segment of the packet is written.
+    #[inline]
+    pub fn write_packet<MPoolPriv: Zeroable>(
+        &mut self,
+        pkt: &Packet<'_, MPoolPriv>,
+        timestamp: Duration,
+    ) -> io::Result<()> {
+        self.write_frame(pkt.data(), pkt.len(), timestamp)
+    }
+
+    /// Flush the underlying writer.
+    #[inline]
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Returns the underlying writer.
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Returns the number of bytes padding `len` bytes to 32 bits.
+fn padding(len: usize) -> usize {
+    (4 - len % 4) % 4
+}
+
+/// Append a pcapng block, padding its body to 32 bits.
+fn push_block(buffer: &mut Vec<u8>, block_type: u32, body: &[u8]) {
+    let padding = padding(body.len());
+    let total_len = (12 + body.len() + padding) as u32;
+    buffer.extend_from_slice(&block_type.to_le_bytes());
+    buffer.extend_from_slice(&total_len.to_le_bytes());
+    buffer.extend_from_slice(body);
+    buffer.extend_from_slice(&[0; 3][..padding]);
+    buffer.extend_from_slice(&total_len.to_le_bytes());
+}
+
+/// Append a pcapng option, padding its value to 32 bits.
+fn push_option(buffer: &mut Vec<u8>, code: u16, value: &[u8]) {
+    buffer.extend_from_slice(&code.to_le_bytes());
+    buffer.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    buffer.extend_from_slice(value);
+    buffer.resize(buffer.len() + padding(value.len()), 0);
+}
+
+/// Append a pcapng interface description block of Ethernet frames with nanosecond timestamps.
+fn push_interface(buffer: &mut Vec<u8>, snap_len: u32, name: Option<&str>) {
+    let mut interface = Vec::with_capacity(32);
+    interface.extend_from_slice(&LINKTYPE_ETHERNET.to_le_bytes());
+    interface.extend_from_slice(&0u16.to_le_bytes());
+    interface.extend_from_slice(&snap_len.to_le_bytes());
+    if let Some(name) = name {
+        push_option(&mut interface, PCAPNG_OPT_IF_NAME, name.as_bytes());
+    }
+    // Timestamps in units of 10^-9 seconds.
+    push_option(&mut interface, PCAPNG_OPT_IF_TSRESOL, &[9]);
+    push_option(&mut interface, PCAPNG_OPT_END, &[]);
+    push_block(buffer, PCAPNG_INTERFACE_DESCRIPTION, &interface);
+}
+
+/// Returns the time elapsed since the Unix epoch.
+#[inline]
+pub(crate) fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// Write a burst to a shared writer. Returns `false` once writing failed.
+fn record_burst<W: Write, MPoolPriv: Zeroable>(
+    writer: &Mutex<PcapWriter<W>>,
+    pkts: &[Packet<'_, MPoolPriv>],
+) -> bool {
+    let timestamp = now();
+    let mut writer = writer.lock().unwrap();
+    match pkts
+        .iter()
+        .try_for_each(|pkt| writer.write_packet(pkt, timestamp))
+    {
+        Ok(()) => true,
+        Err(err) => {
+            warn!("pcap recording stopped, error({}) while writing", err);
+            false
+        }
+    }
+}
+
+impl<MPoolPriv: Zeroable> RxQ<MPoolPriv> {
+    /// Record received packets to `writer` until the returned callback is dropped.
+    ///
+    /// Packets are written synchronously by [`RxQ::rx`], thus this is meant for tests and
+    /// debugging. Recording stops at the first write error.
+    #[inline]
+    pub fn record<W: Write + Send + 'static>(
+        &self,
+        writer: Arc<Mutex<PcapWriter<W>>>,
+    ) -> Result<QueueCallback, ErrorCode> {
+        let mut failed = false;
+        self.add_callback(move |pkts| {
+            if !failed {
+                failed = !record_burst(&writer, pkts);
+            }
+            pkts.len()
+        })
+    }
+}
+
+impl TxQ<'_> {
+    /// Record packets to transmit to `writer` until the returned callback is dropped.
+    ///
+    /// Packets are written synchronously by [`TxQ::tx`], including packets which do not fit into
+    /// the queue and are retried later. Recording stops at the first write error.
+    #[inline]
+    pub fn record<W: Write + Send + 'static>(
+        &self,
+        writer: Arc<Mutex<PcapWriter<W>>>,
+    ) -> Result<QueueCallback, ErrorCode> {
+        let mut failed = false;
+        self.add_callback(move |pkts| {
+            if !failed {
+                failed = !record_burst(&writer, pkts);
+            }
+            pkts.len()
+        })
+    }
+}
+
+/// A frame read by [`PcapReader`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PcapRecord<'a> {
+    /// Capture time since the Unix epoch.
+    pub timestamp: Duration,
+    /// Length of the frame on the wire, which may exceed the captured data.
+    pub orig_len: usize,
+    /// Captured data.
+    pub data: &'a [u8],
+    /// Where the frame was captured, default for classic pcap files.
+    pub info: PcapFrameInfo,
+}
+
+#[derive(Debug)]
+enum ReaderState {
+    Pcap {
+        big_endian: bool,
+        nanos: bool,
+    },
+    PcapNg {
+        big_endian: bool,
+        /// Timestamp units per second of each interface of the current section.
+        ts_units: Vec<u64>,
+    },
+}
+
+/// Reader of Ethernet frames from a pcap or pcapng file.
+///
+/// Pcapng packets are read from enhanced packet blocks, and other blocks are skipped.
+#[derive(Debug)]
+pub struct PcapReader<R: Read> {
+    reader: R,
+    state: ReaderState,
+    buffer: Vec<u8>,
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Read `buffer` entirely, or return `false` at the end of the file.
+fn read_or_eof<R: Read>(reader: &mut R, buffer: &mut [u8]) -> io::Result<bool> {
+    let mut read = 0;
+    while read < buffer.len() {
+        match reader.read(&mut buffer[read..]) {
+            Ok(0) if read == 0 => return Ok(false),
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => read += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(true)
+}
+
+#[inline]
+fn read_u16(bytes: &[u8], big_endian: bool) -> u16 {
+    let bytes = bytes[..2].try_into().unwrap();
+    if big_endian {
+        u16::from_be_bytes(bytes)
+    } else {
+        u16::from_le_bytes(bytes)
+    }
+}
+
+#[inline]
+fn read_u32(bytes: &[u8], big_endian: bool) -> u32 {
+    let bytes = bytes[..4].try_into().unwrap();
+    if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    }
+}
+
+/// Call `f` with the code and the value of each option of a pcapng block.
+fn for_each_option(
+    mut options: &[u8],
+    big_endian: bool,
+    mut f: impl FnMut(u16, &[u8]) -> io::Result<()>,
+) -> io::Result<()> {
+    while options.len() >= 4 {
+        let code = read_u16(options, big_endian);
+        let len = read_u16(&options[2..], big_endian) as usize;
+        if code == PCAPNG_OPT_END {
+            break;
+        }
+        let value = options
+            .get(4..4 + len)
+            .ok_or_else(|| invalid_data("truncated pcapng option"))?;
+        f(code, value)?;
+        options = options.get((4 + len).div_ceil(4) * 4..).unwrap_or_default();
+    }
+    Ok(())
+}
+
+/// Returns the timestamp units per second of a pcapng interface description block body.
+fn interface_ts_units(body: &[u8], big_endian: bool) -> io::Result<u64> {
+    if body.len() < 8 {
+        return Err(invalid_data("truncated pcapng interface description"));
+    }
+    if read_u16(body, big_endian) != LINKTYPE_ETHERNET {
+        return Err(invalid_data("unsupported pcapng link type"));
+    }
+    // Microseconds by default.
+    let mut units = Some(1_000_000);
+    for_each_option(&body[8..], big_endian, |code, value| {
+        if let (PCAPNG_OPT_IF_TSRESOL, [resolution]) = (code, value) {
+            let exponent = u32::from(resolution & 0x7f);
+            units = if resolution & 0x80 == 0 {
+                10u64.checked_pow(exponent)
+            } else {
+                2u64.checked_pow(exponent)
+            };
+        }
+        Ok(())
+    })?;
+    units.ok_or_else(|| invalid_data("invalid pcapng timestamp resolution"))
+}
+
+/// Returns where a frame was captured from the options of a pcapng enhanced packet block.
+fn enhanced_packet_info(
+    interface: u32,
+    options: &[u8],
+    big_endian: bool,
+) -> io::Result<PcapFrameInfo> {
+    let mut info = PcapFrameInfo {
+        interface,
+        ..Default::default()
+    };
+    for_each_option(options, big_endian, |code, value| {
+        match code {
+            PCAPNG_OPT_EPB_FLAGS if value.len() == 4 => {
+                info.direction = match read_u32(value, big_endian) & 0b11 {
+                    1 => Some(PcapDirection::Inbound),
+                    2 => Some(PcapDirection::Outbound),
+                    _ => None,
+                };
+            }
+            PCAPNG_OPT_EPB_QUEUE if value.len() == 4 => {
+                info.queue = Some(read_u32(value, big_endian));
+            }
+            _ => {}
+        }
+        Ok(())
+    })?;
+    Ok(info)
+}
+
+impl<R: Read> PcapReader<R> {
+    /// Read the file header.
+    ///
+    /// Returns `InvalidData` for unknown formats and for link types other than Ethernet.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if u32::from_le_bytes(magic) == PCAPNG_SECTION_HEADER {
+            let mut pcap_reader = Self {
+                reader,
+                state: ReaderState::PcapNg {
+                    big_endian: false,
+                    ts_units: Vec::new(),
+                },
+                buffer: Vec::new(),
+            };
+            pcap_reader.read_section_header()?;
+            return Ok(pcap_reader);
+        }
+
+        let mut header = [0u8; 20];
+        reader.read_exact(&mut header)?;
+        let (big_endian, nanos) = match (u32::from_le_bytes(magic), u32::from_be_bytes(magic)) {
+            (PCAP_MAGIC_MICROS, _) => (false, false),
+            (PCAP_MAGIC_NANOS, _) => (false, true),
+            (_, PCAP_MAGIC_MICROS) => (true, false),
+            (_, PCAP_MAGIC_NANOS) => (true, true),
+            _ => return Err(invalid_data("unknown pcap format")),
+        };
+        // The upper bits of the link type hold FCS information.
+        if read_u32(&header[16..], big_endian) & 0xffff != u32::from(LINKTYPE_ETHERNET) {
+            return Err(invalid_data("unsupported pcap link type"));
+        }
+        Ok(Self {
+            reader,
+            state: ReaderState::Pcap { big_endian, nanos },
+            buffer: Vec::new(),
+        })
+    }
+
+    /// Returns the format of the file. It is pcapng from the first section header block on.
+    #[inline]
+    pub fn format(&self) -> PcapFormat {
+        match self.state {
+            ReaderState::Pcap { .. } => PcapFormat::Pcap,
+            ReaderState::PcapNg { .. } => PcapFormat::PcapNg,
+        }
+    }
+
+    /// Read the rest of a pcapng section header block, whose type was read, and start a new
+    /// section.
+    fn read_section_header(&mut self) -> io::Result<()> {
+        let mut header = [0u8; 8];
+        self.reader.read_exact(&mut header)?;
+        let big_endian = match read_u32(&header[4..], false) {
+            PCAPNG_BYTE_ORDER_MAGIC => false,
+            magic if magic.swap_bytes() == PCAPNG_BYTE_ORDER_MAGIC => true,
+            _ => return Err(invalid_data("invalid pcapng byte-order magic")),
+        };
+        let total_len = read_u32(&header, big_endian) as usize;
+        self.read_block_body(total_len, 12)?;
+        self.state = ReaderState::PcapNg {
+            big_endian,
+            ts_units: Vec::new(),
+        };
+        Ok(())
+    }
+
+    /// Read the rest of a pcapng block of `total_len` bytes into the buffer, of which `read` bytes
+    /// were read. The trailing length is not kept.
+    fn read_block_body(&mut self, total_len: usize, read: usize) -> io::Result<()> {
+        if total_len < read + 4 || padding(total_len) != 0 || total_len > PCAPNG_MAX_BLOCK_LEN {
+            return Err(invalid_data("invalid pcapng block length"));
+        }
+        self.buffer.resize(total_len - read, 0);
+        self.reader.read_exact(&mut self.buffer)?;
+        self.buffer.truncate(total_len - read - 4);
+        Ok(())
+    }
+
+    /// Read the next frame, or return `None` at the end of the file.
+    pub fn next_frame(&mut self) -> io::Result<Option<PcapRecord<'_>>> {
+        match self.state {
+            ReaderState::Pcap { big_endian, nanos } => {
+                let mut header = [0u8; 16];
+                if !read_or_eof(&mut self.reader, &mut header)? {
+                    return Ok(None);
+                }
+                let secs = read_u32(&header, big_endian);
+                let fraction = read_u32(&header[4..], big_endian);
+                let cap_len = read_u32(&header[8..], big_endian) as usize;
+                let orig_len = read_u32(&header[12..], big_endian) as usize;
+                let subsec_nanos = if nanos {
+                    fraction
+                } else {
+                    fraction
+                        .checked_mul(1000)
+                        .ok_or_else(|| invalid_data("invalid pcap record header"))?
+                };
+                if subsec_nanos >= 1_000_000_000 || cap_len > PCAPNG_MAX_BLOCK_LEN {
+                    return Err(invalid_data("invalid pcap record header"));
+                }
+                self.buffer.resize(cap_len, 0);
+                self.reader.read_exact(&mut self.buffer)?;
+                Ok(Some(PcapRecord {
+                    timestamp: Duration::new(secs.into(), subsec_nanos),
+                    orig_len,
+                    data: &self.buffer,
+                    info: PcapFrameInfo::default(),
+                }))
+            }
+            ReaderState::PcapNg { .. } => self.next_enhanced_packet(),
+        }
+    }
+
+    fn next_enhanced_packet(&mut self) -> io::Result<Option<PcapRecord<'_>>> {
+        let (timestamp, orig_len, cap_len, info) = loop {
+            let mut block_type = [0u8; 4];
+            if !read_or_eof(&mut self.reader, &mut block_type)? {
+                return Ok(None);
+            }
+            if u32::from_le_bytes(block_type) == PCAPNG_SECTION_HEADER {
+                self.read_section_header()?;
+                continue;
+            }
+            let big_endian = match self.state {
+                ReaderState::PcapNg { big_endian, .. } => big_endian,
+                ReaderState::Pcap { .. } => unreachable!(),
+            };
+            let mut total_len = [0u8; 4];
+            self.reader.read_exact(&mut total_len)?;
+            let total_len = read_u32(&total_len, big_endian) as usize;
+            self.read_block_body(total_len, 8)?;
+
+            let body = &self.buffer;
+            let ts_units = match &mut self.state {
+                ReaderState::PcapNg { ts_units, .. } => ts_units,
+                ReaderState::Pcap { .. } => unreachable!(),
+            };
+            match read_u32(&block_type, big_endian) {
+                PCAPNG_INTERFACE_DESCRIPTION => {
+                    ts_units.push(interface_ts_units(body, big_endian)?);
+                }
+                PCAPNG_ENHANCED_PACKET => {
+                    if body.len() < 20 {
+                        return Err(invalid_data("truncated pcapng enhanced packet"));
+                    }
+                    let interface = read_u32(body, big_endian);
+                    let units = *ts_units
+                        .get(interface as usize)
+                        .ok_or_else(|| invalid_data("unknown pcapng interface"))?;
+                    let ts = u64::from(read_u32(&body[4..], big_endian)) << 32
+                        | u64::from(read_u32(&body[8..], big_endian));
+                    let cap_len = read_u32(&body[12..], big_endian) as usize;
+                    let orig_len = read_u32(&body[16..], big_endian) as usize;
+                    if body.len() < 20 + cap_len {
+                        return Err(invalid_data("truncated pcapng enhanced packet"));
+                    }
+                    let options = body.get((20 + cap_len).div_ceil(4) * 4..);
+                    let info =
+                        enhanced_packet_info(interface, options.unwrap_or_default(), big_endian)?;
+                    let subsec_nanos = u128::from(ts % units) * 1_000_000_000 / u128::from(units);
+                    break (
+                        Duration::new(ts / units, subsec_nanos as u32),
+                        orig_len,
+                        cap_len,
+                        info,
+                    );
+                }
+                _ => {}
+            }
+        };
+        Ok(Some(PcapRecord {
+            timestamp,
+            orig_len,
+            data: &self.buffer[20..20 + cap_len],
+            info,
+        }))
+    }
+
+    /// Read the next frame into a packet allocated from `pool`, and return it with its timestamp,
+    /// or return `None` at the end of the file.
+    ///
+    /// Frames are truncated to the buffer size of the pool. Returns `OutOfMemory` if the pool is
+    /// exhausted.
+    pub fn read_packet<'pool, MPoolPriv: Zeroable>(
+        &mut self,
+        pool: &'pool MPool<MPoolPriv>,
+    ) -> io::Result<Option<(Packet<'pool, MPoolPriv>, Duration)>> {
+        let record = match self.next_frame()? {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+        let mut pkt = pool
+            .alloc()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::OutOfMemory, "mempool exhausted"))?;
+        let len = record.data.len().min(pkt.buffer().len());
+        pkt.append(len);
+        pkt.data_mut().copy_from_slice(&record.data[..len]);
+        Ok(Some((pkt, record.timestamp)))
+    }
+
+    /// Read frames into packets allocated from `pool` until `buffer` is full or the end of the
+    /// file, e.g. to replay a file on a [`TxQ`].
+    ///
+    /// Returns the number of packets read, which is zero at the end of the file.
+    pub fn read_burst<'pool, MPoolPriv: Zeroable, const N: usize>(
+        &mut self,
+        pool: &'pool MPool<MPoolPriv>,
+        buffer: &mut ArrayVec<Packet<'pool, MPoolPriv>, N>,
+    ) -> io::Result<usize> {
+        let current = buffer.len();
+        while !buffer.is_full() {
+            match self.read_packet(pool)? {
+                Some((pkt, _)) => buffer.push(pkt),
+                None => break,
+            }
+        }
+        Ok(buffer.len() - current)
+    }
+}
@@ -0,0 +1,335 @@
+//! Wrapper for DPDK's lockless ring (`rte_ring`) and ring-backed ethdev ports.
+use crate::eal::{rte_errno, Eal, ErrorCode, Packet, SocketId, UninitPort};
+use crate::zeroable::Zeroable;
+use arrayvec::ArrayVec;
+use log::{info, warn};
+use std::convert::TryInto;
+use std::ffi::CString;
+use std::marker::PhantomData;
+use std::os::raw::c_void;
+use std::ptr::{self, NonNull};
+use std::sync::Arc;
+
+/// Abstract type for a DPDK ring carrying packets.
+///
+/// The ring is multi-producer/multi-consumer, thus it can be shared between threads.
+#[derive(Debug, Clone)]
+pub struct Ring<MPoolPriv: Zeroable> {
+    inner: Arc<RingInner>,
+    _phantom: PhantomData<MPoolPriv>,
+}
+
+#[derive(Debug)]
+struct RingInner {
+    ptr: NonNull<dpdk_sys::rte_ring>,
+    // Whether the ring is freed with its last handle. Rings created by another process are not.
+    owned: bool,
+    // Rings must be freed before EAL is cleaned up.
+    _eal: Eal,
+}
+
+/// The `net_ring` device of a ring port, holding the rings it reads and writes.
+///
+/// The device is removed before the rings are released, so that no port refers to freed rings.
+#[derive(Debug)]
+struct RingDevice {
+    name: CString,
+    _rings: Vec<Arc<RingInner>>,
+    eal: Eal,
+}
+
+impl Drop for RingDevice {
+    #[inline]
+    fn drop(&mut self) {
+        let bus_name = CString::new("vdev").unwrap();
+        // Safety: foreign function. The port of the device is closed or was never configured.
+        let ret = self.eal.remove_with(|| unsafe {
+            dpdk_sys::rte_eal_hotplug_remove(bus_name.as_ptr(), self.name.as_ptr())
+        });
+        let enoent: ErrorCode = dpdk_sys::ENOENT.try_into().unwrap();
+        match ret {
+            Ok(()) => info!("Ring device {:?} removed", self.name),
+            // Already removed by `Eal::detach`.
+            Err(err) if err == enoent => {}
+            Err(err) => warn!("Failed to remove ring device {:?}: {}", self.name, err),
+        }
+    }
+}
+
+/// # Safety
+/// Multi-producer/multi-consumer rings are thread-safe.
+unsafe impl Send for RingInner {}
+unsafe impl Sync for RingInner {}
+
+impl Drop for RingInner {
+    #[inline]
+    fn drop(&mut self) {
+        if !self.owned {
+            return;
+        }
+        // Free packets which are still in the ring, otherwise they leak.
+        let mut pkt_buffer = [ptr::null_mut::<c_void>(); 32];
+        loop {
+            // Safety: foreign function. `pkt_buffer` has room for `pkt_buffer.len()` objects.
+            let cnt = unsafe {
+                dpdk_sys::rte_ring_dequeue_burst(
+                    self.ptr.as_ptr(),
+                    pkt_buffer.as_mut_ptr(),
+                    pkt_buffer.len() as u32,
+                    ptr::null_mut(),
+                )
+            };
+            if cnt == 0 {
+                break;
+            }
+            for pkt in &pkt_buffer[..cnt as usize] {
+                // Safety: foreign function. Only mbufs are enqueued into the ring.
+                unsafe { dpdk_sys::rte_pktmbuf_free(*pkt as *mut dpdk_sys::rte_mbuf) };
+            }
+        }
+        // Safety: foreign function.
+        unsafe { dpdk_sys::rte_ring_free(self.ptr.as_ptr()) };
+    }
+}
+
+impl<MPoolPriv: Zeroable> Ring<MPoolPriv> {
+    /// Enqueue packets from the given arrayvec.
+    ///
+    /// Returns number of enqueued packets. As in [`TxQ::tx()`][crate::eal::TxQ::tx], packets
+    /// which did not fit into the ring are left in the `buffer`.
+    #[inline]
+    pub fn enqueue<const N: usize>(
+        &self,
+        buffer: &mut ArrayVec<Packet<'_, MPoolPriv>, N>,
+    ) -> usize {
+        let current = buffer.len();
+        let pkt_buffer = buffer.as_mut_ptr() as *mut *mut c_void;
+
+        // Safety: foreign function.
+        // Safety: `pkt_buffer` is safe to read till `pkt_buffer[current]`.
+        let cnt = unsafe {
+            dpdk_sys::rte_ring_enqueue_burst(
+                self.inner.ptr.as_ptr(),
+                pkt_buffer,
+                current as u32,
+                ptr::null_mut(),
+            ) as usize
+        };
+
+        // Enqueued packets are now owned by the ring. Move the rest to the head of the array.
+        let remaining = current - cnt;
+        // Safety: pkt_buffer[cnt...current] are not enqueued thus safe to be accessed.
+        unsafe { ptr::copy(pkt_buffer.add(cnt), pkt_buffer, remaining) };
+        // Safety: headers are filled with remaining packets and it is safe to set the length.
+        unsafe { buffer.set_len(remaining) };
+        cnt
+    }
+
+    /// Dequeue packets and store them in the remaining capacity of the given arrayvec.
+    ///
+    /// Returns number of dequeued packets.
+    #[inline]
+    pub fn dequeue<'ring, const N: usize>(
+        &'ring self,
+        buffer: &mut ArrayVec<Packet<'ring, MPoolPriv>, N>,
+    ) -> usize {
+        let current = buffer.len();
+        let remaining = buffer.capacity() - current;
+        // Safety: foreign function. Dequeued mbufs are written to the uninitialized tail.
+        unsafe {
+            let pkt_buffer = buffer.as_mut_ptr() as *mut *mut c_void;
+            let cnt = dpdk_sys::rte_ring_dequeue_burst(
+                self.inner.ptr.as_ptr(),
+                pkt_buffer.add(current),
+                remaining as u32,
+                ptr::null_mut(),
+            ) as usize;
+            buffer.set_len(current + cnt);
+            cnt
+        }
+    }
+
+    /// Returns number of packets in the ring.
+    #[inline]
+    pub fn len(&self) -> usize {
+        // Safety: foreign function.
+        unsafe { dpdk_sys::rte_ring_count(self.inner.ptr.as_ptr()) as usize }
+    }
+
+    /// Returns whether the ring is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the maximum number of packets the ring can hold.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        // Safety: foreign function.
+        unsafe { dpdk_sys::rte_ring_get_capacity(self.inner.ptr.as_ptr()) as usize }
+    }
+}
+
+/// Ethdev port backed by rings (`net_ring` PMD).
+///
+/// Packets enqueued to `rx_rings[i]` are received by the port's rx queue `i`, and packets
+/// transmitted through the port's tx queue `i` can be dequeued from `tx_rings[i]`. This allows
+/// driving rx/tx paths without any NIC (e.g. with `--no-huge --no-pci`).
+///
+/// Note: packets received from this port are the very mbufs injected to `rx_rings`, not the ones
+/// allocated from the rx queue's mempool. They must share the same `MPoolPriv`.
+#[derive(Debug)]
+pub struct RingPort<MPoolPriv: Zeroable> {
+    /// The new port. Rings are kept alive as long as the port, whose device is removed when it is
+    /// dropped.
+    pub port: UninitPort,
+    /// Rings read by the port's rx queues.
+    pub rx_rings: Vec<Ring<MPoolPriv>>,
+    /// Rings written by the port's tx queues.
+    pub tx_rings: Vec<Ring<MPoolPriv>>,
+}
+
+impl Eal {
+    /// Create a new `Ring`.
+    ///
+    /// @param size The number of packets the ring can hold. It is rounded up to a power of 2.
+    ///
+    /// @param socket_id The socket identifier where the memory should be allocated. The value can
+    /// be `None` (corresponds to DPDK's *SOCKET_ID_ANY*) if there is no NUMA constraint.
+    #[inline]
+    pub fn create_ring<S: AsRef<str>, MPoolPriv: Zeroable>(
+        &self,
+        name: S,
+        size: usize,
+        socket_id: Option<SocketId>,
+    ) -> Result<Ring<MPoolPriv>, ErrorCode> {
+        let ring_name = CString::new(name.as_ref()).unwrap();
+
+        // Safety: foreign function.
+        if !unsafe { dpdk_sys::rte_ring_lookup(ring_name.as_ptr()) }.is_null() {
+            // Ring names must be globally unique.
+            return Err(dpdk_sys::EEXIST.try_into().unwrap());
+        }
+        // Safety: foreign function.
+        let ptr = unsafe {
+            dpdk_sys::rte_ring_create(
+                ring_name.as_ptr(),
+                // A ring of size `n` holds `n - 1` objects.
+                (size + 1).next_power_of_two().try_into().unwrap(),
+                socket_id
+                    .map(|x| Into::<u32>::into(x) as i32)
+                    .unwrap_or(dpdk_sys::SOCKET_ID_ANY),
+                0,
+            )
+        };
+        let ptr = match NonNull::new(ptr) {
+            Some(ptr) => ptr,
+            None => return Err(rte_errno()),
+        };
+
+        Ok(Ring {
+            inner: Arc::new(RingInner {
+                ptr,
+                owned: true,
+                _eal: self.clone(),
+            }),
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Attach to a ring created by another process (e.g. the primary) with the given name
+    /// (`rte_ring_lookup`).
+    ///
+    /// The ring is neither drained nor freed when the returned handles are dropped. Returns
+    /// `ENOENT` if there is no such ring.
+    ///
+    /// # Safety
+    ///
+    /// The ring must only carry packets of pools whose private data is at least as large as
+    /// `MPoolPriv`, as dequeued packets give access to it.
+    #[inline]
+    pub unsafe fn attach_ring<S: AsRef<str>, MPoolPriv: Zeroable>(
+        &self,
+        name: S,
+    ) -> Result<Ring<MPoolPriv>, ErrorCode> {
+        let ring_name = CString::new(name.as_ref()).unwrap();
+        // Safety: foreign function.
+        let ptr = match NonNull::new(dpdk_sys::rte_ring_lookup(ring_name.as_ptr())) {
+            Some(ptr) => ptr,
+            None => return Err(dpdk_sys::ENOENT.try_into().unwrap()),
+        };
+        Ok(Ring {
+            inner: Arc::new(RingInner {
+                ptr,
+                owned: false,
+                _eal: self.clone(),
+            }),
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Create an ethdev port from newly created rings (`rte_eth_from_rings`).
+    ///
+    /// `name` is used as the vdev name, and must start with `net_ring` (e.g. `net_ring_test0`).
+    /// Each ring can hold `ring_size` packets.
+    pub fn create_ring_port<S: AsRef<str>, MPoolPriv: Zeroable>(
+        &self,
+        name: S,
+        rx_queue_count: u16,
+        tx_queue_count: u16,
+        ring_size: usize,
+    ) -> Result<RingPort<MPoolPriv>, ErrorCode> {
+        let name = name.as_ref();
+        let rx_rings = (0..rx_queue_count)
+            .map(|queue_id| self.create_ring(format!("{}_rx{}", name, queue_id), ring_size, None))
+            .collect::<Result<Vec<Ring<MPoolPriv>>, _>>()?;
+        let tx_rings = (0..tx_queue_count)
+            .map(|queue_id| self.create_ring(format!("{}_tx{}", name, queue_id), ring_size, None))
+            .collect::<Result<Vec<Ring<MPoolPriv>>, _>>()?;
+
+        let rx_ptrs = rx_rings
+            .iter()
+            .map(|ring| ring.inner.ptr.as_ptr())
+            .collect::<Vec<_>>();
+        let tx_ptrs = tx_rings
+            .iter()
+            .map(|ring| ring.inner.ptr.as_ptr())
+            .collect::<Vec<_>>();
+        let dev_name = CString::new(name).unwrap();
+
+        // Safety: foreign function. Pointer arrays outlive the call, and rings outlive the port.
+        let mut ports = self.probe_with(|| unsafe {
+            dpdk_sys::rte_eth_from_rings(
+                dev_name.as_ptr(),
+                rx_ptrs.as_ptr(),
+                rx_queue_count.into(),
+                tx_ptrs.as_ptr(),
+                tx_queue_count.into(),
+                dpdk_sys::rte_socket_id(),
+            )
+        })?;
+        // Removes the device on the error path below, before the rings are freed.
+        let device = RingDevice {
+            name: dev_name,
+            _rings: rx_rings
+                .iter()
+                .chain(tx_rings.iter())
+                .map(|ring| ring.inner.clone())
+                .collect(),
+            eal: self.clone(),
+        };
+        if ports.len() != 1 {
+            // The driver did not create exactly one port for the device.
+            return Err(dpdk_sys::ENODEV.try_into().unwrap());
+        }
+        let mut port = ports.pop().unwrap();
+        port.keep_alive(Arc::new(device));
+        info!("Ring port {} created", port.port_id());
+
+        Ok(RingPort {
+            port,
+            rx_rings,
+            tx_rings,
+        })
+    }
+}
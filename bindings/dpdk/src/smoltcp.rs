@@ -0,0 +1,189 @@
+//! [`smoltcp`](::smoltcp) network device on top of an rx queue, a tx queue and a mempool
+//! (`smoltcp` feature), e.g. for management endpoints or test responders.
+//!
+//! Frames are not copied: rx tokens hand out the data of received packets, and tx tokens build
+//! frames in packets allocated from the mempool.
+use crate::eal::{MPool, MbufFlags, Packet, RxOffload, RxQ, TxQ};
+use crate::zeroable::Zeroable;
+use ::smoltcp::phy::{self, DeviceCapabilities};
+use ::smoltcp::time::Instant;
+use ::smoltcp::{Error, Result};
+use arrayvec::ArrayVec;
+use std::fmt;
+
+/// A [`smoltcp::phy::Device`](::smoltcp::phy::Device) sending and receiving Ethernet frames on a
+/// port.
+///
+/// Packets which the port flags with a bad checksum are dropped if the corresponding rx offloads
+/// are enabled. smoltcp still verifies every rx checksum, as packets may be flagged neither good
+/// nor bad. Tx checksums are always computed by smoltcp, as tx offloads require per-packet header
+/// lengths.
+///
+/// Note: packets are received one at a time, and only the first segment of received packets is
+/// visible to smoltcp.
+pub struct PortDevice<'pool, MPoolPriv: Zeroable> {
+    rxq: RxQ<MPoolPriv>,
+    tx: TxHalf<'pool, MPoolPriv>,
+    capabilities: DeviceCapabilities,
+    rx_offloads: RxOffload,
+}
+
+impl<MPoolPriv: Zeroable> fmt::Debug for PortDevice<'_, MPoolPriv> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PortDevice")
+            .field("port_id", &self.rxq.port().port_id())
+            .field("rx_queue_id", &self.rxq.queue_id())
+            .field("tx_queue_id", &self.tx.txq.queue_id())
+            .finish()
+    }
+}
+
+impl<'pool, MPoolPriv: Zeroable> PortDevice<'pool, MPoolPriv> {
+    /// Create a device receiving from `rxq`, and transmitting packets allocated from `pool` on
+    /// `txq`.
+    ///
+    /// The MTU is taken from the port of `rxq`.
+    ///
+    /// Panic: when the MTU of the port cannot be read.
+    #[inline]
+    pub fn new(rxq: RxQ<MPoolPriv>, txq: TxQ<'pool>, pool: &'pool MPool<MPoolPriv>) -> Self {
+        let port = rxq.port();
+        let rx_offloads = port.rx_offloads();
+        let mtu = port.mtu().expect("failed to read the MTU of the port");
+
+        // Capabilities are non-exhaustive.
+        let mut capabilities = DeviceCapabilities::default();
+        capabilities.max_transmission_unit =
+            usize::from(mtu) + dpdk_sys::RTE_ETHER_HDR_LEN as usize;
+
+        Self {
+            rxq,
+            tx: TxHalf { txq, pool },
+            capabilities,
+            rx_offloads,
+        }
+    }
+
+    /// Returns the queues of this device.
+    #[inline]
+    pub fn into_inner(self) -> (RxQ<MPoolPriv>, TxQ<'pool>) {
+        (self.rxq, self.tx.txq)
+    }
+
+    /// Returns whether the port found a bad checksum in the packet.
+    #[inline]
+    fn bad_checksum(&self, pkt: &Packet<'_, MPoolPriv>) -> bool {
+        let flags = pkt.ol_flags();
+        (self.rx_offloads.contains(RxOffload::IPV4_CKSUM)
+            && flags.contains(MbufFlags::RX_IP_CKSUM_BAD))
+            || (self
+                .rx_offloads
+                .intersects(RxOffload::UDP_CKSUM | RxOffload::TCP_CKSUM)
+                && flags.contains(MbufFlags::RX_L4_CKSUM_BAD))
+    }
+}
+
+impl<'a, MPoolPriv: Zeroable + 'a> phy::Device<'a> for PortDevice<'_, MPoolPriv> {
+    type RxToken = RxToken<'a, MPoolPriv>;
+    type TxToken = TxToken<'a>;
+
+    #[inline]
+    fn receive(&'a mut self) -> Option<(Self::RxToken, Self::TxToken)> {
+        let pkt = loop {
+            let mut burst = ArrayVec::<_, 1>::new();
+            self.rxq.rx(&mut burst);
+            let pkt = burst.pop()?;
+            if !self.bad_checksum(&pkt) {
+                break pkt;
+            }
+        };
+        Some((RxToken { pkt }, TxToken { tx: &mut self.tx }))
+    }
+
+    #[inline]
+    fn transmit(&'a mut self) -> Option<Self::TxToken> {
+        Some(TxToken { tx: &mut self.tx })
+    }
+
+    #[inline]
+    fn capabilities(&self) -> DeviceCapabilities {
+        self.capabilities.clone()
+    }
+}
+
+/// Token of a received packet.
+#[derive(Debug)]
+pub struct RxToken<'a, MPoolPriv: Zeroable> {
+    pkt: Packet<'a, MPoolPriv>,
+}
+
+impl<MPoolPriv: Zeroable> phy::RxToken for RxToken<'_, MPoolPriv> {
+    #[inline]
+    fn consume<R, F>(mut self, _timestamp: Instant, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> Result<R>,
+    {
+        f(self.pkt.data_mut())
+    }
+}
+
+/// Transmitting side of a device.
+struct TxHalf<'pool, MPoolPriv: Zeroable> {
+    txq: TxQ<'pool>,
+    pool: &'pool MPool<MPoolPriv>,
+}
+
+/// Frame transmission, which hides the pool lifetime from tokens: smoltcp requires devices to
+/// implement `Device<'a>` for every `'a`, which tokens borrowing `TxQ<'pool>` could not satisfy.
+trait Transmit {
+    /// Allocate a packet of `len` bytes, fill it with `f`, and transmit it unless `f` fails.
+    fn transmit(&mut self, len: usize, f: &mut dyn FnMut(&mut [u8]) -> Result<()>) -> Result<()>;
+}
+
+impl<MPoolPriv: Zeroable> Transmit for TxHalf<'_, MPoolPriv> {
+    #[inline]
+    fn transmit(&mut self, len: usize, f: &mut dyn FnMut(&mut [u8]) -> Result<()>) -> Result<()> {
+        let mut pkt = self.pool.alloc().ok_or(Error::Exhausted)?;
+        if pkt.buffer().len() < len {
+            return Err(Error::Truncated);
+        }
+        pkt.append(len);
+        f(pkt.data_mut())?;
+        let mut burst = ArrayVec::<_, 1>::new();
+        burst.push(pkt);
+        self.txq.tx(&mut burst);
+        if !burst.is_empty() {
+            return Err(Error::Exhausted);
+        }
+        Ok(())
+    }
+}
+
+/// Token to transmit a packet.
+pub struct TxToken<'a> {
+    tx: &'a mut (dyn Transmit + 'a),
+}
+
+impl fmt::Debug for TxToken<'_> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TxToken").finish()
+    }
+}
+
+impl phy::TxToken for TxToken<'_> {
+    #[inline]
+    fn consume<R, F>(self, _timestamp: Instant, len: usize, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut [u8]) -> Result<R>,
+    {
+        let mut f = Some(f);
+        let mut ret = None;
+        self.tx.transmit(len, &mut |buf| {
+            ret = Some(f.take().unwrap()(buf)?);
+            Ok(())
+        })?;
+        Ok(ret.unwrap())
+    }
+}
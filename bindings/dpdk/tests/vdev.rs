@@ -0,0 +1,1376 @@
+//! Integration tests running on virtual devices, without NICs and hugepages.
+//!
+//! EAL can be initialized only once per process, thus this test uses its own harness
+//! (`harness = false` in Cargo.toml) and runs every case sequentially in a single process.
+extern crate arrayvec;
+extern crate dpdk;
+
+use arrayvec::ArrayVec;
+use dpdk::capture::CaptureConf;
+use dpdk::eal::*;
+use dpdk::extbuf::ExtBuf;
+use dpdk::mempool::{MempoolOps, ObjectPool, PoolBox};
+use dpdk::mp::{MpMessage, MpRequest, ProcessType};
+use dpdk::pcap::{PcapDirection, PcapFormat, PcapPortConf, PcapReader, PcapRx, PcapTx, PcapWriter};
+use dpdk::ring::Ring;
+use dpdk::tx_buffer::TxBuffer;
+use dpdk::zeroable::Zeroable;
+use std::cell::RefCell;
+use std::convert::TryInto;
+use std::env;
+use std::ffi::CString;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::panic::{self, AssertUnwindSafe};
+use std::process;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// `net_null0` becomes port 0 and `net_ring0` (rx/tx loopback) becomes port 1.
+const EAL_ARGS: &[&str] = &[
+    "--no-huge",
+    "--no-pci",
+    "--no-shconf",
+    "-m",
+    "512",
+    "-l",
+    "0",
+    "--log-level=warning",
+    "--vdev=net_null0",
+    "--vdev=net_ring0",
+];
+
+const TEST_POOL_SIZE: usize = 1023;
+
+/// Shared state of all test cases.
+struct Fixture<'pool> {
+    eal: Eal,
+    pool: &'pool MPool<()>,
+    null_port: Port,
+    null_rxq: RxQ<()>,
+    null_txq: RefCell<TxQ<'pool>>,
+    loop_port: Port,
+    loop_rxq: RxQ<()>,
+    loop_txq: RefCell<TxQ<'pool>>,
+}
+
+/// Initialize a port with one rx and one tx queue, and start it.
+fn start_port(port: UninitPort) -> (Port, RxQ<()>, TxQ<'static>) {
+    let (port, (rxqs, txqs)) = port.init(1, 1, None);
+    port.start().unwrap();
+    let rxq = rxqs.into_iter().next().unwrap();
+    let txq = txqs.into_iter().next().unwrap();
+    (port, rxq, txq)
+}
+
+/// Allocate `n` packets with `len` bytes of data, filled with `fill`.
+fn alloc_packets<'pool, const N: usize>(
+    pool: &'pool MPool<()>,
+    n: usize,
+    len: usize,
+    fill: u8,
+) -> ArrayVec<Packet<'pool, ()>, N> {
+    let mut pkts = ArrayVec::new();
+    for _ in 0..n {
+        let mut pkt = pool.alloc().expect("pool exhausted");
+        pkt.append(len);
+        pkt.data_mut().iter_mut().for_each(|b| *b = fill);
+        pkts.push(pkt);
+    }
+    pkts
+}
+
+/// Drain a ring until it becomes empty and return number of drained packets.
+fn drain_ring(ring: &Ring<()>) -> usize {
+    let mut count = 0;
+    let mut pkts = ArrayVec::<Packet<'_, ()>, DEFAULT_RX_BURST>::new();
+    while ring.dequeue(&mut pkts) > 0 {
+        count += pkts.len();
+        pkts.clear();
+    }
+    count
+}
+
+fn mempool_alloc_free(f: &Fixture<'_>) {
+    let pool: MPool<()> = f
+        .eal
+        .create_mpool("test_small_pool", 63, 0, DEFAULT_PACKET_DATA_LENGTH, None)
+        .unwrap();
+
+    // Every object can be allocated once, and exhausted pool returns `None`.
+    let pkts = (0..63).map(|_| pool.alloc().unwrap()).collect::<Vec<_>>();
+    assert!(pool.alloc().is_none());
+
+    // Freed objects are returned to the pool.
+    drop(pkts);
+    let mut bulk = ArrayVec::<Packet<'_, ()>, 63>::new();
+    assert!(pool.alloc_bulk(&mut bulk));
+    assert_eq!(bulk.len(), 63);
+
+    // Bulk allocation is all-or-nothing.
+    let mut more = ArrayVec::<Packet<'_, ()>, 1>::new();
+    assert!(!pool.alloc_bulk(&mut more));
+    assert!(more.is_empty());
+}
+
+fn mempool_lookup_counters(f: &Fixture<'_>) {
+    struct Meta {
+        _flow_id: u64,
+    }
+    unsafe impl Zeroable for Meta {}
+    struct LargeMeta {
+        _flow_id: [u64; 2],
+    }
+    unsafe impl Zeroable for LargeMeta {}
+
+    // Names are globally unique.
+    let dup: Result<MPool<()>, _> =
+        f.eal
+            .create_mpool("test_pool", 63, 0, DEFAULT_PACKET_DATA_LENGTH, None);
+    assert!(dup.is_err());
+
+    let pool: MPool<Meta> = f
+        .eal
+        .create_mpool_by_ops(
+            "test_stack_pool",
+            63,
+            0,
+            DEFAULT_PACKET_DATA_LENGTH,
+            None,
+            MempoolOps::Stack,
+        )
+        .unwrap();
+    let found = MPool::<Meta>::lookup(&f.eal, "test_stack_pool").unwrap();
+    // Private data of packets must be large enough.
+    assert!(MPool::<LargeMeta>::lookup(&f.eal, "test_stack_pool").is_err());
+    assert!(MPool::<()>::lookup(&f.eal, "test_no_pool").is_err());
+
+    let pkt = found.alloc().unwrap();
+    assert_eq!(pool.available(), 62);
+    assert_eq!(pool.in_use(), 1);
+    drop(pkt);
+    assert_eq!(found.available(), 63);
+    pool.audit();
+    assert!(pool.dump().contains("test_stack_pool"));
+
+    // The pool is gone with its last handle.
+    drop(pool);
+    drop(found);
+    assert!(MPool::<Meta>::lookup(&f.eal, "test_stack_pool").is_err());
+}
+
+fn object_pool(f: &Fixture<'_>) {
+    #[derive(Debug, PartialEq)]
+    struct FlowRecord {
+        key: [u32; 4],
+        packets: u64,
+        tags: Vec<String>,
+    }
+
+    for (name, ops) in [
+        ("test_object_pool", None),
+        ("test_object_pool_ring", Some(MempoolOps::Ring)),
+        ("test_object_pool_bucket", Some(MempoolOps::Bucket)),
+    ] {
+        let pool: ObjectPool<FlowRecord> = match ops {
+            Some(ops) => f.eal.create_object_pool_by_ops(name, 4, 0, None, ops),
+            None => f.eal.create_object_pool(name, 4, 0, None),
+        }
+        .unwrap();
+
+        let mut records = (0..4)
+            .map(|i| {
+                pool.alloc(FlowRecord {
+                    key: [i; 4],
+                    packets: 0,
+                    tags: vec![format!("flow{}", i)],
+                })
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(pool.in_use(), 4);
+
+        // Exhausted pool gives the value back.
+        let rejected = pool.alloc(FlowRecord {
+            key: [9; 4],
+            packets: 0,
+            tags: Vec::new(),
+        });
+        assert_eq!(rejected.unwrap_err().key, [9; 4]);
+
+        records[1].packets += 10;
+        assert_eq!(records[1].packets, 10);
+        let record = PoolBox::into_inner(records.remove(1));
+        assert_eq!(record.tags, vec!["flow1".to_string()]);
+        assert_eq!(pool.available(), 1);
+
+        drop(records);
+        assert_eq!(pool.available(), 4);
+        pool.audit();
+    }
+}
+
+fn packet_head_tail(f: &Fixture<'_>) {
+    let mut pkt = f.pool.alloc().unwrap();
+    assert!(pkt.is_empty());
+    assert_eq!(pkt.capacity(), DEFAULT_PACKET_DATA_LENGTH);
+
+    pkt.append(100);
+    assert_eq!(pkt.len(), 100);
+    pkt.data_mut()
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, b)| *b = i as u8);
+
+    // Prepending keeps the payload behind the new header.
+    pkt.prepend(14);
+    assert_eq!(pkt.len(), 114);
+    assert_eq!(pkt.data()[14], 0);
+    assert_eq!(pkt.data()[113], 99);
+
+    pkt.trim_head(14);
+    assert_eq!(pkt.len(), 100);
+    assert_eq!(pkt.data()[0], 0);
+
+    pkt.append(10);
+    assert_eq!(pkt.len(), 110);
+    pkt.trim_tail(10);
+    assert_eq!(pkt.len(), 100);
+    assert_eq!(pkt.data()[99], 99);
+
+    pkt.set_len(60);
+    assert_eq!(pkt.len(), 60);
+    pkt.reset_headroom();
+    assert_eq!(pkt.len(), 60);
+}
+
+fn null_rx_tx(f: &Fixture<'_>) {
+    f.null_port.reset_stat();
+
+    // `net_null` generates packets on every rx burst.
+    let mut pkts = ArrayVec::<Packet<'_, ()>, DEFAULT_RX_BURST>::new();
+    f.null_rxq.rx(&mut pkts);
+    assert_eq!(pkts.len(), DEFAULT_RX_BURST);
+    drop(pkts);
+
+    // ... and swallows every transmitted packet.
+    let mut txq_pkts = alloc_packets::<DEFAULT_TX_BURST>(f.pool, DEFAULT_TX_BURST, 64, 0);
+    f.null_txq.borrow_mut().tx(&mut txq_pkts);
+    assert!(txq_pkts.is_empty());
+    assert_eq!(f.null_port.get_stat().opackets, DEFAULT_TX_BURST as u64);
+}
+
+fn loopback_rx_tx(f: &Fixture<'_>) {
+    let mut pkts = alloc_packets::<8>(f.pool, 8, 64, 0xab);
+    f.loop_txq.borrow_mut().tx(&mut pkts);
+    assert!(pkts.is_empty());
+
+    let mut received = ArrayVec::<Packet<'_, ()>, DEFAULT_RX_BURST>::new();
+    f.loop_rxq.rx(&mut received);
+    assert_eq!(received.len(), 8);
+    assert!(received
+        .iter()
+        .all(|pkt| pkt.len() == 64 && pkt.data().iter().all(|b| *b == 0xab)));
+}
+
+fn tx_cloned_refcount(f: &Fixture<'_>) {
+    let pkts = alloc_packets::<4>(f.pool, 4, 64, 0xcd);
+    let mut txq = f.loop_txq.borrow_mut();
+    assert_eq!(txq.tx_cloned(&pkts), 4);
+    assert_eq!(txq.tx_cloned(&pkts), 4);
+
+    // The same mbufs come back twice, while the originals are still valid.
+    let mut received = ArrayVec::<Packet<'_, ()>, DEFAULT_RX_BURST>::new();
+    f.loop_rxq.rx(&mut received);
+    assert_eq!(received.len(), 8);
+    assert!(pkts.iter().all(|pkt| pkt.data().iter().all(|b| *b == 0xcd)));
+
+    // Dropping every reference returns all mbufs to the pool.
+    drop(received);
+    drop(pkts);
+    let mut bulk = ArrayVec::<Packet<'_, ()>, TEST_POOL_SIZE>::new();
+    assert!(f.pool.alloc_bulk(&mut bulk));
+}
+
+fn packet_clone_copy(f: &Fixture<'_>) {
+    #[derive(Debug, PartialEq)]
+    struct Meta {
+        flow_id: u64,
+    }
+    unsafe impl Zeroable for Meta {}
+
+    let pool: MPool<Meta> = f
+        .eal
+        .create_mpool("test_direct_pool", 15, 0, DEFAULT_PACKET_DATA_LENGTH, None)
+        .unwrap();
+    // Indirect mbufs only hold headers.
+    let indirect_pool: MPool<Meta> = f
+        .eal
+        .create_mpool("test_indirect_pool", 15, 0, 0, None)
+        .unwrap();
+
+    let mut pkt = pool.alloc().unwrap();
+    pkt.append(64);
+    pkt.data_mut().fill(0x11);
+    pkt.priv_data_mut().flow_id = 7;
+    assert_eq!(pkt.refcnt(), 1);
+    assert!(!pkt.is_shared());
+
+    // Clones share the buffer and get their own header.
+    let mut clone = pkt.clone_shallow(&indirect_pool).unwrap();
+    assert_eq!(indirect_pool.in_use(), 1);
+    assert_eq!(pool.in_use(), 1);
+    assert!(pkt.is_shared() && clone.is_shared());
+    assert_eq!(clone.refcnt(), 1);
+    assert_eq!(clone.priv_data().flow_id, 7);
+    clone.trim_head(14);
+    assert_eq!(clone.len(), 50);
+    assert_eq!(pkt.len(), 64);
+    assert!(clone.data().iter().all(|b| *b == 0x11));
+
+    // Writing to a shared buffer copies the packet first.
+    clone.data_mut()[0] = 0x22;
+    assert!(!clone.is_shared() && !pkt.is_shared());
+    assert_eq!(indirect_pool.in_use(), 0);
+    assert_eq!(pool.in_use(), 2);
+    assert_eq!(clone.priv_data().flow_id, 7);
+    assert_eq!(clone.len(), 50);
+    assert_eq!(clone.data()[0], 0x22);
+    assert_eq!(pkt.data()[0], 0x11);
+
+    // Dropping the original keeps the buffer alive for its clones.
+    let clone = pkt.clone_shallow(&indirect_pool).unwrap();
+    drop(pkt);
+    assert_eq!(pool.in_use(), 2);
+    assert!(!clone.is_shared());
+    assert!(clone.data().iter().all(|b| *b == 0x11));
+    drop(clone);
+    assert_eq!(indirect_pool.in_use(), 0);
+    assert_eq!(pool.in_use(), 1);
+
+    // Deep copies are truncated to the packet.
+    let mut pkt = pool.alloc().unwrap();
+    pkt.append(64);
+    pkt.data_mut()
+        .iter_mut()
+        .enumerate()
+        .for_each(|(i, b)| *b = i as u8);
+    let copy = pkt.copy(&pool, 10, 100).unwrap();
+    assert_eq!(copy.len(), 54);
+    assert_eq!(copy.data()[0], 10);
+    assert!(!pkt.is_shared() && !copy.is_shared());
+    assert!(pkt.copy(&pool, 65, 1).is_none());
+}
+
+fn extbuf_attach(f: &Fixture<'_>) {
+    /// Payload which reports when it is freed.
+    struct Payload(Vec<u8>, Arc<AtomicBool>);
+    impl AsRef<[u8]> for Payload {
+        fn as_ref(&self) -> &[u8] {
+            &self.0
+        }
+    }
+    impl Drop for Payload {
+        fn drop(&mut self) {
+            self.1.store(true, Ordering::SeqCst);
+        }
+    }
+
+    let freed = Arc::new(AtomicBool::new(false));
+    let extbuf = ExtBuf::new(Payload(vec![0xef; 256], freed.clone()));
+    assert_eq!(extbuf.len(), 256);
+
+    let mut pkts = alloc_packets::<4>(f.pool, 4, 0, 0);
+    for (i, pkt) in pkts.iter_mut().enumerate() {
+        pkt.attach_extbuf(&extbuf, i * 64, 64);
+        assert_eq!(pkt.len(), 64);
+        assert!(pkt.is_shared());
+    }
+    assert_eq!(extbuf.refcnt(), 5);
+
+    // Writing copies the packet into the pool.
+    pkts[0].data_mut()[0] = 0;
+    assert!(!pkts[0].is_shared());
+    assert_eq!(extbuf[0], 0xef);
+    assert_eq!(extbuf.refcnt(), 4);
+
+    // The buffer outlives its handle until transmitted packets are freed.
+    drop(extbuf);
+    assert!(!freed.load(Ordering::SeqCst));
+    f.loop_txq.borrow_mut().tx(&mut pkts);
+    assert!(pkts.is_empty());
+    assert!(!freed.load(Ordering::SeqCst));
+
+    let mut received = ArrayVec::<Packet<'_, ()>, DEFAULT_RX_BURST>::new();
+    f.loop_rxq.rx(&mut received);
+    assert_eq!(received.len(), 4);
+    assert!(received[1..]
+        .iter()
+        .all(|pkt| pkt.len() == 64 && pkt.data().iter().all(|b| *b == 0xef)));
+    drop(received);
+    assert!(freed.load(Ordering::SeqCst));
+}
+
+fn queue_callbacks(f: &Fixture<'_>) {
+    let rx_seen = Arc::new(AtomicUsize::new(0));
+    let rx_count = rx_seen.clone();
+    // Keep packets whose first byte is even.
+    let rx_cb = f
+        .loop_rxq
+        .add_callback(move |pkts: &mut [Packet<'_, ()>]| {
+            rx_count.fetch_add(pkts.len(), Ordering::SeqCst);
+            let mut kept = 0;
+            for i in 0..pkts.len() {
+                if pkts[i].data()[0] & 1 == 0 {
+                    pkts.swap(kept, i);
+                    kept += 1;
+                }
+            }
+            kept
+        })
+        .unwrap();
+    // Stamp every packet, and hold back the last one of each burst.
+    let tx_cb = f
+        .loop_txq
+        .borrow()
+        .add_callback(|pkts: &mut [Packet<'_, ()>]| {
+            for pkt in pkts.iter_mut() {
+                pkt.data_mut()[1] = 0x5a;
+            }
+            pkts.len().saturating_sub(1)
+        })
+        .unwrap();
+
+    let mut pkts = (0..4u8)
+        .map(|i| {
+            let mut pkt = f.pool.alloc().unwrap();
+            pkt.append(64);
+            pkt.data_mut()[0] = i;
+            pkt
+        })
+        .collect::<ArrayVec<_, 4>>();
+    f.loop_txq.borrow_mut().tx(&mut pkts);
+    assert_eq!(pkts.len(), 1);
+    assert_eq!(pkts[0].data()[0], 3);
+
+    let mut received = ArrayVec::<Packet<'_, ()>, DEFAULT_RX_BURST>::new();
+    f.loop_rxq.rx(&mut received);
+    assert_eq!(rx_seen.load(Ordering::SeqCst), 3);
+    assert_eq!(received.len(), 2);
+    assert!(received
+        .iter()
+        .all(|pkt| pkt.data()[0] & 1 == 0 && pkt.data()[1] == 0x5a));
+    drop(received);
+
+    // Removed callbacks are not called anymore.
+    drop(rx_cb);
+    drop(tx_cb);
+    f.loop_txq.borrow_mut().tx(&mut pkts);
+    assert!(pkts.is_empty());
+    let mut received = ArrayVec::<Packet<'_, ()>, DEFAULT_RX_BURST>::new();
+    f.loop_rxq.rx(&mut received);
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].data()[0], 3);
+    assert_eq!(rx_seen.load(Ordering::SeqCst), 3);
+}
+
+/// Attach a `net_tap` port with rx interrupts, and a `net_af_packet` port which injects frames into
+/// it through its interface. Returns `None` if either device is unavailable (e.g. without
+/// `CAP_NET_ADMIN`).
+fn attach_tap_pair(eal: &Eal, name: &str) -> Option<(Port, RxQ<()>, Port, TxQ<'static>)> {
+    let iface = format!("dtap_{}", name);
+    let tap = eal
+        .attach_vdev(format!("net_tap_{}", name), format!("iface={}", iface))
+        .ok()?
+        .pop()?;
+    let mut port_conf = RteEthConf::for_rx_queues(1);
+    port_conf.set_rx_interrupts(true);
+    let (tap, (rxqs, _)) = tap.init(1, 1, Some(port_conf));
+    tap.start().unwrap();
+    let injector = eal
+        .attach_vdev(
+            format!("net_af_packet_{}", name),
+            format!("iface={}", iface),
+        )
+        .ok()?
+        .pop()?;
+    let (injector, _, txq) = start_port(injector);
+    Some((tap, rxqs.into_iter().next().unwrap(), injector, txq))
+}
+
+/// Returns the number of packets filled with `fill`. The kernel may send its own frames (e.g. IPv6
+/// neighbor discovery) to a tap interface, which are not counted.
+fn count_filled(pkts: &[Packet<'_, ()>], fill: u8) -> usize {
+    pkts.iter().filter(|pkt| pkt.data()[0] == fill).count()
+}
+
+fn rx_interrupts(f: &Fixture<'_>) {
+    use dpdk::interrupt::{wait_rx_interrupt, AdaptiveRx};
+
+    // Rx interrupts are not configured by default, and net_ring does not support them.
+    assert!(f.loop_rxq.enable_interrupt().is_err());
+    let port = f
+        .eal
+        .attach_vdev("net_ring_intr0", "")
+        .unwrap()
+        .pop()
+        .unwrap();
+    let mut port_conf = RteEthConf::for_rx_queues(1);
+    port_conf.set_rx_interrupts(true);
+    let (_port, (rxqs, _)) = port.init::<()>(1, 1, Some(port_conf));
+    assert!(rxqs[0].enable_interrupt().is_err());
+
+    let (_tap, rxq, _injector, mut txq) = match attach_tap_pair(&f.eal, "intr") {
+        Some(ports) => ports,
+        None => {
+            eprintln!("net_tap or net_af_packet is unavailable, skipping rx_interrupts");
+            return;
+        }
+    };
+    // Injected packets wake up the armed queue.
+    rxq.enable_interrupt().unwrap();
+    let mut pkts = alloc_packets::<2>(f.pool, 2, 64, 1);
+    txq.tx(&mut pkts);
+    assert!(pkts.is_empty());
+    assert!(wait_rx_interrupt(Some(Duration::from_secs(5))).unwrap());
+    rxq.disable_interrupt().unwrap();
+
+    let mut adaptive = AdaptiveRx::new(&rxq, 4);
+    adaptive.set_timeout(Some(Duration::from_secs(1)));
+    let mut rx_filled = |fill: u8, n: usize| {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut cnt = 0;
+        while cnt < n {
+            assert!(Instant::now() < deadline, "packets not received");
+            let mut received = ArrayVec::<Packet<'_, ()>, DEFAULT_RX_BURST>::new();
+            adaptive.rx(&mut received).unwrap();
+            cnt += count_filled(&received, fill);
+        }
+        assert_eq!(cnt, n);
+    };
+    rx_filled(1, 2);
+
+    // The idle loop sleeps until packets are sent from another thread.
+    let pool = f.pool;
+    let txq = &mut txq;
+    crossbeam_utils::thread::scope(|s| {
+        s.spawn(move |_| {
+            thread::sleep(Duration::from_millis(20));
+            let mut pkts = alloc_packets::<3>(pool, 3, 64, 2);
+            txq.tx(&mut pkts);
+            assert!(pkts.is_empty());
+        });
+        rx_filled(2, 3);
+    })
+    .unwrap();
+}
+
+#[cfg(feature = "async")]
+fn async_rx_tx(f: &Fixture<'_>) {
+    use dpdk::async_io::{block_on, RxStream, TxSink};
+    use futures_core::Stream;
+    use futures_sink::Sink;
+    use std::future::poll_fn;
+    use std::pin::Pin;
+
+    // Cooperative polling.
+    {
+        let mut txq = f.loop_txq.borrow_mut();
+        let mut sink = TxSink::<(), 4>::new(&mut txq);
+        for pkt in alloc_packets::<3>(f.pool, 3, 64, 7) {
+            block_on(poll_fn(|cx| Pin::new(&mut sink).poll_ready(cx))).unwrap();
+            Pin::new(&mut sink).start_send(pkt).unwrap();
+        }
+        assert_eq!(sink.len(), 3);
+        block_on(poll_fn(|cx| Pin::new(&mut sink).poll_flush(cx))).unwrap();
+        assert!(sink.is_empty());
+    }
+    let mut stream = RxStream::<(), DEFAULT_RX_BURST>::polling(&f.loop_rxq);
+    let burst = block_on(poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)))
+        .unwrap()
+        .unwrap();
+    assert_eq!(burst.len(), 3);
+    assert!(burst.iter().all(|pkt| pkt.data()[0] == 7));
+}
+
+// Rx interrupts registered to Tokio's reactor.
+#[cfg(feature = "async")]
+fn async_rx_interrupts(f: &Fixture<'_>) {
+    use dpdk::async_io::RxStream;
+    use futures_core::Stream;
+    use std::future::poll_fn;
+    use std::pin::Pin;
+
+    let (_tap, rxq, _injector, mut txq) = match attach_tap_pair(&f.eal, "async") {
+        Some(ports) => ports,
+        None => {
+            eprintln!("net_tap or net_af_packet is unavailable, skipping async_rx_interrupts");
+            return;
+        }
+    };
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_io()
+        .build()
+        .unwrap();
+
+    let pool = f.pool;
+    let txq = &mut txq;
+    crossbeam_utils::thread::scope(|s| {
+        let mut stream = runtime
+            .block_on(async { RxStream::<(), DEFAULT_RX_BURST>::with_interrupt(&rxq) })
+            .unwrap();
+        s.spawn(move |_| {
+            thread::sleep(Duration::from_millis(20));
+            let mut pkts = alloc_packets::<2>(pool, 2, 64, 9);
+            txq.tx(&mut pkts);
+            assert!(pkts.is_empty());
+        });
+        let mut cnt = 0;
+        while cnt < 2 {
+            let burst = runtime
+                .block_on(poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)))
+                .unwrap()
+                .unwrap();
+            cnt += count_filled(&burst, 9);
+        }
+        assert_eq!(cnt, 2);
+    })
+    .unwrap();
+}
+
+#[cfg(feature = "smoltcp")]
+fn smoltcp_device(f: &Fixture<'_>) {
+    use dpdk::smoltcp::PortDevice;
+    use smoltcp::iface::{EthernetInterfaceBuilder, NeighborCache};
+    use smoltcp::phy::{Checksum, ChecksumCapabilities, Device};
+    use smoltcp::socket::SocketSet;
+    use smoltcp::time::Instant;
+    use smoltcp::wire::*;
+    use std::collections::BTreeMap;
+
+    let local_mac = EthernetAddress([0x02, 0, 0, 0, 0, 0x01]);
+    let local_ip = Ipv4Address::new(10, 0, 0, 1);
+    let peer_mac = EthernetAddress([0x02, 0, 0, 0, 0, 0x02]);
+    let peer_ip = Ipv4Address::new(10, 0, 0, 2);
+    let caps = ChecksumCapabilities::default();
+
+    let ring_port = f
+        .eal
+        .create_ring_port::<_, ()>("net_ring_smoltcp", 1, 1, 63)
+        .unwrap();
+    let rx_ring = ring_port.rx_rings[0].clone();
+    let tx_ring = ring_port.tx_rings[0].clone();
+    let mut port_conf = RteEthConf::for_rx_queues(1);
+    port_conf.data.rxmode.offloads = RxOffload::IPV4_CKSUM.bits();
+    let (port, (rxqs, txqs)) = ring_port.port.init(1, 1, Some(port_conf));
+    port.start().unwrap();
+    assert_eq!(port.rx_offloads(), RxOffload::IPV4_CKSUM);
+    let pool: MPool<()> = f
+        .eal
+        .create_mpool("test_smoltcp_pool", 63, 0, DEFAULT_PACKET_DATA_LENGTH, None)
+        .unwrap();
+    let device = PortDevice::new(
+        rxqs.into_iter().next().unwrap(),
+        txqs.into_iter().next().unwrap(),
+        &pool,
+    );
+    let device_caps = device.capabilities();
+    assert_eq!(device_caps.max_transmission_unit, 1514);
+    assert!(matches!(device_caps.checksum.ipv4, Checksum::Both));
+    assert!(matches!(device_caps.checksum.tcp, Checksum::Both));
+
+    let mut iface = EthernetInterfaceBuilder::new(device)
+        .ethernet_addr(local_mac)
+        .neighbor_cache(NeighborCache::new(BTreeMap::new()))
+        .ip_addrs([IpCidr::new(IpAddress::Ipv4(local_ip), 24)])
+        .finalize();
+    let mut sockets = SocketSet::new(vec![]);
+    let mut poll = |iface: &mut smoltcp::iface::EthernetInterface<'_, PortDevice<'_, ()>>| {
+        let _ = iface.poll(&mut sockets, Instant::from_millis(0));
+    };
+    let inject = |eth: EthernetRepr, len: usize, emit: &dyn Fn(&mut [u8]), flags: MbufFlags| {
+        let mut pkt = f.pool.alloc().unwrap();
+        pkt.append(EthernetFrame::<&[u8]>::header_len() + len);
+        let mut frame = EthernetFrame::new_unchecked(pkt.data_mut());
+        eth.emit(&mut frame);
+        emit(frame.payload_mut());
+        pkt.set_ol_flags(flags);
+        let mut pkts = ArrayVec::<_, 1>::new();
+        pkts.push(pkt);
+        assert_eq!(rx_ring.enqueue(&mut pkts), 1);
+    };
+    let extract = || {
+        let mut pkts = ArrayVec::<Packet<'_, ()>, DEFAULT_RX_BURST>::new();
+        tx_ring.dequeue(&mut pkts);
+        pkts
+    };
+
+    // ARP.
+    let arp = ArpRepr::EthernetIpv4 {
+        operation: ArpOperation::Request,
+        source_hardware_addr: peer_mac,
+        source_protocol_addr: peer_ip,
+        target_hardware_addr: EthernetAddress([0; 6]),
+        target_protocol_addr: local_ip,
+    };
+    let eth = EthernetRepr {
+        src_addr: peer_mac,
+        dst_addr: EthernetAddress::BROADCAST,
+        ethertype: EthernetProtocol::Arp,
+    };
+    inject(
+        eth,
+        arp.buffer_len(),
+        &|buf| arp.emit(&mut ArpPacket::new_unchecked(buf)),
+        MbufFlags::empty(),
+    );
+    poll(&mut iface);
+    let replies = extract();
+    assert_eq!(replies.len(), 1);
+    let frame = EthernetFrame::new_checked(replies[0].data()).unwrap();
+    assert_eq!(frame.dst_addr(), peer_mac);
+    let reply = ArpRepr::parse(&ArpPacket::new_checked(frame.payload()).unwrap()).unwrap();
+    assert!(matches!(
+        reply,
+        ArpRepr::EthernetIpv4 {
+            operation: ArpOperation::Reply,
+            source_hardware_addr,
+            source_protocol_addr,
+            ..
+        } if source_hardware_addr == local_mac && source_protocol_addr == local_ip
+    ));
+    drop(replies);
+
+    // ICMP echo, and requests whose IP checksum is bad according to the port.
+    let data = [0xab; 16];
+    let icmp = Icmpv4Repr::EchoRequest {
+        ident: 1,
+        seq_no: 1,
+        data: &data,
+    };
+    let ip = Ipv4Repr {
+        src_addr: peer_ip,
+        dst_addr: local_ip,
+        protocol: IpProtocol::Icmp,
+        payload_len: icmp.buffer_len(),
+        hop_limit: 64,
+    };
+    let eth = EthernetRepr {
+        src_addr: peer_mac,
+        dst_addr: local_mac,
+        ethertype: EthernetProtocol::Ipv4,
+    };
+    let emit_echo = |buf: &mut [u8]| {
+        let mut packet = Ipv4Packet::new_unchecked(buf);
+        ip.emit(&mut packet, &caps);
+        icmp.emit(
+            &mut Icmpv4Packet::new_unchecked(packet.payload_mut()),
+            &caps,
+        );
+    };
+    let len = ip.buffer_len() + icmp.buffer_len();
+    inject(eth, len, &emit_echo, MbufFlags::RX_IP_CKSUM_BAD);
+    poll(&mut iface);
+    assert!(extract().is_empty());
+    // smoltcp verifies checksums which the port did not flag.
+    let emit_corrupt_echo = |buf: &mut [u8]| {
+        emit_echo(buf);
+        buf[10] ^= 0xff;
+    };
+    inject(eth, len, &emit_corrupt_echo, MbufFlags::empty());
+    poll(&mut iface);
+    assert!(extract().is_empty());
+    inject(eth, len, &emit_echo, MbufFlags::empty());
+    poll(&mut iface);
+    let replies = extract();
+    assert_eq!(replies.len(), 1);
+    let frame = EthernetFrame::new_checked(replies[0].data()).unwrap();
+    let packet = Ipv4Packet::new_checked(frame.payload()).unwrap();
+    let reply_ip = Ipv4Repr::parse(&packet, &caps).unwrap();
+    assert_eq!(reply_ip.dst_addr, peer_ip);
+    let reply =
+        Icmpv4Repr::parse(&Icmpv4Packet::new_checked(packet.payload()).unwrap(), &caps).unwrap();
+    assert_eq!(
+        reply,
+        Icmpv4Repr::EchoReply {
+            ident: 1,
+            seq_no: 1,
+            data: &data,
+        }
+    );
+}
+
+fn pcap_replay_record(f: &Fixture<'_>) {
+    let dir = env::temp_dir().join(format!("dpdk_pcap_test_{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let rx_path = dir.join("rx.pcap");
+    let tx_path = dir.join("tx.pcap");
+
+    // Classic pcap with nanosecond timestamps.
+    let frames = (0..3u8)
+        .map(|i| vec![i; 60 + 100 * i as usize])
+        .collect::<Vec<_>>();
+    let mut writer = PcapWriter::new(
+        BufWriter::new(File::create(&rx_path).unwrap()),
+        PcapFormat::Pcap,
+    )
+    .unwrap();
+    for (i, frame) in frames.iter().enumerate() {
+        let timestamp = Duration::new(1_600_000_000 + i as u64, 123_456_789);
+        writer.write_frame(frame, frame.len(), timestamp).unwrap();
+    }
+    writer.into_inner().into_inner().unwrap();
+    let mut reader = PcapReader::new(File::open(&rx_path).unwrap()).unwrap();
+    assert_eq!(reader.format(), PcapFormat::Pcap);
+    let record = reader.next_frame().unwrap().unwrap();
+    assert_eq!(record.timestamp, Duration::new(1_600_000_000, 123_456_789));
+    assert_eq!(record.data, &frames[0][..]);
+
+    // Replay the file through a port, which writes transmitted packets to another file.
+    let conf = PcapPortConf {
+        rx: vec![PcapRx::File(rx_path.clone())],
+        tx: vec![PcapTx::File(tx_path.clone())],
+        ..Default::default()
+    };
+    assert_eq!(
+        conf.devargs().unwrap(),
+        format!(
+            "rx_pcap={},tx_pcap={}",
+            rx_path.display(),
+            tx_path.display()
+        )
+    );
+    let (port, rxq, txq) = start_port(f.eal.create_pcap_port("net_pcap_replay", &conf).unwrap());
+    let mut txq: TxQ<'_> = txq;
+
+    // Record received packets in memory, as pcapng.
+    let recorded = Arc::new(Mutex::new(
+        PcapWriter::new(Vec::new(), PcapFormat::PcapNg).unwrap(),
+    ));
+    let record_cb = rxq.record(recorded.clone()).unwrap();
+    let mut pkts = ArrayVec::<Packet<'_, ()>, DEFAULT_RX_BURST>::new();
+    rxq.rx(&mut pkts);
+    assert_eq!(pkts.len(), 3);
+    for (pkt, frame) in pkts.iter().zip(&frames) {
+        assert_eq!(pkt.data(), &frame[..]);
+    }
+    txq.tx(&mut pkts);
+    assert!(pkts.is_empty());
+    drop(record_cb);
+    assert_eq!(port.get_stat().opackets, 3);
+
+    let mut reader = PcapReader::new(File::open(&tx_path).unwrap()).unwrap();
+    for frame in &frames {
+        assert_eq!(reader.next_frame().unwrap().unwrap().data, &frame[..]);
+    }
+    assert!(reader.next_frame().unwrap().is_none());
+
+    // Read the recording back into packets.
+    let recorded = Arc::try_unwrap(recorded)
+        .unwrap()
+        .into_inner()
+        .unwrap()
+        .into_inner();
+    let mut reader = PcapReader::new(&recorded[..]).unwrap();
+    assert_eq!(reader.format(), PcapFormat::PcapNg);
+    let mut pkts = ArrayVec::<Packet<'_, ()>, 2>::new();
+    assert_eq!(reader.read_burst(f.pool, &mut pkts).unwrap(), 2);
+    assert_eq!(pkts[1].data(), &frames[1][..]);
+    pkts.clear();
+    assert_eq!(reader.read_burst(f.pool, &mut pkts).unwrap(), 1);
+    assert_eq!(pkts[0].data(), &frames[2][..]);
+    assert_eq!(reader.read_burst(f.pool, &mut pkts).unwrap(), 0);
+
+    // Interfaces cannot be combined with other queues.
+    let conf = PcapPortConf {
+        iface: Some("eth0".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(conf.devargs().unwrap(), "iface=eth0");
+    let conf = PcapPortConf {
+        tx: vec![PcapTx::Iface("eth1".to_string())],
+        ..conf
+    };
+    assert!(conf.devargs().is_err());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Ethernet frame of `len` bytes from 02:00:00:00:00:01 carrying an IPv4 packet from 10.0.0.1 to
+/// 10.0.0.2, with TCP/UDP ports.
+fn ipv4_frame(proto: u8, src_port: u16, dst_port: u16, len: usize) -> Vec<u8> {
+    let mut frame = vec![0u8; len];
+    frame[6..12].copy_from_slice(&[2, 0, 0, 0, 0, 1]);
+    frame[12..14].copy_from_slice(&0x0800u16.to_be_bytes());
+    frame[14] = 0x45;
+    frame[23] = proto;
+    frame[26..30].copy_from_slice(&[10, 0, 0, 1]);
+    frame[30..34].copy_from_slice(&[10, 0, 0, 2]);
+    frame[34..36].copy_from_slice(&src_port.to_be_bytes());
+    frame[36..38].copy_from_slice(&dst_port.to_be_bytes());
+    frame
+}
+
+fn capture_queues(f: &Fixture<'_>) {
+    let path = env::temp_dir().join(format!("dpdk_capture_test_{}.pcapng", process::id()));
+    let writer = PcapWriter::new(
+        BufWriter::new(File::create(&path).unwrap()),
+        PcapFormat::PcapNg,
+    )
+    .unwrap();
+    let conf = CaptureConf {
+        snap_len: 100,
+        filter: Some("udp port 53".parse().unwrap()),
+        ring_size: 63,
+        pool_size: 127,
+    };
+    let capture = f.eal.create_capture("test_capture", writer, conf).unwrap();
+    capture.add_rx(&f.loop_rxq).unwrap();
+    capture.add_tx(&f.loop_txq.borrow()).unwrap();
+
+    let frames = [
+        ipv4_frame(17, 1000, 53, 200),
+        ipv4_frame(6, 1000, 80, 200),
+        ipv4_frame(17, 53, 1000, 60),
+    ];
+    let forward = |frames: &[Vec<u8>]| {
+        let mut pkts = frames
+            .iter()
+            .map(|frame| {
+                let mut pkt = f.pool.alloc().unwrap();
+                pkt.append(frame.len());
+                pkt.data_mut().copy_from_slice(frame);
+                pkt
+            })
+            .collect::<ArrayVec<_, 4>>();
+        f.loop_txq.borrow_mut().tx(&mut pkts);
+        assert!(pkts.is_empty());
+        let mut received = ArrayVec::<Packet<'_, ()>, DEFAULT_RX_BURST>::new();
+        f.loop_rxq.rx(&mut received);
+        assert_eq!(received.len(), frames.len());
+    };
+
+    // Both directions are filtered.
+    forward(&frames);
+    let stats = capture.stats();
+    assert_eq!((stats.captured, stats.filtered, stats.dropped), (4, 2, 0));
+
+    // Paused captures and removed ports copy nothing.
+    capture.set_filter(None);
+    capture.pause();
+    forward(&frames[1..2]);
+    capture.resume();
+    forward(&frames[1..2]);
+    assert_eq!(capture.stats().captured, 6);
+    capture.remove_port(f.loop_port.port_id());
+    forward(&frames[1..2]);
+
+    let stats = capture.stop().unwrap();
+    assert_eq!((stats.captured, stats.written), (6, 6));
+
+    let mut reader = PcapReader::new(File::open(&path).unwrap()).unwrap();
+    let expected = [
+        (&frames[0], PcapDirection::Outbound),
+        (&frames[2], PcapDirection::Outbound),
+        (&frames[0], PcapDirection::Inbound),
+        (&frames[2], PcapDirection::Inbound),
+        (&frames[1], PcapDirection::Outbound),
+        (&frames[1], PcapDirection::Inbound),
+    ];
+    for (frame, direction) in &expected {
+        let record = reader.next_frame().unwrap().unwrap();
+        let len = frame.len().min(100);
+        assert_eq!(record.data, &frame[..len]);
+        assert_eq!(record.orig_len, frame.len());
+        assert_eq!(record.info.interface, 1);
+        assert_eq!(record.info.direction, Some(*direction));
+        assert_eq!(record.info.queue, Some(0));
+    }
+    assert!(reader.next_frame().unwrap().is_none());
+    fs::remove_file(&path).unwrap();
+}
+
+/// Request of the `multi_process` test, answered with the sum of its numbers.
+#[derive(Debug)]
+struct SumRequest(Vec<u32>);
+
+impl MpMessage for SumRequest {
+    fn encode(&self, param: &mut Vec<u8>) {
+        for x in &self.0 {
+            param.extend_from_slice(&x.to_le_bytes());
+        }
+    }
+
+    fn decode(param: &[u8]) -> Option<Self> {
+        if !param.len().is_multiple_of(4) {
+            return None;
+        }
+        let numbers = param.chunks_exact(4);
+        let numbers = numbers.map(|x| u32::from_le_bytes(x.try_into().unwrap()));
+        Some(Self(numbers.collect()))
+    }
+}
+
+impl MpRequest for SumRequest {
+    const NAME: &'static str = "test_sum";
+    type Reply = String;
+}
+
+fn multi_process(f: &Fixture<'_>) {
+    struct Meta {
+        _flow_id: u64,
+    }
+    unsafe impl Zeroable for Meta {}
+
+    assert_eq!(f.eal.process_type(), ProcessType::Primary);
+
+    // Pools, rings and ports are attached by name, and left to their owner.
+    // Safety: "test_pool" is a packet pool.
+    let pool = unsafe { f.eal.attach_mpool::<_, ()>("test_pool") }.unwrap();
+    let pkt = pool.alloc().unwrap();
+    assert_eq!(f.pool.in_use(), 1);
+    drop(pkt);
+    drop(pool);
+    assert_eq!(f.pool.available(), TEST_POOL_SIZE);
+    assert!(unsafe { f.eal.attach_mpool::<_, Meta>("test_pool") }.is_err());
+    assert!(unsafe { f.eal.attach_mpool::<_, ()>("test_no_pool") }.is_err());
+    // The created pool is still found by name.
+    assert_eq!(
+        MPool::<()>::lookup(&f.eal, "test_pool")
+            .unwrap()
+            .available(),
+        TEST_POOL_SIZE
+    );
+
+    // Pools created by another process are found by name once attached.
+    let pool_name = CString::new("test_mp_pool").unwrap();
+    // Safety: foreign function.
+    let raw_pool =
+        unsafe { dpdk_sys::rte_pktmbuf_pool_create(pool_name.as_ptr(), 7, 0, 0, 2048, 0) };
+    assert!(!raw_pool.is_null());
+    assert!(MPool::<()>::lookup(&f.eal, "test_mp_pool").is_err());
+    // Safety: "test_mp_pool" is a packet pool.
+    let pool = unsafe { f.eal.attach_mpool::<_, ()>("test_mp_pool") }.unwrap();
+    assert!(MPool::<()>::lookup(&f.eal, "test_mp_pool").is_ok());
+    drop(pool);
+    assert!(MPool::<()>::lookup(&f.eal, "test_mp_pool").is_err());
+    // Safety: no packets of the pool are in use.
+    unsafe { dpdk_sys::rte_mempool_free(raw_pool) };
+
+    let ring: Ring<()> = f.eal.create_ring("test_mp_ring", 15, None).unwrap();
+    // Safety: the ring carries packets of pools without private data.
+    let attached = unsafe { f.eal.attach_ring::<_, ()>("test_mp_ring") }.unwrap();
+    let mut pkts = alloc_packets::<2>(f.pool, 2, 64, 0);
+    assert_eq!(attached.enqueue(&mut pkts), 2);
+    drop(attached);
+    assert_eq!(ring.len(), 2);
+    drop(ring);
+    assert_eq!(f.pool.available(), TEST_POOL_SIZE);
+    assert!(unsafe { f.eal.attach_ring::<_, ()>("test_no_ring") }.is_err());
+
+    // Attached ports only give statistics and link status: their queues belong to the primary.
+    let port = f.eal.attach_port("net_ring0").unwrap();
+    assert_eq!(port.port_id(), f.loop_port.port_id());
+    let mut pkts = alloc_packets::<4>(f.pool, 4, 64, 0);
+    f.loop_txq.borrow_mut().tx(&mut pkts);
+    let mut received = ArrayVec::<Packet<'_, ()>, DEFAULT_RX_BURST>::new();
+    f.loop_rxq.rx(&mut received);
+    assert_eq!(received.len(), 4);
+    drop(received);
+    let sent = f.loop_port.get_stat().opackets;
+    assert_eq!(port.get_stat().opackets, sent);
+    // Statistics are reset only for the attached handle.
+    port.reset_stat();
+    assert_eq!(port.get_stat().opackets, 0);
+    assert_eq!(f.loop_port.get_stat().opackets, sent);
+    drop(port);
+    assert!(f.loop_port.is_link_up());
+    assert!(f.eal.attach_port("net_no_port").is_err());
+
+    let enotsup: ErrorCode = dpdk_sys::ENOTSUP.try_into().unwrap();
+    let sum = |req: SumRequest| req.0.iter().sum::<u32>().to_string();
+    let action = match f.eal.register_mp_action(sum) {
+        Ok(action) => action,
+        // DPDK disables IPC with `--no-shconf`.
+        Err(err) if err == enotsup => return,
+        Err(err) => panic!("register_mp_action: {}", err),
+    };
+    assert!(f
+        .eal
+        .register_mp_action(|_: SumRequest| String::new())
+        .is_err());
+
+    let timeout = Duration::from_secs(1);
+    // Requests must fit into a message.
+    assert!(f.eal.mp_request(&SumRequest(vec![0; 65]), timeout).is_err());
+
+    // Only the mock backend answers requests with the actions of the process itself; DPDK sends
+    // them to secondary processes, and there are none.
+    #[cfg(feature = "mock")]
+    {
+        let request = SumRequest(vec![1, 2, 3]);
+        assert_eq!(f.eal.mp_request(&request, timeout).unwrap(), ["6"]);
+        drop(action);
+        let replies = f.eal.mp_request(&SumRequest(vec![1]), timeout).unwrap();
+        assert!(replies.is_empty());
+    }
+    #[cfg(not(feature = "mock"))]
+    drop(action);
+}
+
+/// Buffer a packet, and return the number of sent and unsent packets.
+///
+/// Unsent packets are returned as a draining iterator borrowing `tx_buf`, thus only count them.
+fn tx<'pool>(
+    tx_buf: &mut TxBuffer<'pool, (), 4>,
+    txq: &mut TxQ<'pool>,
+    pkt: Packet<'pool, ()>,
+) -> (usize, Option<usize>) {
+    let (sent, unsent) = tx_buf.tx(txq, pkt);
+    (sent, unsent.map(|unsent| unsent.len()))
+}
+
+fn tx_buffer_flush(f: &Fixture<'_>) {
+    let ring_port = f
+        .eal
+        .create_ring_port::<_, ()>("net_ring_txbuf", 1, 1, 7)
+        .unwrap();
+    let tx_ring = ring_port.tx_rings[0].clone();
+    assert_eq!(tx_ring.capacity(), 7);
+    let (_port, _rxq, txq) = start_port(ring_port.port);
+    let mut txq: TxQ<'_> = txq;
+
+    let mut tx_buf: TxBuffer<'_, (), 4> = TxBuffer::new();
+    let mut pkts = alloc_packets::<8>(f.pool, 8, 64, 0).into_iter();
+
+    // Nothing is sent until the buffer becomes full.
+    for pkt in pkts.by_ref().take(3) {
+        assert_eq!(tx(&mut tx_buf, &mut txq, pkt), (0, None));
+    }
+    assert!(tx_ring.is_empty());
+    assert_eq!(tx(&mut tx_buf, &mut txq, pkts.next().unwrap()), (4, None));
+    assert_eq!(tx_ring.len(), 4);
+
+    // Flushing an empty buffer is a no-op.
+    let (sent, unsent) = tx_buf.flush(&mut txq);
+    assert_eq!(sent, 0);
+    assert!(unsent.is_none());
+    drop(unsent);
+
+    // The ring has room for 3 more packets, the 4th one is returned.
+    for pkt in pkts.by_ref().take(3) {
+        tx(&mut tx_buf, &mut txq, pkt);
+    }
+    assert_eq!(
+        tx(&mut tx_buf, &mut txq, pkts.next().unwrap()),
+        (3, Some(1))
+    );
+
+    assert_eq!(drain_ring(&tx_ring), 7);
+}
+
+fn ring_port_inject_extract(f: &Fixture<'_>) {
+    let ring_port = f
+        .eal
+        .create_ring_port::<_, ()>("net_ring_inject", 1, 1, 63)
+        .unwrap();
+    let rx_ring = ring_port.rx_rings[0].clone();
+    let tx_ring = ring_port.tx_rings[0].clone();
+    let (port, rxq, txq) = start_port(ring_port.port);
+    let mut txq: TxQ<'_> = txq;
+
+    // Injected packets are received from the port.
+    let mut pkts = alloc_packets::<16>(f.pool, 16, 128, 0x11);
+    assert_eq!(rx_ring.enqueue(&mut pkts), 16);
+    let mut received = ArrayVec::<Packet<'_, ()>, DEFAULT_RX_BURST>::new();
+    rxq.rx(&mut received);
+    assert_eq!(received.len(), 16);
+    assert!(rx_ring.is_empty());
+
+    // Transmitted packets are extracted from the ring.
+    let mut to_send = alloc_packets::<16>(f.pool, 16, 256, 0x22);
+    txq.tx(&mut to_send);
+    assert!(to_send.is_empty());
+    let mut extracted = ArrayVec::<Packet<'_, ()>, DEFAULT_RX_BURST>::new();
+    assert_eq!(tx_ring.dequeue(&mut extracted), 16);
+    assert!(extracted
+        .iter()
+        .all(|pkt| pkt.len() == 256 && pkt.data()[0] == 0x22));
+
+    let stat = port.get_stat();
+    assert_eq!(stat.ipackets, 16);
+    assert_eq!(stat.opackets, 16);
+
+    // The device is removed with the port, before the rings are freed.
+    drop((received, extracted));
+    drop((rxq, txq, port));
+    assert!(f.eal.attach_port("net_ring_inject").is_err());
+    drop((rx_ring, tx_ring, ring_port.rx_rings, ring_port.tx_rings));
+    // Unused ports remove their device too, and the name can be reused.
+    let ring_port = f
+        .eal
+        .create_ring_port::<_, ()>("net_ring_inject", 1, 1, 63)
+        .unwrap();
+    drop(ring_port);
+    assert!(f.eal.attach_port("net_ring_inject").is_err());
+}
+
+fn stats_reset(f: &Fixture<'_>) {
+    let mut pkts = alloc_packets::<4>(f.pool, 4, 64, 0);
+    f.loop_txq.borrow_mut().tx(&mut pkts);
+    let mut received = ArrayVec::<Packet<'_, ()>, DEFAULT_RX_BURST>::new();
+    f.loop_rxq.rx(&mut received);
+    assert!(f.loop_port.get_stat().opackets > 0);
+
+    // Either hardware reset or software emulation must report zeroed counters.
+    f.loop_port.reset_stat();
+    let stat = f.loop_port.get_stat();
+    assert_eq!(stat.ipackets, 0);
+    assert_eq!(stat.opackets, 0);
+    assert_eq!(stat.ibytes, 0);
+    assert_eq!(stat.obytes, 0);
+}
+
+fn deferred_mempool_gc(f: &Fixture<'_>) {
+    let ring: Ring<()> = f.eal.create_ring("test_gc_ring", 15, None).unwrap();
+    {
+        let pool: MPool<()> = f
+            .eal
+            .create_mpool("test_gc_pool", 63, 0, DEFAULT_PACKET_DATA_LENGTH, None)
+            .unwrap();
+        let mut pkts = alloc_packets::<1>(&pool, 1, 64, 0);
+        assert_eq!(ring.enqueue(&mut pkts), 1);
+        // The pool is dropped with an outstanding mbuf, thus it is freed later by `Eal`.
+    }
+    assert_eq!(ring.len(), 1);
+    assert_eq!(f.eal.collect_garbage(), 0);
+    assert_eq!(
+        f.eal.pending_garbage(),
+        vec![PendingGarbage {
+            name: "test_gc_pool".into(),
+            in_use: 1,
+        }]
+    );
+
+    // Freeing the last mbuf makes the deferred request collectable.
+    assert_eq!(drain_ring(&ring), 1);
+    assert_eq!(f.eal.collect_garbage(), 1);
+    assert!(f.eal.pending_garbage().is_empty());
+}
+
+fn garbage_collector(f: &Fixture<'_>) {
+    let collector = f.eal.spawn_garbage_collector(Duration::from_millis(1));
+    let ring: Ring<()> = f.eal.create_ring("test_gc_ring2", 15, None).unwrap();
+    {
+        let pool: MPool<()> = f
+            .eal
+            .create_mpool("test_gc_pool2", 63, 0, DEFAULT_PACKET_DATA_LENGTH, None)
+            .unwrap();
+        let mut pkts = alloc_packets::<1>(&pool, 1, 64, 0);
+        assert_eq!(ring.enqueue(&mut pkts), 1);
+    }
+    assert_eq!(f.eal.pending_garbage().len(), 1);
+
+    assert_eq!(drain_ring(&ring), 1);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !f.eal.pending_garbage().is_empty() {
+        assert!(Instant::now() < deadline, "garbage is not collected");
+        thread::sleep(Duration::from_millis(1));
+    }
+    drop(collector);
+}
+
+/// Named test case run against the shared fixture.
+type TestCase = (&'static str, fn(&Fixture<'_>));
+
+fn main() {
+    simple_logger::SimpleLogger::new()
+        .with_level(log::LevelFilter::Warn)
+        .init()
+        .unwrap();
+
+    let mut args: Vec<String> = env::args().take(1).collect();
+    args.extend(EAL_ARGS.iter().map(|arg| arg.to_string()));
+    let eal = Eal::new(&mut args).expect("EAL initialization failed");
+
+    let mut ports = eal.ports().unwrap();
+    assert_eq!(ports.len(), 2, "expected net_null0 and net_ring0");
+    let (loop_port, loop_rxq, loop_txq) = start_port(ports.pop().unwrap());
+    let (null_port, null_rxq, null_txq) = start_port(ports.pop().unwrap());
+    let pool = eal
+        .create_mpool(
+            "test_pool",
+            TEST_POOL_SIZE,
+            0,
+            DEFAULT_PACKET_DATA_LENGTH,
+            None,
+        )
+        .unwrap();
+
+    let fixture = Fixture {
+        eal,
+        pool: &pool,
+        null_port,
+        null_rxq,
+        null_txq: RefCell::new(null_txq),
+        loop_port,
+        loop_rxq,
+        loop_txq: RefCell::new(loop_txq),
+    };
+
+    let cases: &[TestCase] = &[
+        ("mempool_alloc_free", mempool_alloc_free),
+        ("mempool_lookup_counters", mempool_lookup_counters),
+        ("object_pool", object_pool),
+        ("packet_head_tail", packet_head_tail),
+        ("null_rx_tx", null_rx_tx),
+        ("loopback_rx_tx", loopback_rx_tx),
+        ("tx_cloned_refcount", tx_cloned_refcount),
+        ("packet_clone_copy", packet_clone_copy),
+        ("extbuf_attach", extbuf_attach),
+        ("queue_callbacks", queue_callbacks),
+        ("rx_interrupts", rx_interrupts),
+        #[cfg(feature = "async")]
+        ("async_rx_tx", async_rx_tx),
+        #[cfg(feature = "async")]
+        ("async_rx_interrupts", async_rx_interrupts),
+        #[cfg(feature = "smoltcp")]
+        ("smoltcp_device", smoltcp_device),
+        ("pcap_replay_record", pcap_replay_record),
+        ("capture_queues", capture_queues),
+        ("multi_process", multi_process),
+        ("tx_buffer_flush", tx_buffer_flush),
+        ("ring_port_inject_extract", ring_port_inject_extract),
+        ("stats_reset", stats_reset),
+        ("deferred_mempool_gc", deferred_mempool_gc),
+        ("garbage_collector", garbage_collector),
+    ];
+
+    let mut failed = Vec::new();
+    for (name, case) in cases {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| case(&fixture)));
+        match result {
+            Ok(()) => println!("test {} ... ok", name),
+            Err(_) => {
+                println!("test {} ... FAILED", name);
+                failed.push(*name);
+            }
+        }
+    }
+
+    // Drop everything to run deferred mempool collection and EAL cleanup.
+    drop(fixture);
+    drop(pool);
+
+    println!(
+        "\ntest result: {}. {} passed; {} failed",
+        if failed.is_empty() { "ok" } else { "FAILED" },
+        cases.len() - failed.len(),
+        failed.len()
+    );
+    if !failed.is_empty() {
+        process::exit(1);
+    }
+}
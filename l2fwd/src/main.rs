@@ -1,6 +1,8 @@
 use anyhow::Context;
 use dpdk::arrayvec::ArrayVec;
+use dpdk::capture::{Capture, CaptureConf, Filter};
 use dpdk::eal::{self, Eal, LCoreId, Port, TxQ};
+use dpdk::pcap::{PcapFormat, PcapWriter};
 use dpdk::tx_buffer::TxBuffer;
 use log::{info, warn};
 use smoltcp::wire::{EthernetAddress, EthernetFrame};
@@ -8,6 +10,10 @@ use structopt::StructOpt;
 
 use dpdk::eal::EalGlobalApi;
 use std::env;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 mod utils;
 
@@ -30,6 +36,18 @@ struct Opt {
     /// statistics refresh period in seconds, 0 to disable
     #[structopt(short = "T", long, default_value = "10", name = "PERIOD")]
     stats_period: u32,
+
+    /// capture forwarded packets of all ports into a pcapng file
+    #[structopt(long, name = "FILE")]
+    capture: Option<PathBuf>,
+
+    /// capture only packets matching a tcpdump-like filter, e.g. "udp port 53"
+    #[structopt(long, name = "FILTER", requires = "FILE")]
+    capture_filter: Option<Filter>,
+
+    /// maximum number of bytes captured per packet [default: 2048]
+    #[structopt(long, name = "SNAPLEN", requires = "FILE")]
+    snaplen: Option<u32>,
 }
 
 fn main() -> anyhow::Result<()> {
@@ -68,6 +86,11 @@ fn main() -> anyhow::Result<()> {
     anyhow::ensure!(!ports.is_empty(), "no enabled ports");
     info!("{} enabled lcores and {} ports", lcores.len(), ports.len());
 
+    let capture = match &opt.capture {
+        Some(path) => Some(start_capture(&eal, &opt, path, &portswq)?),
+        None => None,
+    };
+
     let fwds = pair_ports(portswq);
     let assigned_fwds = assign_work(lcores, fwds, &opt);
 
@@ -77,17 +100,67 @@ fn main() -> anyhow::Result<()> {
             .with_context(|| format!("starting port {}", port.port_id()))?;
     }
 
+    // Stop forwarding on SIGINT and SIGTERM, so that the capture file is completed.
+    let handler = handle_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    // Safety: foreign function. The handler only stores to an atomic, which is async-signal-safe.
+    unsafe {
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGTERM, handler);
+    }
     dpdk::thread::scope(|scope| {
         for (lcore, fwds) in assigned_fwds {
-            lcore.launch(scope, |id| forward_loop(&eal, id, fwds));
+            lcore.launch(scope, |id| forward_loop(&eal, id, fwds, &STOP));
         }
     })
     .map_err(|err| anyhow::anyhow!("{:?}", err))
     .context("lcore failed")?;
 
+    if let Some(capture) = capture {
+        let stats = capture.stop().context("writing capture")?;
+        info!("capture: {:?}", stats);
+    }
+
     Ok(())
 }
 
+/// Set by SIGINT and SIGTERM to stop forwarding.
+static STOP: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn handle_signal(_signal: libc::c_int) {
+    STOP.store(true, Ordering::Relaxed);
+}
+
+/// Capture both directions of every queue into a pcapng file.
+fn start_capture(
+    eal: &Eal,
+    opt: &Opt,
+    path: &Path,
+    ports: &[PortWithQueues],
+) -> anyhow::Result<Capture> {
+    // Not a `default_value`, which would always trigger `requires`.
+    let snap_len = opt.snaplen.unwrap_or(2048);
+    let file =
+        File::create(path).with_context(|| format!("creating capture file {}", path.display()))?;
+    let writer = PcapWriter::with_snap_len(BufWriter::new(file), PcapFormat::PcapNg, snap_len)
+        .context("writing capture header")?;
+    let conf = CaptureConf {
+        snap_len: snap_len as usize,
+        filter: opt.capture_filter.clone(),
+        ..CaptureConf::default()
+    };
+    let capture = eal
+        .create_capture("l2fwd_capture", writer, conf)
+        .context("creating capture")?;
+    for port in ports {
+        capture
+            .add_rx(&port.rx)
+            .and_then(|()| capture.add_tx(&port.tx))
+            .with_context(|| format!("capturing port {}", port.port.port_id()))?;
+    }
+    info!("capturing to {}", path.display());
+    Ok(capture)
+}
+
 struct ForwardDesc {
     src: RxQ,
     dst: TxQ<'static>,
@@ -152,7 +225,8 @@ fn assign_work(
 const BURST_TX_DRAIN_US: u64 = 100;
 const US_PER_S: u64 = 1000000;
 
-fn forward_loop(eal: &Eal, lcore: LCoreId, fwds: Vec<ForwardDesc>) {
+/// Forward packets of `fwds` until `stop` is set.
+fn forward_loop(eal: &Eal, lcore: LCoreId, fwds: Vec<ForwardDesc>, stop: &AtomicBool) {
     info!("entering main loop on lcore {}", lcore);
     for fwd in &fwds {
         println!(
@@ -190,7 +264,7 @@ fn forward_loop(eal: &Eal, lcore: LCoreId, fwds: Vec<ForwardDesc>) {
     let mut _dropped = 0;
     let mut _recv = 0;
 
-    loop {
+    while !stop.load(Ordering::Relaxed) {
         let cur_tsc = eal.get_tsc_cycles();
         let diff_tsc = cur_tsc - prev_tsc;
         if diff_tsc > drain_tsc {
@@ -244,3 +318,96 @@ fn set_macs(pkt: &mut Packet, src_mac: [u8; 6], dst_mac: [u8; 6]) {
 fn get_fake_dst_mac(port: &Port) -> [u8; 6] {
     [2, 0, 0, 0, 0, port.port_id() as u8]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dpdk::ring::Ring;
+    use std::time::{Duration, Instant};
+
+    /// EAL can be initialized only once per process, thus this is the only test which uses it.
+    const EAL_ARGS: &[&str] = &[
+        "l2fwd",
+        "--no-huge",
+        "--no-pci",
+        "--no-shconf",
+        "-m",
+        "512",
+        "-l",
+        "0",
+        "--log-level=warning",
+    ];
+
+    /// Dequeue packets from `ring` until `count` packets arrived, or a second passed.
+    fn extract(ring: &Ring<PacketMeta>, count: usize) -> Vec<Packet<'_>> {
+        let mut pkts = Vec::new();
+        let mut burst = ArrayVec::<Packet<'_>, MAX_PKT_BURST>::new();
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while pkts.len() < count && Instant::now() < deadline {
+            ring.dequeue(&mut burst);
+            pkts.extend(burst.drain(..));
+        }
+        pkts
+    }
+
+    #[test]
+    fn forward_between_ring_ports() {
+        let mut args: Vec<String> = EAL_ARGS.iter().map(|arg| arg.to_string()).collect();
+        let eal = Eal::new(&mut args).unwrap();
+        let pool = eal
+            .create_mpool("l2fwd_test", 255, 0, eal::DEFAULT_PACKET_DATA_LENGTH, None)
+            .unwrap();
+
+        let mut rx_rings = Vec::new();
+        let mut tx_rings = Vec::new();
+        let mut portswq = Vec::new();
+        for index in 0..2 {
+            let ring_port = eal
+                .create_ring_port(format!("net_ring_l2fwd{}", index), 1, 1, 63)
+                .unwrap();
+            rx_rings.push(ring_port.rx_rings[0].clone());
+            tx_rings.push(ring_port.tx_rings[0].clone());
+            let (port, (rxqs, txqs)) = ring_port.port.init(1, 1, None);
+            port.start().unwrap();
+            portswq.push(PortWithQueues {
+                port,
+                rx: rxqs.into_iter().next().unwrap(),
+                tx: txqs.into_iter().next().unwrap(),
+            });
+        }
+        let ports: Vec<Port> = portswq.iter().map(|p| p.port.clone()).collect();
+        let lcore = eal.lcores()[0];
+        let fwds = pair_ports(portswq);
+
+        let stop = AtomicBool::new(false);
+        dpdk::thread::scope(|scope| {
+            scope.spawn(|_| forward_loop(&eal, lcore, fwds, &stop));
+
+            for (src, dst) in [(0, 1), (1, 0)].iter().copied() {
+                let mut pkts = ArrayVec::<Packet<'_>, MAX_PKT_BURST>::new();
+                for _ in 0..16 {
+                    let mut pkt = pool.alloc().unwrap();
+                    pkt.append(64);
+                    pkt.data_mut().iter_mut().for_each(|b| *b = 0xff);
+                    pkts.push(pkt);
+                }
+                assert_eq!(rx_rings[src].enqueue(&mut pkts), 16);
+
+                // Packets leave the paired port, with its own source and a fake destination MAC.
+                let forwarded = extract(&tx_rings[dst], 16);
+                assert_eq!(forwarded.len(), 16);
+                for pkt in &forwarded {
+                    let eth = EthernetFrame::new_checked(pkt.data()).unwrap();
+                    assert_eq!(eth.src_addr(), EthernetAddress(ports[dst].mac_addr()));
+                    assert_eq!(
+                        eth.dst_addr(),
+                        EthernetAddress(get_fake_dst_mac(&ports[dst]))
+                    );
+                    assert_eq!(pkt.len(), 64);
+                }
+            }
+            stop.store(true, Ordering::Relaxed);
+        })
+        .unwrap();
+    }
+}